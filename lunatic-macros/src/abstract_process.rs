@@ -29,7 +29,7 @@ pub struct AbstractProcessTransformer {
     req_builder_methods: Vec<TokenStream>,
     /// message (message, request, and response) struct definitions
     message_structs: Vec<TokenStream>,
-    /// impl blocks for MessageHandler and RequestHandler
+    /// impl blocks for MessageHandler, RequestHandler and DeferredRequestHandler
     handler_impls: Vec<TokenStream>,
     /// compiler errors
     errors: Vec<TokenStream>,
@@ -88,10 +88,19 @@ impl AbstractProcessTransformer {
                     self.extract_handle_link_trapped(method)
                 }
                 Method(method) if method.has_tag("handle_message") => {
-                    self.extract_handle_message(method);
+                    self.extract_handle_message(method, "handle_message", "");
+                }
+                Method(method) if method.has_tag("handle_cast") => {
+                    self.extract_handle_message(method, "handle_cast", "cast_");
                 }
                 Method(method) if method.has_tag("handle_request") => {
-                    self.extract_handle_request(method);
+                    self.extract_handle_request(method, "handle_request", "");
+                }
+                Method(method) if method.has_tag("handle_call") => {
+                    self.extract_handle_request(method, "handle_call", "call_");
+                }
+                Method(method) if method.has_tag("handle_deferred_request") => {
+                    self.extract_handle_deferred_request(method);
                 }
                 _ => {
                     self.type_impls.skipped_items.push(quote! { #item });
@@ -343,11 +352,15 @@ impl AbstractProcessTransformer {
         });
     }
 
-    fn extract_handle_message(&mut self, method: &syn::ImplItemMethod) {
+    /// `wrapper_prefix` is prepended to the name of the generated client methods.
+    fn extract_handle_message(
+        &mut self,
+        method: &syn::ImplItemMethod,
+        tag: &str,
+        wrapper_prefix: &str,
+    ) {
         let mut method = method.clone();
-        method
-            .attrs
-            .retain(|attr| !attr.path.is_ident("handle_message"));
+        method.attrs.retain(|attr| !attr.path.is_ident(tag));
         let attrs = &method.attrs;
 
         let HandlerComponents {
@@ -358,6 +371,7 @@ impl AbstractProcessTransformer {
             handler_arg_types,
             message_destructuring,
         } = self.parse_handler_input(&method);
+        let wrapper_ident = format_ident!("{}{}", wrapper_prefix, fn_ident);
 
         let ident = &self.impl_type.clone().unwrap();
 
@@ -390,11 +404,11 @@ impl AbstractProcessTransformer {
         });
         self.handler_wrappers.trait_defs.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*);
+            fn #wrapper_ident(&self, #(#handler_args),*);
         });
         self.handler_wrappers.trait_impls.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*) {
+            fn #wrapper_ident(&self, #(#handler_args),*) {
                 use lunatic::process::Message;
                 let msg = #message_type(#arg_phantom #(#handler_arg_names),*);
                 self.send(msg);
@@ -402,7 +416,7 @@ impl AbstractProcessTransformer {
         });
         self.msg_builder_methods.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*) {
+            fn #wrapper_ident(&self, #(#handler_args),*) {
                 use lunatic::process::Message;
                 let msg = #message_type(#arg_phantom #(#handler_arg_names),*);
                 self.process_ref.send_after(msg, self.duration);
@@ -411,11 +425,15 @@ impl AbstractProcessTransformer {
         self.type_impls.skipped_items.push(quote! { #method });
     }
 
-    fn extract_handle_request(&mut self, method: &syn::ImplItemMethod) {
+    /// `wrapper_prefix` is prepended to the name of the generated client methods.
+    fn extract_handle_request(
+        &mut self,
+        method: &syn::ImplItemMethod,
+        tag: &str,
+        wrapper_prefix: &str,
+    ) {
         let mut method = method.clone();
-        method
-            .attrs
-            .retain(|attr| !attr.path.is_ident("handle_request"));
+        method.attrs.retain(|attr| !attr.path.is_ident(tag));
         let attrs = &method.attrs;
 
         let HandlerComponents {
@@ -426,6 +444,7 @@ impl AbstractProcessTransformer {
             handler_arg_types,
             message_destructuring,
         } = self.parse_handler_input(&method);
+        let wrapper_ident = format_ident!("{}{}", wrapper_prefix, fn_ident);
 
         let ident = &self.impl_type.clone().unwrap();
 
@@ -466,11 +485,11 @@ impl AbstractProcessTransformer {
         });
         self.handler_wrappers.trait_defs.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*) -> #response_type;
+            fn #wrapper_ident(&self, #(#handler_args),*) -> #response_type;
         });
         self.handler_wrappers.trait_impls.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*) -> #response_type {
+            fn #wrapper_ident(&self, #(#handler_args),*) -> #response_type {
                 use lunatic::process::Request;
                 let req = #message_type(#arg_phantom #(#handler_arg_names),*);
                 self.request(req)
@@ -478,7 +497,7 @@ impl AbstractProcessTransformer {
         });
         self.req_builder_methods.push(quote! {
             #(#attrs)*
-            fn #fn_ident(&self, #(#handler_args),*) -> Result<#response_type, lunatic::ReceiveError> {
+            fn #wrapper_ident(&self, #(#handler_args),*) -> Result<#response_type, lunatic::ReceiveError> {
                 use lunatic::process::Request;
                 let req = #message_type(#arg_phantom #(#handler_arg_names),*);
                 self.process_ref.request_timeout(req, self.duration)
@@ -487,6 +506,98 @@ impl AbstractProcessTransformer {
         self.type_impls.skipped_items.push(quote! { #method });
     }
 
+    fn extract_handle_deferred_request(&mut self, method: &syn::ImplItemMethod) {
+        let mut method = method.clone();
+        method
+            .attrs
+            .retain(|attr| !attr.path.is_ident("handle_deferred_request"));
+        let attrs = &method.attrs;
+
+        // The last argument is the reply handle and is not part of the request.
+        let mut request_method = method.clone();
+        let response_type = match request_method.sig.inputs.pop().map(|arg| arg.into_value()) {
+            Some(syn::FnArg::Typed(arg)) => reply_handle_response_type(&arg.ty),
+            _ => None,
+        };
+        let response_type = match response_type {
+            Some(response_type) => response_type,
+            None => {
+                let err = syn::Error::new(
+                    method.sig.span(),
+                    "The last argument of a `#[handle_deferred_request]` method must be of type `ReplyHandle<T>`",
+                )
+                .into_compile_error();
+                self.errors.push(err);
+                return;
+            }
+        };
+
+        let HandlerComponents {
+            fn_ident,
+            message_type,
+            handler_args,
+            handler_arg_names,
+            handler_arg_types,
+            message_destructuring,
+        } = self.parse_handler_input(&request_method);
+
+        let ident = &self.impl_type.clone().unwrap();
+
+        let (impl_generics, ty_generics, where_clause) = &self.impl_type_generics.split_for_impl();
+
+        let (msg_phantom, arg_phantom) = if self.impl_type_generics.params.is_empty() {
+            (None, None)
+        } else {
+            (
+                Some(quote! { std::marker::PhantomData #ty_generics, }),
+                Some(quote! { std::marker::PhantomData, }),
+            )
+        };
+
+        self.message_structs.push(quote! {
+            #[derive(serde::Serialize, serde::Deserialize)]
+            struct #message_type #ty_generics (
+                #msg_phantom
+                #(#handler_arg_types),*
+            );
+        });
+        self.handler_impls.push(quote! {
+            #(#attrs)*
+            impl #impl_generics lunatic::process::DeferredRequestHandler<#message_type #ty_generics>
+            for #ident #where_clause {
+                type Response = #response_type;
+                fn handle(
+                    state: &mut Self::State,
+                    message: #message_type #ty_generics,
+                    reply: lunatic::process::ReplyHandle<#response_type>,
+                ) {
+                    state.#fn_ident(#(#message_destructuring,)* reply)
+                }
+            }
+        });
+        self.handler_wrappers.trait_defs.push(quote! {
+            #(#attrs)*
+            fn #fn_ident(&self, #(#handler_args),*) -> #response_type;
+        });
+        self.handler_wrappers.trait_impls.push(quote! {
+            #(#attrs)*
+            fn #fn_ident(&self, #(#handler_args),*) -> #response_type {
+                use lunatic::process::DeferredRequest;
+                let req = #message_type(#arg_phantom #(#handler_arg_names),*);
+                self.deferred_request(req)
+            }
+        });
+        self.req_builder_methods.push(quote! {
+            #(#attrs)*
+            fn #fn_ident(&self, #(#handler_args),*) -> Result<#response_type, lunatic::ReceiveError> {
+                use lunatic::process::DeferredRequest;
+                let req = #message_type(#arg_phantom #(#handler_arg_names),*);
+                self.process_ref.deferred_request_timeout(req, self.duration)
+            }
+        });
+        self.type_impls.skipped_items.push(quote! { #method });
+    }
+
     fn parse_handler_input(&self, item: &syn::ImplItemMethod) -> HandlerComponents {
         let sig = &item.sig;
         let fn_ident = &sig.ident;
@@ -621,6 +732,24 @@ struct HandlerWrappers {
     trait_impls: Vec<TokenStream>,
 }
 
+/// Extracts `T` from a `ReplyHandle<T>` type.
+fn reply_handle_response_type(ty: &syn::Type) -> Option<syn::Type> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    if segment.ident != "ReplyHandle" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(ty) => Some(ty.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 trait HasTag {
     fn has_tag(&self, tag: &str) -> bool;
 }
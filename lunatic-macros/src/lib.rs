@@ -4,6 +4,7 @@ use proc_macro::TokenStream;
 use quote::quote;
 
 mod abstract_process;
+mod message_enum;
 use abstract_process::AbstractProcessTransformer;
 
 /// Marks the main function to be executed by the lunatic runtime as the root process.
@@ -54,6 +55,14 @@ pub fn main(_args: TokenStream, item: TokenStream) -> TokenStream {
 /// specify methods for implementing [`AbstractProcess`].
 /// - Use `#[handle_message]` and `#[handle_request]` attributes to specify
 /// message and request handlers.
+/// - Use `#[handle_deferred_request]` for request handlers replying later through a `ReplyHandle`.
+/// - Use `#[handle_cast]` and `#[handle_call]` instead of `#[handle_message]` and
+///   `#[handle_request]` to prefix the generated client methods with `cast_` (fire and forget)
+///   and `call_` (waits for the reply), e.g. `counter.cast_increment()` and
+///   `counter.call_count()`.
+///
+/// The last argument of a deferred request handler must be of type `ReplyHandle<T>`, where `T` is
+/// the response type.
 ///
 /// Specifying message types is unnecessary because the macro will create wrapper
 /// types for messages on all handlers. Handlers can take arbitrary number of
@@ -179,6 +188,69 @@ pub fn abstract_process(args: TokenStream, item: TokenStream) -> TokenStream {
     }
 }
 
+/// Derives the dispatching of messages for an enum of messages.
+///
+/// A process that accepts different kinds of messages usually receives an enum and matches on
+/// it. The derive generates a handler trait, named after the enum with a `Handler` suffix, with
+/// one method per variant. The methods are named after the variants in snake case and take the
+/// fields of the variant as arguments. A received message can be passed to the matching method
+/// with [`Mailbox::receive_dispatch`](../lunatic/struct.Mailbox.html#method.receive_dispatch).
+///
+/// For each variant with a single unnamed field, a `From` implementation is generated, so that
+/// the field can be turned into a message with `into()`. Variants with the same field type are
+/// skipped, because the conversion would be ambiguous.
+///
+/// # Example
+///
+/// ```ignore
+/// use lunatic::{Mailbox, MessageEnum};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, MessageEnum)]
+/// enum Command {
+///     Add(u32),
+///     Rename { name: String },
+///     Reset,
+/// }
+///
+/// struct Counter {
+///     name: String,
+///     count: u32,
+/// }
+///
+/// impl CommandHandler for Counter {
+///     fn add(&mut self, value: u32) {
+///         self.count += value;
+///     }
+///
+///     fn rename(&mut self, name: String) {
+///         self.name = name;
+///     }
+///
+///     fn reset(&mut self) {
+///         self.count = 0;
+///     }
+/// }
+///
+/// #[lunatic::main]
+/// fn main(mailbox: Mailbox<Command>) {
+///     mailbox.this().send(5.into());
+///     let mut counter = Counter { name: "counter".to_owned(), count: 0 };
+///     mailbox.receive_dispatch(&mut counter);
+///     assert_eq!(counter.count, 5);
+/// }
+/// ```
+#[proc_macro_derive(MessageEnum)]
+pub fn message_enum(item: TokenStream) -> TokenStream {
+    match syn::parse(item) {
+        Ok(input) => match message_enum::derive(input) {
+            Ok(tokens) => tokens.into(),
+            Err(e) => e.into_compile_error().into(),
+        },
+        Err(e) => e.into_compile_error().into(),
+    }
+}
+
 fn token_stream_with_error(mut tokens: TokenStream, error: syn::Error) -> TokenStream {
     tokens.extend(TokenStream::from(error.into_compile_error()));
     tokens
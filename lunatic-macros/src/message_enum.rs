@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{parse_quote, spanned::Spanned, Fields};
+
+/// Expand the `MessageEnum` derive
+pub fn derive(input: syn::DeriveInput) -> syn::Result<TokenStream> {
+    let data = match &input.data {
+        syn::Data::Enum(data) => data,
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "`MessageEnum` can only be derived for enums",
+            ))
+        }
+    };
+
+    let vis = &input.vis;
+    let enum_ident = &input.ident;
+    let handler_trait = format_ident!("{}Handler", enum_ident);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Types that are used by multiple single field variants can't be converted unambiguously.
+    let mut single_field_types = HashMap::new();
+    for variant in &data.variants {
+        if let Fields::Unnamed(fields) = &variant.fields {
+            if fields.unnamed.len() == 1 {
+                let ty = &fields.unnamed[0].ty;
+                *single_field_types
+                    .entry(quote!(#ty).to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut handler_methods = Vec::new();
+    let mut dispatch_arms = Vec::new();
+    let mut from_impls = Vec::new();
+    for variant in &data.variants {
+        let variant_ident = &variant.ident;
+        let method = method_ident(variant_ident);
+        let (pattern, params, args) = match &variant.fields {
+            Fields::Named(fields) => {
+                let names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                let types = fields.named.iter().map(|f| &f.ty);
+                (
+                    quote! { { #(#names),* } },
+                    quote! { #(#names: #types),* },
+                    quote! { #(#names),* },
+                )
+            }
+            Fields::Unnamed(fields) => {
+                let names: Vec<_> = if fields.unnamed.len() == 1 {
+                    vec![format_ident!("value")]
+                } else {
+                    (0..fields.unnamed.len())
+                        .map(|i| format_ident!("value_{}", i))
+                        .collect()
+                };
+                let types = fields.unnamed.iter().map(|f| &f.ty);
+                (
+                    quote! { ( #(#names),* ) },
+                    quote! { #(#names: #types),* },
+                    quote! { #(#names),* },
+                )
+            }
+            Fields::Unit => (quote! {}, quote! {}, quote! {}),
+        };
+
+        let doc = format!(
+            "Handles the [`{0}::{1}`]({0}::{1}) message.",
+            enum_ident, variant_ident
+        );
+        handler_methods.push(quote! {
+            #[doc = #doc]
+            fn #method(&mut self, #params);
+        });
+        dispatch_arms.push(quote! {
+            #enum_ident::#variant_ident #pattern => handler.#method(#args),
+        });
+
+        match &variant.fields {
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let ty = &fields.unnamed[0].ty;
+                if single_field_types[&quote!(#ty).to_string()] == 1 {
+                    from_impls.push(quote! {
+                        impl #impl_generics ::std::convert::From<#ty> for #enum_ident #ty_generics
+                        #where_clause
+                        {
+                            fn from(value: #ty) -> Self {
+                                #enum_ident::#variant_ident(value)
+                            }
+                        }
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The handler is an additional generic parameter of the `Dispatch` implementation.
+    let mut dispatch_generics = input.generics.clone();
+    dispatch_generics
+        .params
+        .push(parse_quote!(__H: ?Sized + #handler_trait #ty_generics));
+    let (dispatch_impl_generics, _, _) = dispatch_generics.split_for_impl();
+
+    let handler_doc = format!(
+        "Handler for the messages of [`{0}`], dispatched with `Mailbox::receive_dispatch`.",
+        enum_ident
+    );
+    Ok(quote! {
+        #[doc = #handler_doc]
+        #vis trait #handler_trait #impl_generics #where_clause {
+            #(#handler_methods)*
+        }
+
+        impl #dispatch_impl_generics lunatic::Dispatch<__H> for #enum_ident #ty_generics
+        #where_clause
+        {
+            fn dispatch(self, handler: &mut __H) {
+                match self {
+                    #(#dispatch_arms)*
+                }
+            }
+        }
+
+        #(#from_impls)*
+    })
+}
+
+/// Name of the handler method for the variant, a raw identifier if it's a keyword.
+fn method_ident(variant: &syn::Ident) -> syn::Ident {
+    let name = variant.to_string().to_case(Case::Snake);
+    match syn::parse_str::<syn::Ident>(&name) {
+        Ok(_) => syn::Ident::new(&name, variant.span()),
+        Err(_) => syn::Ident::new_raw(&name, variant.span()),
+    }
+}
@@ -0,0 +1,342 @@
+//! Multi-producer, single-consumer channels between processes.
+//!
+//! A channel is a pair of a [`Sender`] and a [`Receiver`], similar to the channels of `std` or
+//! `tokio`. Values are buffered by a hidden process, that is linked to the process that created
+//! the channel. Both halves can be sent to other processes, and senders can be cloned to have
+//! many processes send into the same channel.
+//!
+//! Contrary to a [`Mailbox`](crate::Mailbox), a channel isn't tied to one process. The receiver
+//! can be handed over to another process and messages sent in the meantime are not lost.
+//!
+//! A [`bounded`] channel holds at most a fixed number of values, senders block until there is
+//! space again. [`channel`] creates an unbounded one.
+//!
+//! The buffer process can't tell if all senders or the receiver are gone, because the halves can
+//! be copied to other processes. It fails together with the process that created the channel,
+//! but keeps running if the creator finishes normally, until the receiver
+//! [`close`](Receiver::close)s the channel.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{channel, Mailbox, Process};
+//!
+//! let (sender, receiver) = channel::channel();
+//! for i in 0..3 {
+//!     Process::spawn((sender.clone(), i), |(sender, i), _: Mailbox<()>| sender.send(i));
+//! }
+//! let mut values: Vec<u64> = (0..3).map(|_| receiver.recv()).collect();
+//! values.sort();
+//! assert_eq!(values, vec![0, 1, 2]);
+//! ```
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    process::{
+        AbstractProcess, DeferredRequest, DeferredRequestHandler, Message, MessageHandler,
+        ProcessRef, ReplyHandle, Request, RequestHandler, StartProcess,
+    },
+    ReceiveError,
+};
+
+/// Creates an unbounded channel.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>)
+where
+    T: Serialize + DeserializeOwned,
+{
+    start(None)
+}
+
+/// Creates a channel that buffers at most `capacity` values.
+///
+/// With a `capacity` of 0, each [`send`](Sender::send) blocks until the value is received.
+pub fn bounded<T>(capacity: usize) -> (Sender<T>, Receiver<T>)
+where
+    T: Serialize + DeserializeOwned,
+{
+    start(Some(capacity))
+}
+
+fn start<T>(capacity: Option<usize>) -> (Sender<T>, Receiver<T>)
+where
+    T: Serialize + DeserializeOwned,
+{
+    let buffer = Buffer::start_link(capacity, None);
+    (
+        Sender {
+            buffer: buffer.clone(),
+        },
+        Receiver { buffer },
+    )
+}
+
+/// The sending half of a channel.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Sender<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    buffer: ProcessRef<Buffer<T>>,
+}
+
+impl<T> Sender<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Sends a value into the channel, blocking while the channel is full.
+    pub fn send(&self, value: T) {
+        self.buffer.deferred_request(Push(value));
+    }
+
+    /// Sends a value into the channel if there is space for it right now.
+    ///
+    /// Returns the value back if the channel is full.
+    pub fn try_send(&self, value: T) -> Result<(), T> {
+        self.buffer.request(TryPush(value))
+    }
+}
+
+impl<T> Clone for Sender<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn clone(&self) -> Self {
+        Sender {
+            buffer: self.buffer.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Sender<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sender")
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+/// The receiving half of a channel.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Receiver<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    buffer: ProcessRef<Buffer<T>>,
+}
+
+impl<T> Receiver<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    /// Blocks until a value is received.
+    pub fn recv(&self) -> T {
+        self.buffer
+            .deferred_request(Recv(None))
+            .expect("no timeout specified")
+    }
+
+    /// Same as [`recv`](Self::recv), but only waits for the duration of timeout.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<T, ReceiveError> {
+        // The buffer replies once the timeout passes, so that no value is lost to a receiver that
+        // stopped waiting.
+        self.buffer
+            .deferred_request(Recv(Some(timeout)))
+            .ok_or(ReceiveError::Timeout)
+    }
+
+    /// Receives a value if one is available right now.
+    pub fn try_recv(&self) -> Option<T> {
+        self.buffer.request(TryRecv)
+    }
+
+    /// Stops the buffer process of the channel.
+    ///
+    /// Values that weren't received yet are dropped. The runtime doesn't report messages sent to
+    /// finished processes, so senders that are waiting or use the channel afterwards block forever.
+    pub fn close(self) {
+        self.buffer.shutdown();
+    }
+}
+
+impl<T> std::fmt::Debug for Receiver<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Receiver")
+            .field("buffer", &self.buffer)
+            .finish()
+    }
+}
+
+/// Process holding the values of a channel.
+struct Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    this: ProcessRef<Buffer<T>>,
+    capacity: Option<usize>,
+    values: VecDeque<T>,
+    // Receivers waiting for a value, with the deadline of their timeout.
+    receivers: VecDeque<(ReplyHandle<Option<T>>, Option<Instant>)>,
+    // Senders waiting for space in the buffer.
+    senders: VecDeque<(ReplyHandle<()>, T)>,
+}
+
+impl<T> Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn has_space(&self) -> bool {
+        match self.capacity {
+            Some(capacity) => self.values.len() < capacity,
+            None => true,
+        }
+    }
+
+    /// Moves values of waiting senders into the buffer, as long as there is space.
+    fn admit(&mut self) {
+        while self.has_space() {
+            match self.senders.pop_front() {
+                Some((reply, value)) => {
+                    self.values.push_back(value);
+                    reply.reply(());
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Takes the next value, directly from a waiting sender if the buffer is empty.
+    fn take(&mut self) -> Option<T> {
+        let value = match self.values.pop_front() {
+            Some(value) => value,
+            None => {
+                let (reply, value) = self.senders.pop_front()?;
+                reply.reply(());
+                value
+            }
+        };
+        self.admit();
+        Some(value)
+    }
+
+    /// Hands out values to waiting receivers and drops the ones that timed out.
+    fn serve(&mut self) {
+        self.admit();
+        let now = Instant::now();
+        for (reply, deadline) in std::mem::take(&mut self.receivers) {
+            match self.take() {
+                Some(value) => reply.reply(Some(value)),
+                None if matches!(deadline, Some(deadline) if deadline <= now) => reply.reply(None),
+                None => self.receivers.push_back((reply, deadline)),
+            }
+        }
+    }
+}
+
+impl<T> AbstractProcess for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Arg = Option<usize>;
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, capacity: Option<usize>) -> Self::State {
+        Buffer {
+            this,
+            capacity,
+            values: VecDeque::new(),
+            receivers: VecDeque::new(),
+            senders: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Push<T>(T);
+impl<T> DeferredRequestHandler<Push<T>> for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Response = ();
+
+    fn handle(state: &mut Self::State, Push(value): Push<T>, reply: ReplyHandle<()>) {
+        state.senders.push_back((reply, value));
+        state.serve();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TryPush<T>(T);
+impl<T> RequestHandler<TryPush<T>> for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Response = Result<(), T>;
+
+    fn handle(state: &mut Self::State, TryPush(value): TryPush<T>) -> Result<(), T> {
+        // A waiting receiver takes the value right away, even if the buffer is full.
+        if !state.has_space() && state.receivers.is_empty() {
+            return Err(value);
+        }
+        state.values.push_back(value);
+        state.serve();
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Recv(Option<Duration>);
+impl<T> DeferredRequestHandler<Recv> for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Response = Option<T>;
+
+    fn handle(state: &mut Self::State, Recv(timeout): Recv, reply: ReplyHandle<Option<T>>) {
+        let deadline = timeout.map(|timeout| {
+            state.this.send_after(Expire, timeout);
+            Instant::now() + timeout
+        });
+        state.receivers.push_back((reply, deadline));
+        state.serve();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TryRecv;
+impl<T> RequestHandler<TryRecv> for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    type Response = Option<T>;
+
+    fn handle(state: &mut Self::State, _: TryRecv) -> Option<T> {
+        // Don't overtake receivers that are already waiting.
+        if !state.receivers.is_empty() {
+            return None;
+        }
+        state.take()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Expire;
+impl<T> MessageHandler<Expire> for Buffer<T>
+where
+    T: Serialize + DeserializeOwned,
+{
+    fn handle(state: &mut Self::State, _: Expire) {
+        state.serve();
+    }
+}
@@ -1,14 +1,49 @@
-use crate::host;
+use std::cell::OnceCell;
+
+use crate::{env, host};
 
 /// Process configurations determine permissions of processes.
 ///
 /// The functions `spawn_config` & `spawn_link_config` can be used to create processes with a
 /// specific configuration.
+///
+/// A new configuration can't spawn processes, compile modules, create configurations or access
+/// the filesystem. These permissions need to be granted with the `set_can_*` methods and
+/// [`preopen_dir`](ProcessConfig::preopen_dir). The runtime doesn't provide a networking
+/// permission, so processes can always open sockets.
+///
+/// Memory and fuel limits can be used to sandbox untrusted or expensive children. A child
+/// exceeding its limits fails, without affecting the parent unless they are linked.
+///
+/// The runtime schedules all processes of a node fairly. Each process runs until it waits for a
+/// message or executed a fixed number of instructions, and there is no way to give a process a
+/// higher priority or a longer slice through its configuration. Latency-critical processes, like
+/// heartbeat responders, can be kept responsive by limiting CPU-bound workers: fuel puts an upper
+/// bound on their total work, [`yield_now`](crate::yield_now) lets them step aside inside long
+/// loops, and spawning them on a separate [node](crate::distributed) isolates them completely.
+///
+/// Each setter also has a chained `with_*` variant, so a configuration can be built in a single
+/// expression. Children that should run with the same configuration as the current process can be
+/// spawned with [`ProcessConfig::inherit`].
+///
+/// # Example
+///
+/// ```
+/// let mut config = ProcessConfig::new();
+/// config.set_max_memory(5_000_000); // ~5 Mb
+/// config.set_max_fuel(10); // ~1M instructions
+/// let task = spawn_link!(@task &config, || (1..=100u64).sum::<u64>());
+/// assert_eq!(task.result(), 5050);
+///
+/// let config = ProcessConfig::default()
+///     .with_max_memory(5_000_000)
+///     .with_can_spawn_processes(true);
+/// ```
 pub struct ProcessConfig(ProcessConfigType);
 
 enum ProcessConfigType {
-    /// ID of a configuration held by the host as a resource.
-    Config(u64),
+    /// ID of a configuration held by the host as a resource, created on first use.
+    Config(OnceCell<u64>),
     /// Indicates that the configuration should be inherited from the parent process.
     Inherit,
 }
@@ -16,7 +51,11 @@ enum ProcessConfigType {
 impl Drop for ProcessConfigType {
     fn drop(&mut self) {
         match self {
-            ProcessConfigType::Config(id) => unsafe { host::api::process::drop_config(*id) },
+            ProcessConfigType::Config(id) => {
+                if let Some(id) = id.get() {
+                    unsafe { host::api::process::drop_config(*id) }
+                }
+            }
             ProcessConfigType::Inherit => (),
         }
     }
@@ -44,33 +83,57 @@ impl ProcessConfig {
     /// There is no memory or fuel limit set on the newly created configuration, they are not
     /// inherited from parent.
     pub fn new() -> Self {
-        let id = unsafe { host::api::process::create_config() };
-        Self(ProcessConfigType::Config(id))
+        let config = Self::default();
+        config.resource();
+        config
     }
 
-    pub(crate) fn inherit() -> Self {
+    /// Returns a configuration that spawns processes with the configuration of the current
+    /// process.
+    ///
+    /// The runtime resolves an inherited configuration when a process is spawned and doesn't
+    /// expose its values, so an inherited configuration can't be read or modified. Calling any
+    /// getter or setter on it panics.
+    pub fn inherit() -> Self {
         Self(ProcessConfigType::Inherit)
     }
 
+    /// Returns `true` if this is an [inherited](ProcessConfig::inherit) configuration.
+    pub fn is_inherited(&self) -> bool {
+        matches!(self.0, ProcessConfigType::Inherit)
+    }
+
     /// Returns the id of the configuration resource or -1 in case it's an inherited configuration.
     pub fn id(&self) -> i64 {
         match self.0 {
-            ProcessConfigType::Config(id) => id as i64,
+            ProcessConfigType::Config(_) => self.resource() as i64,
             ProcessConfigType::Inherit => -1,
         }
     }
 
+    /// Returns the id of the configuration resource, creating it if necessary.
+    fn resource(&self) -> u64 {
+        match &self.0 {
+            ProcessConfigType::Config(id) => {
+                *id.get_or_init(|| unsafe { host::api::process::create_config() })
+            }
+            ProcessConfigType::Inherit => {
+                panic!("an inherited process configuration can't be read or modified")
+            }
+        }
+    }
+
     /// Sets the maximum amount of memory in bytes that can be used by a process.
     ///
     /// If a process tries to allocate more memory with `memory.grow`, the instruction is going to
     /// return -1.
     pub fn set_max_memory(&mut self, max_memory: u64) {
-        unsafe { host::api::process::config_set_max_memory(self.id() as u64, max_memory) };
+        unsafe { host::api::process::config_set_max_memory(self.resource(), max_memory) };
     }
 
     /// Returns the maximum amount of memory in bytes.
     pub fn get_max_memory(&self) -> u64 {
-        unsafe { host::api::process::config_get_max_memory(self.id() as u64) }
+        unsafe { host::api::process::config_get_max_memory(self.resource()) }
     }
 
     /// Sets the maximum amount of fuel available to the process.
@@ -78,22 +141,22 @@ impl ProcessConfig {
     /// One unit of fuel is approximately 100k wasm instructions. If a process runs out of fuel it
     /// will trap.
     pub fn set_max_fuel(&mut self, max_fuel: u64) {
-        unsafe { host::api::process::config_set_max_fuel(self.id() as u64, max_fuel) };
+        unsafe { host::api::process::config_set_max_fuel(self.resource(), max_fuel) };
     }
 
     /// Returns the maximum amount of fuel.
     pub fn get_max_fuel(&self) -> u64 {
-        unsafe { host::api::process::config_get_max_fuel(self.id() as u64) }
+        unsafe { host::api::process::config_get_max_fuel(self.resource()) }
     }
 
     /// Sets the ability of a process to compile WebAssembly modules.
     pub fn set_can_compile_modules(&mut self, can: bool) {
-        unsafe { host::api::process::config_set_can_compile_modules(self.id() as u64, can as u32) };
+        unsafe { host::api::process::config_set_can_compile_modules(self.resource(), can as u32) };
     }
 
     /// Returns true if processes can compile WebAssembly modules.
     pub fn can_compile_modules(&self) -> bool {
-        (unsafe { host::api::process::config_can_compile_modules(self.id() as u64) }) > 0
+        (unsafe { host::api::process::config_can_compile_modules(self.resource()) }) > 0
     }
 
     /// Sets the ability of a process to create their own sub-configuration.
@@ -102,29 +165,29 @@ impl ProcessConfig {
     /// possibility to create new configurations, it can spawn sub-processes using a new config
     /// that has the permission enabled.
     pub fn set_can_create_configs(&mut self, can: bool) {
-        unsafe { host::api::process::config_set_can_create_configs(self.id() as u64, can as u32) };
+        unsafe { host::api::process::config_set_can_create_configs(self.resource(), can as u32) };
     }
 
     /// Returns true if processes can create their own configurations.
     pub fn can_create_configs(&self) -> bool {
-        (unsafe { host::api::process::config_can_create_configs(self.id() as u64) }) > 0
+        (unsafe { host::api::process::config_can_create_configs(self.resource()) }) > 0
     }
 
     /// Sets the ability of a process to spawn sub-processes.
     pub fn set_can_spawn_processes(&mut self, can: bool) {
-        unsafe { host::api::process::config_set_can_spawn_processes(self.id() as u64, can as u32) };
+        unsafe { host::api::process::config_set_can_spawn_processes(self.resource(), can as u32) };
     }
 
     /// Returns true if processes can spawn sub-processes.
     pub fn can_spawn_processes(&self) -> bool {
-        (unsafe { host::api::process::config_can_spawn_processes(self.id() as u64) }) > 0
+        (unsafe { host::api::process::config_can_spawn_processes(self.resource()) }) > 0
     }
 
     /// Adds environment variable.
     pub fn add_environment_variable(&mut self, key: &str, value: &str) {
         unsafe {
             host::api::wasi::config_add_environment_variable(
-                self.id() as u64,
+                self.resource(),
                 key.as_ptr(),
                 key.len(),
                 value.as_ptr(),
@@ -133,11 +196,29 @@ impl ProcessConfig {
         }
     }
 
+    /// Labels the processes spawned with this configuration, e.g. `ws-conn:1234`.
+    ///
+    /// The label can be read by the process with [`env::label`] and is part of its
+    /// [crash reports](crate::supervisor::CrashReport). It's passed as an environment variable,
+    /// so a configuration should only be labeled once.
+    pub fn set_label(&mut self, label: &str) {
+        self.add_environment_variable(env::LABEL_VAR, label);
+    }
+
+    /// Adds the key/value pair `key` and `value` to the metadata of processes spawned with this
+    /// configuration.
+    ///
+    /// The metadata can be read by the process with [`env::metadata`]. Like the
+    /// [label](ProcessConfig::set_label), it's passed as an environment variable.
+    pub fn add_metadata(&mut self, key: &str, value: &str) {
+        self.add_environment_variable(&format!("{}{}", env::METADATA_PREFIX, key), value);
+    }
+
     /// Adds command line argument.
     pub fn add_command_line_argument(&mut self, argument: &str) {
         unsafe {
             host::api::wasi::config_add_command_line_argument(
-                self.id() as u64,
+                self.resource(),
                 argument.as_ptr(),
                 argument.len(),
             )
@@ -145,13 +226,84 @@ impl ProcessConfig {
     }
 
     /// Mark a directory as preopened.
+    ///
+    /// Processes spawned with this configuration can only access files inside of preopened
+    /// directories.
     pub fn preopen_dir(&self, dir: &str) {
-        unsafe { host::api::wasi::config_preopen_dir(self.id() as u64, dir.as_ptr(), dir.len()) }
+        unsafe { host::api::wasi::config_preopen_dir(self.resource(), dir.as_ptr(), dir.len()) }
+    }
+
+    /// Sets the maximum amount of memory in bytes, see
+    /// [`set_max_memory`](ProcessConfig::set_max_memory).
+    pub fn with_max_memory(mut self, max_memory: u64) -> Self {
+        self.set_max_memory(max_memory);
+        self
+    }
+
+    /// Sets the maximum amount of fuel, see [`set_max_fuel`](ProcessConfig::set_max_fuel).
+    pub fn with_max_fuel(mut self, max_fuel: u64) -> Self {
+        self.set_max_fuel(max_fuel);
+        self
+    }
+
+    /// Sets the ability to compile WebAssembly modules, see
+    /// [`set_can_compile_modules`](ProcessConfig::set_can_compile_modules).
+    pub fn with_can_compile_modules(mut self, can: bool) -> Self {
+        self.set_can_compile_modules(can);
+        self
+    }
+
+    /// Sets the ability to create sub-configurations, see
+    /// [`set_can_create_configs`](ProcessConfig::set_can_create_configs).
+    pub fn with_can_create_configs(mut self, can: bool) -> Self {
+        self.set_can_create_configs(can);
+        self
+    }
+
+    /// Sets the ability to spawn sub-processes, see
+    /// [`set_can_spawn_processes`](ProcessConfig::set_can_spawn_processes).
+    pub fn with_can_spawn_processes(mut self, can: bool) -> Self {
+        self.set_can_spawn_processes(can);
+        self
+    }
+
+    /// Adds environment variable, see
+    /// [`add_environment_variable`](ProcessConfig::add_environment_variable).
+    pub fn with_environment_variable(mut self, key: &str, value: &str) -> Self {
+        self.add_environment_variable(key, value);
+        self
+    }
+
+    /// Labels the spawned processes, see [`set_label`](ProcessConfig::set_label).
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.set_label(label);
+        self
+    }
+
+    /// Adds a key/value pair to the metadata, see [`add_metadata`](ProcessConfig::add_metadata).
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.add_metadata(key, value);
+        self
+    }
+
+    /// Adds command line argument, see
+    /// [`add_command_line_argument`](ProcessConfig::add_command_line_argument).
+    pub fn with_command_line_argument(mut self, argument: &str) -> Self {
+        self.add_command_line_argument(argument);
+        self
+    }
+
+    /// Marks a directory as preopened, see [`preopen_dir`](ProcessConfig::preopen_dir).
+    pub fn with_preopened_dir(self, dir: &str) -> Self {
+        self.preopen_dir(dir);
+        self
     }
 }
 
+/// The default configuration has all permissions denied, like [`ProcessConfig::new`], but the
+/// configuration resource is only created once the configuration is used.
 impl Default for ProcessConfig {
     fn default() -> Self {
-        Self::new()
+        Self(ProcessConfigType::Config(OnceCell::new()))
     }
 }
@@ -0,0 +1,107 @@
+//! Observing messages sent to processes that are gone.
+//!
+//! The lunatic runtime silently drops messages sent to processes that finished, failed or never
+//! existed. To make lost messages visible, a process can be set as the dead-letter handler of the
+//! node. Messages sent with [`Process::send_checked`] to a process that is gone are wrapped in a
+//! [`DeadLetter`] and forwarded to the handler instead.
+//!
+//! Handlers are registered per message type, like names in the [`registry`](crate::registry).
+//! Messages sent with [`send`](Process::send) are never checked, because the runtime doesn't
+//! report if a message was delivered. Instead, the first checked message to a process sets up a
+//! [`Monitor`](crate::Monitor) of it, and later messages are forwarded once the monitor reported
+//! the process as gone. The check doesn't block the sender, but messages sent before the monitor
+//! reports the process, like the first one, are still lost.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{dead_letter::{self, DeadLetter}, Mailbox, Process};
+//!
+//! let handler = Process::spawn((), |_, mailbox: Mailbox<DeadLetter<String>>| loop {
+//!     let letter = mailbox.receive();
+//!     println!("Lost message for {:?}: {}", letter.target, letter.message);
+//! });
+//! dead_letter::set_handler(&handler);
+//! ```
+
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{host, registry, serializer::Bincode, Monitor, Process};
+
+/// Name under which the handlers are registered.
+const HANDLER_NAME: &str = "lunatic::dead_letter";
+
+crate::process_local! {
+    /// Monitors of the processes that checked messages were sent to, `None` once they went down.
+    static TARGETS: RefCell<HashMap<(u64, u64), Option<Monitor>>> = RefCell::new(HashMap::new());
+}
+
+/// A message that couldn't be delivered, because the target process is gone.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DeadLetter<M> {
+    /// The process that sent the message.
+    pub sender: Process<()>,
+    /// The process that the message was sent to.
+    pub target: Process<()>,
+    /// The undelivered message.
+    pub message: M,
+}
+
+/// Sets the process receiving undelivered messages of type `M` on this node.
+///
+/// A previously set handler for the same type is replaced.
+pub fn set_handler<M>(handler: &Process<DeadLetter<M>>) {
+    registry::register(HANDLER_NAME, handler);
+}
+
+/// Removes the handler of undelivered messages of type `M`.
+///
+/// Undelivered messages of this type are dropped again afterwards.
+pub fn remove_handler<M>() {
+    registry::unregister::<DeadLetter<M>>(HANDLER_NAME);
+}
+
+/// Returns the process receiving undelivered messages of type `M`.
+pub fn handler<M>() -> Option<Process<DeadLetter<M>>> {
+    registry::lookup(HANDLER_NAME)
+}
+
+/// Forwards `message` to the handler, if one is set.
+pub(crate) fn forward<M, S>(target: &Process<M, S>, message: M)
+where
+    Bincode: crate::serializer::Serializer<DeadLetter<M>>,
+{
+    if let Some(handler) = handler::<M>() {
+        handler.send(DeadLetter {
+            sender: Process::this(),
+            target: Process::new(target.node_id(), target.id()),
+            message,
+        });
+    }
+}
+
+/// Returns `true` if the process was reported as gone, without waiting for the report.
+///
+/// The process is monitored from the first call on, so a process that is already gone is only
+/// reported by later calls.
+pub(crate) fn is_down(node_id: u64, id: u64) -> bool {
+    if node_id == host::node_id() && id == host::process_id() {
+        return false;
+    }
+    TARGETS.with(|targets| {
+        let mut targets = targets.borrow_mut();
+        let target = targets
+            .entry((node_id, id))
+            .or_insert_with(|| Some(Monitor::new(node_id, id)));
+        let down = match target {
+            Some(monitor) => monitor.wait_timeout(Duration::ZERO).is_ok(),
+            None => return true,
+        };
+        if down {
+            *target = None;
+        }
+        down
+    })
+}
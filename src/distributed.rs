@@ -1,16 +1,57 @@
+//! Information about the distributed lunatic nodes.
+//!
+//! If the runtime is started as part of a cluster, processes can be spawned on other nodes with
+//! [`Process::spawn_node`](crate::Process::spawn_node) or
+//! [`StartProcess::start_node`](crate::process::StartProcess::start_node). The returned handles
+//! can be used the same way as handles to local processes, messages are transparently delivered
+//! across nodes.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{distributed, Mailbox, Process};
+//!
+//! let this = distributed::node_id();
+//! // Spawn a process on each remote node.
+//! for node in distributed::nodes().into_iter().filter(|node| *node != this) {
+//!     let process = Process::spawn_node(node, (), |_, mailbox: Mailbox<String>| {
+//!         println!("{}", mailbox.receive());
+//!     });
+//!     process.send(format!("Hello from node {}", this));
+//! }
+//! ```
+//!
+//! Links can't cross nodes, the runtime only links processes running on the same node. A process
+//! on another node can be observed with [`Process::monitor`](crate::Process::monitor) instead,
+//! which also reports [`DownReason::NodeDown`](crate::DownReason::NodeDown) if the node leaves the
+//! cluster.
+//!
+//! Processes can [`subscribe`] to nodes joining and leaving the cluster. The runtime only exposes
+//! the IDs of the nodes, there is no additional metadata (e.g. addresses) available about them.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
 use crate::host::api::{
     self,
     distributed::{get_nodes, module_id, nodes_count},
 };
 use crate::{
     module::{params_to_vec, Param},
-    LunaticError,
+    serializer::Bincode,
+    LunaticError, Mailbox, Process, ReceiveError, Tag,
 };
 
+/// How often the list of nodes is checked for changes by a [`NodeSubscription`].
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Returns the ID of the node the current process is running on.
 pub fn node_id() -> u64 {
     unsafe { api::distributed::node_id() }
 }
 
+/// Returns the IDs of the nodes in the cluster that are known to the current node.
 pub fn nodes() -> Vec<u64> {
     let cnt = unsafe { nodes_count() as usize };
     let mut nodes = vec![0; cnt];
@@ -19,6 +60,11 @@ pub fn nodes() -> Vec<u64> {
     nodes
 }
 
+/// Spawns a process on the node `node_id`, running `entry` with the argument `arg`.
+///
+/// This is a low level function used by [`Process::spawn_node`](crate::Process::spawn_node),
+/// which should be preferred in most cases. The same module as the current one is used for the
+/// new process.
 pub fn spawn(node_id: u64, config_id: i64, entry: fn(i32), arg: i32) -> Result<u64, LunaticError> {
     let entry = entry as usize as i32;
     let params = params_to_vec(&[Param::I32(entry), Param::I32(arg)]);
@@ -39,6 +85,98 @@ pub fn spawn(node_id: u64, config_id: i64, entry: fn(i32), arg: i32) -> Result<u
     if result == 0 {
         Ok(id)
     } else {
-        Err(LunaticError::from(id))
+        Err(LunaticError::NodeSpawnFailed(id))
+    }
+}
+
+/// A change in the membership of the cluster, observed by a [`NodeSubscription`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeEvent {
+    /// The node with this ID joined the cluster.
+    NodeUp(u64),
+    /// The node with this ID left the cluster.
+    NodeDown(u64),
+}
+
+/// Subscribes the current process to nodes joining and leaving the cluster.
+///
+/// Only changes happening after the call are reported, the current nodes can be listed with
+/// [`nodes`].
+///
+/// # Example
+///
+/// ```
+/// use lunatic::distributed::{self, NodeEvent};
+///
+/// let subscription = distributed::subscribe();
+/// match subscription.receive() {
+///     NodeEvent::NodeUp(node) => println!("Node {} joined", node),
+///     NodeEvent::NodeDown(node) => println!("Node {} left", node),
+/// }
+/// subscription.unsubscribe();
+/// ```
+pub fn subscribe() -> NodeSubscription {
+    let tag = Tag::new();
+    let this = Process::<NodeEvent, Bincode>::this();
+    let watcher = Process::spawn((this, tag, nodes()), watcher);
+    NodeSubscription { watcher, tag }
+}
+
+/// A subscription to membership changes of the cluster, created with [`subscribe`].
+///
+/// The runtime doesn't notify processes about membership changes, so the nodes are periodically
+/// checked by a helper process. The events are delivered to the subscribed process as
+/// [`NodeEvent`] messages, tagged with the [`tag`](Self::tag) of the subscription. The helper
+/// process keeps running until the subscription is [`unsubscribe`](Self::unsubscribe)d, even if
+/// the subscribed process finishes in the meantime.
+#[derive(Debug)]
+pub struct NodeSubscription {
+    watcher: Process<(), Bincode>,
+    tag: Tag,
+}
+
+impl NodeSubscription {
+    /// Returns the tag used for the [`NodeEvent`] messages.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Blocks until the next membership change.
+    pub fn receive(&self) -> NodeEvent {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<NodeEvent, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[self.tag]))
+    }
+
+    /// Same as [`receive`](Self::receive), but only waits for the duration of timeout.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<NodeEvent, ReceiveError> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<NodeEvent, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive_timeout(Some(&[self.tag]), timeout)
+    }
+
+    /// Stops the subscription.
+    ///
+    /// Events that were already delivered, but not yet received, stay in the mailbox.
+    pub fn unsubscribe(self) {
+        self.watcher.send(());
+    }
+}
+
+/// Entry point of the helper process that reports changes of the node list.
+fn watcher(
+    (subscriber, tag, mut known): (Process<NodeEvent, Bincode>, Tag, Vec<u64>),
+    mailbox: Mailbox<()>,
+) {
+    // Any untagged message stops the subscription.
+    while let Err(ReceiveError::Timeout) = mailbox.receive_timeout(POLL_INTERVAL) {
+        let current = nodes();
+        for node in current.iter().filter(|node| !known.contains(node)) {
+            subscriber.tag_send(tag, NodeEvent::NodeUp(*node));
+        }
+        for node in known.iter().filter(|node| !current.contains(node)) {
+            subscriber.tag_send(tag, NodeEvent::NodeDown(*node));
+        }
+        known = current;
     }
 }
@@ -0,0 +1,60 @@
+//! Access to the environment of the current process.
+//!
+//! Every lunatic process has its own environment variables and command line arguments. They are
+//! set by the parent at spawn time with
+//! [`ProcessConfig::add_environment_variable`](crate::ProcessConfig::add_environment_variable)
+//! and [`ProcessConfig::add_command_line_argument`](crate::ProcessConfig::add_command_line_argument),
+//! so simple settings don't need to be serialized into the captured value.
+//!
+//! The [label](label) and [metadata](metadata) of a process, set with
+//! [`ProcessConfig::set_label`](crate::ProcessConfig::set_label) and
+//! [`ProcessConfig::add_metadata`](crate::ProcessConfig::add_metadata), are passed the same way.
+//! They help to tell apart processes that only differ by their id, e.g. in logs and
+//! [crash reports](crate::supervisor::CrashReport). The runtime doesn't expose the environment of
+//! other processes, so a process can only read its own label.
+//!
+//! # Example
+//!
+//! ```
+//! let mut config = ProcessConfig::new();
+//! config.add_environment_variable("LOG_LEVEL", "debug");
+//! config.add_command_line_argument("--verbose");
+//!
+//! let task = spawn_link!(@task &config, || {
+//!     assert_eq!(lunatic::env::var("LOG_LEVEL").as_deref(), Some("debug"));
+//!     assert_eq!(lunatic::env::args(), vec!["--verbose".to_owned()]);
+//! });
+//! let _ = task.result();
+//! ```
+
+/// Environment variable holding the label of a process.
+pub(crate) const LABEL_VAR: &str = "LUNATIC_PROCESS_LABEL";
+/// Prefix of the environment variables holding the metadata of a process.
+pub(crate) const METADATA_PREFIX: &str = "LUNATIC_PROCESS_METADATA_";
+
+/// Returns the command line arguments of the current process.
+pub fn args() -> Vec<String> {
+    std::env::args().collect()
+}
+
+/// Returns the value of the environment variable `name`, if it's set to a valid UTF-8 string.
+pub fn var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Returns all environment variables of the current process.
+pub fn vars() -> Vec<(String, String)> {
+    std::env::vars().collect()
+}
+
+/// Returns the label of the current process, if it was spawned with one.
+pub fn label() -> Option<String> {
+    var(LABEL_VAR)
+}
+
+/// Returns the metadata of the current process as key/value pairs.
+pub fn metadata() -> Vec<(String, String)> {
+    std::env::vars()
+        .filter_map(|(name, value)| Some((name.strip_prefix(METADATA_PREFIX)?.to_owned(), value)))
+        .collect()
+}
@@ -7,17 +7,24 @@ use thiserror::Error;
 /// Host calls can have a big number of failure reasons, and it's impossible to enumerate all of
 /// them. This is especially true for calls that involve compiling raw binary data to WebAssembly
 /// modules. Because of this an opaque error ID is returned from host that can be transformed to
-/// a string.
+/// a string. Calls that know why they failed return a more specific variant, and the
+/// [`kind`](LunaticError::kind) of the error can be used to handle them programmatically.
 #[derive(Error)]
 pub enum LunaticError {
     Error(u64),
     PermissionDenied,
+    InvalidModule(u64),
+    SpawnFailed(u64),
+    NodeSpawnFailed(u64),
 }
 
 impl Drop for LunaticError {
     fn drop(&mut self) {
         match self {
-            LunaticError::Error(id) => {
+            LunaticError::Error(id)
+            | LunaticError::InvalidModule(id)
+            | LunaticError::SpawnFailed(id)
+            | LunaticError::NodeSpawnFailed(id) => {
                 unsafe { error::drop(*id) };
             }
             LunaticError::PermissionDenied => (),
@@ -29,34 +36,62 @@ impl LunaticError {
     pub(crate) fn from(id: u64) -> Self {
         LunaticError::Error(id)
     }
-}
 
-impl Debug for LunaticError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+    /// Returns the category of the error.
+    ///
+    /// The host doesn't report why a call failed, so the kind is only known for the calls that
+    /// can tell it from the result, like compiling a module or spawning a process. All other
+    /// errors are of kind [`LunaticErrorKind::Other`].
+    pub fn kind(&self) -> LunaticErrorKind {
         match self {
-            LunaticError::Error(id) => {
+            LunaticError::Error(_) => LunaticErrorKind::Other,
+            LunaticError::PermissionDenied => LunaticErrorKind::PermissionDenied,
+            LunaticError::InvalidModule(_) => LunaticErrorKind::InvalidModule,
+            LunaticError::SpawnFailed(_) => LunaticErrorKind::SpawnFailed,
+            LunaticError::NodeSpawnFailed(_) => LunaticErrorKind::NodeSpawnFailed,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            LunaticError::Error(id)
+            | LunaticError::InvalidModule(id)
+            | LunaticError::SpawnFailed(id)
+            | LunaticError::NodeSpawnFailed(id) => {
                 let size = unsafe { error::string_size(*id) };
                 let mut buff = vec![0; size as usize];
                 unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                let error = std::str::from_utf8(&buff).unwrap();
-                write!(f, "{}", error)
+                String::from_utf8_lossy(&buff).into_owned()
             }
-            LunaticError::PermissionDenied => write!(f, "Permission denied"),
+            LunaticError::PermissionDenied => "Permission denied".to_owned(),
         }
     }
 }
 
+/// A list specifying general categories of [`LunaticError`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum LunaticErrorKind {
+    /// The process doesn't have the permission for the operation, e.g. compiling modules.
+    PermissionDenied,
+    /// The data couldn't be compiled to a WebAssembly module.
+    InvalidModule,
+    /// A local process couldn't be spawned, e.g. because of the configuration's limits.
+    SpawnFailed,
+    /// A process couldn't be spawned on a remote node, e.g. because the node left the cluster.
+    NodeSpawnFailed,
+    /// Any other error, only described by its message.
+    Other,
+}
+
+impl Debug for LunaticError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.message())
+    }
+}
+
 impl Display for LunaticError {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
-        match self {
-            LunaticError::Error(id) => {
-                let size = unsafe { error::string_size(*id) };
-                let mut buff = vec![0; size as usize];
-                unsafe { error::to_string(*id, buff.as_mut_ptr()) };
-                let error = std::str::from_utf8(&buff).unwrap();
-                write!(f, "{}", error)
-            }
-            LunaticError::PermissionDenied => write!(f, "Permission denied"),
-        }
+        write!(f, "{}", self.message())
     }
 }
@@ -0,0 +1,230 @@
+//! Awaiting lunatic operations from async code.
+//!
+//! Processes are already blocked and resumed by the lunatic scheduler, so they don't need an
+//! async runtime. Async code is still useful inside of processes, e.g. to reuse libraries that
+//! are written against `Future`s or to combine several operations with future combinators.
+//! [`block_on`] runs a future to completion on the current process, and tasks, mailboxes and
+//! [`sleep`] can be awaited inside of it:
+//!
+//! ```
+//! use lunatic::{executor, spawn_link, Mailbox};
+//!
+//! async fn sum(mailbox: &Mailbox<u64>) -> u64 {
+//!     let task = spawn_link!(@task || 1 + 2);
+//!     task.await + mailbox.recv().await
+//! }
+//!
+//! fn entry(_: (), mailbox: Mailbox<u64>) {
+//!     mailbox.this().send(3);
+//!     assert_eq!(executor::block_on(sum(&mailbox)), 6);
+//! }
+//! ```
+//!
+//! If only one lunatic operation is pending, the executor blocks the process in the host until it
+//! completes, exactly like the blocking version of the operation. The runtime can only wait for
+//! one operation at a time without consuming its message, so if several operations are awaited
+//! concurrently (e.g. with `join` or `select` combinators), the executor polls them every
+//! millisecond and sleeps in between.
+
+use std::future::{Future, IntoFuture};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::{Duration, Instant};
+
+use crate::{serializer::Serializer, task::Task, Mailbox, ReceiveError};
+
+/// Time the executor sleeps between polls if several operations are pending.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+thread_local! {
+    // Number of lunatic operations that were pending during the last poll.
+    static PENDING: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // Set if the next polled operation should block until it completes.
+    static BLOCKING: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Runs `future` to completion on the current process and returns its output.
+///
+/// Calls of `block_on` can't be nested.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: IntoFuture,
+{
+    let mut future = pin!(future.into_future());
+    let woken = Arc::new(Woken(AtomicBool::new(false)));
+    let waker = Waker::from(woken.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        PENDING.with(|pending| pending.set(0));
+        let poll = future.as_mut().poll(&mut cx);
+        BLOCKING.with(|blocking| blocking.set(false));
+        if let Poll::Ready(output) = poll {
+            return output;
+        }
+
+        // Futures that aren't lunatic operations (e.g. async channels) can only be woken up by
+        // code running on this process, which is polled again right away.
+        if woken.0.swap(false, Ordering::Relaxed) {
+            continue;
+        }
+        match PENDING.with(|pending| pending.get()) {
+            1 => BLOCKING.with(|blocking| blocking.set(true)),
+            _ => crate::sleep(POLL_INTERVAL),
+        }
+    }
+}
+
+/// Waker of [`block_on`], recording that the future should be polled again.
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Polls a lunatic operation.
+///
+/// `operation` receives the timeout it should wait for, `None` if it should block until it
+/// completes. It returns `None` if the operation timed out.
+fn poll_operation<T>(operation: impl FnOnce(Option<Duration>) -> Option<T>) -> Poll<T> {
+    let timeout = match BLOCKING.with(|blocking| blocking.replace(false)) {
+        true => None,
+        false => Some(Duration::ZERO),
+    };
+    match operation(timeout) {
+        Some(output) => Poll::Ready(output),
+        None => {
+            PENDING.with(|pending| pending.set(pending.get() + 1));
+            Poll::Pending
+        }
+    }
+}
+
+/// Future returned by [`Mailbox::recv`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReceiveFuture<'a, M, S>
+where
+    S: Serializer<M>,
+{
+    pub(crate) mailbox: &'a Mailbox<M, S>,
+}
+
+impl<M, S> Future for ReceiveFuture<'_, M, S>
+where
+    M: 'static,
+    S: Serializer<M>,
+{
+    type Output = M;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<M> {
+        poll_operation(|timeout| match timeout {
+            None => Some(self.mailbox.receive()),
+            Some(timeout) => match self.mailbox.receive_timeout(timeout) {
+                Ok(message) => Some(message),
+                Err(ReceiveError::Timeout) => None,
+                Err(err) => panic!("{}", err),
+            },
+        })
+    }
+}
+
+/// Future of a [`Task`], created by awaiting it.
+///
+/// Dropping the future before the task finished [cancels](crate::protocol::Protocol::cancel) the
+/// task, e.g. if it lost a `select`.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct TaskFuture<T, S>
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    task: Option<Task<T, S>>,
+}
+
+impl<T, S> IntoFuture for Task<T, S>
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    type Output = T;
+    type IntoFuture = TaskFuture<T, S>;
+
+    fn into_future(self) -> TaskFuture<T, S> {
+        TaskFuture { task: Some(self) }
+    }
+}
+
+impl<T, S> Future for TaskFuture<T, S>
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<T> {
+        // No field is structurally pinned.
+        let this = unsafe { self.get_unchecked_mut() };
+        let tag = this.task.as_ref().expect("polled after completion").tag();
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<T, S> = unsafe { Mailbox::new() };
+        let result = poll_operation(|timeout| match timeout {
+            None => Some(mailbox.tag_receive(Some(&[tag]))),
+            Some(timeout) => match mailbox.tag_receive_timeout(Some(&[tag]), timeout) {
+                Ok(result) => Some(result),
+                Err(ReceiveError::Timeout) => None,
+                Err(err) => panic!("{}", err),
+            },
+        });
+        if result.is_ready() {
+            this.task.take().unwrap().finish();
+        }
+        result
+    }
+}
+
+impl<T, S> Drop for TaskFuture<T, S>
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.cancel();
+        }
+    }
+}
+
+/// Returns a future that completes after `duration`.
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+    }
+}
+
+/// Future returned by [`sleep`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Poll::Ready(());
+        }
+        poll_operation(|timeout| match timeout {
+            None => {
+                crate::sleep(remaining);
+                Some(())
+            }
+            Some(_) => None,
+        })
+    }
+}
@@ -0,0 +1,37 @@
+//! Access to the filesystem of the current process.
+//!
+//! Processes can only access files inside of directories that were preopened for them with
+//! [`ProcessConfig::preopen_dir`](crate::ProcessConfig::preopen_dir). The runtime maps the
+//! standard library filesystem API to the host through WASI, so this module re-exports it. Paths
+//! outside of the preopened directories can't be opened, and operations on them fail with an
+//! [`Err`] instead of panicking.
+//!
+//! # Example
+//!
+//! ```
+//! use std::io::{Read, Seek, SeekFrom, Write};
+//!
+//! let config = ProcessConfig::new();
+//! config.preopen_dir("/tmp");
+//!
+//! let task = spawn_link!(@task &config, || {
+//!     let mut file = lunatic::fs::OpenOptions::new()
+//!         .read(true)
+//!         .write(true)
+//!         .create(true)
+//!         .open("/tmp/lunatic.txt")
+//!         .unwrap();
+//!     file.write_all(b"Hello world").unwrap();
+//!     file.seek(SeekFrom::Start(6)).unwrap();
+//!     let mut word = String::new();
+//!     file.read_to_string(&mut word).unwrap();
+//!     word
+//! });
+//! assert_eq!(task.result(), "world");
+//! ```
+
+pub use std::fs::{
+    copy, create_dir, create_dir_all, metadata, read, read_dir, read_to_string, remove_dir,
+    remove_dir_all, remove_file, rename, write, DirEntry, File, FileType, Metadata, OpenOptions,
+    Permissions, ReadDir,
+};
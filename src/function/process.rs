@@ -1,15 +1,17 @@
 //! As the name suggests, a "function" process can be spawned just from a function. Opposite of a
 //! `AbstractProcess` that requires a `struct`.
 
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{marker::PhantomData, time::Duration};
 
 use crate::{
+    dead_letter::{self, DeadLetter},
     host::{self, node_id, process_id},
     protocol::ProtocolCapture,
-    serializer::{Bincode, Serializer},
-    timer::TimerRef,
-    ProcessConfig, Tag,
+    registry,
+    serializer::{Bincode, Bytes, Serializer},
+    timer::{IntervalRef, TimerRef},
+    Mailbox, Monitor, ProcessConfig, ReceiveError, Tag,
 };
 
 /// Decides what can be turned into a process.
@@ -78,6 +80,10 @@ pub trait NoLink {}
 /// recommended to spawn linked processes when they depend on each other. That way we can avoid
 /// one process forever waiting on a message from another process that doesn't exist anymore.
 ///
+/// A mailbox process receiving [`ProcessRequest`] messages can also answer
+/// [`request`](Self::request)s, without the need to implement an
+/// [`AbstractProcess`](crate::process::AbstractProcess).
+///
 /// ### Protocol based processes
 ///
 /// A protocol process takes a [`Protocol`](crate::protocol::Protocol) that can define a sequence
@@ -232,12 +238,25 @@ impl<M, S> Process<M, S> {
     }
 
     /// Link process to the one currently running.
+    ///
+    /// Both processes need to run on the same node. Processes on other nodes can be observed with
+    /// [`monitor`](Self::monitor), which also reports if their node leaves the cluster.
     pub fn link(&self) {
         // Don't use tags because a process' [`Mailbox`] can't differentiate between regular
         // messages and signals. Both processes should almost always die when a link is broken.
         unsafe { host::api::process::link(0, self.id) };
     }
 
+    /// Link process to the one currently running, using `tag` as the tag of the link.
+    ///
+    /// If the caller [catches link failures](crate::Mailbox::catch_link_failure), it receives a
+    /// [`LinkTrapped`](crate::LinkTrapped) carrying `tag` once this process fails. Linking each
+    /// process with its own tag tells the caller which of them failed, e.g. for processes that
+    /// were [looked up](crate::registry::lookup) instead of spawned by the caller.
+    pub fn link_with(&self, tag: Tag) {
+        unsafe { host::api::process::link(tag.id(), self.id) };
+    }
+
     /// Unlink processes from the caller.
     pub fn unlink(&self) {
         unsafe { host::api::process::unlink(self.id) };
@@ -248,39 +267,52 @@ impl<M, S> Process<M, S> {
         unsafe { host::api::process::kill(self.id) };
     }
 
+    /// Start monitoring this process.
+    ///
+    /// Once the process fails, a [`ProcessDown`](crate::ProcessDown) message is delivered to the
+    /// caller. Contrary to [`link`](Self::link), the caller will not be killed if this process
+    /// fails. Dropping the returned [`Monitor`] stops it.
+    pub fn monitor(&self) -> Monitor {
+        Monitor::new(self.node_id, self.id)
+    }
+
+    /// Stop monitoring this process.
+    pub fn demonitor(&self, monitor: Monitor) {
+        monitor.demonitor();
+    }
+
+    /// Returns `false` if the process is reported as gone within `timeout`.
+    ///
+    /// The runtime can't be asked directly if a process exists, so a [`Monitor`] is set up and
+    /// the caller waits up to `timeout` for it to report that the process failed. A `true` result
+    /// is only a guess: processes that finished normally, or whose failure isn't reported in time
+    /// (e.g. on another node), are also reported as alive. The call always blocks for the whole
+    /// `timeout` if the process is running.
+    pub fn probe_alive(&self, timeout: Duration) -> bool {
+        if self.node_id == node_id() && self.id == process_id() {
+            return true;
+        }
+        let monitor = self.monitor();
+        let down = monitor.wait_timeout(timeout).is_ok();
+        monitor.demonitor();
+        !down
+    }
+
     /// Register process under a name.
+    ///
+    /// See the [`registry`](crate::registry) module for details.
     pub fn register(&self, name: &str) {
-        // Encode type information in name
-        let name = format!(
-            "{} + Process + {}/{}",
-            name,
-            std::any::type_name::<M>(),
-            std::any::type_name::<S>()
-        );
-        unsafe { host::api::registry::put(name.as_ptr(), name.len(), self.node_id, self.id) };
+        registry::register(name, self);
+    }
+
+    /// Remove the process registered under a name.
+    pub fn unregister(name: &str) {
+        registry::unregister_process::<M, S>(name);
     }
 
     /// Look up a process.
     pub fn lookup(name: &str) -> Option<Self> {
-        let name = format!(
-            "{} + Process + {}/{}",
-            name,
-            std::any::type_name::<M>(),
-            std::any::type_name::<S>()
-        );
-        let mut id = 0;
-        let mut node_id = 0;
-        let result =
-            unsafe { host::api::registry::get(name.as_ptr(), name.len(), &mut node_id, &mut id) };
-        if result == 0 {
-            Some(Self {
-                node_id,
-                id,
-                serializer_type: PhantomData,
-            })
-        } else {
-            None
-        }
+        registry::lookup_process(name)
     }
 }
 
@@ -296,13 +328,59 @@ where
     /// with serializer `S`.
     pub fn send(&self, message: M) {
         // Create new message.
-        unsafe { host::api::message::create_data(Tag::none().id(), 0) };
+        host::create_message::<M, S>(Tag::none().id(), 0);
         // During serialization resources will add themselves to the message.
         S::encode(&message).unwrap();
         // Send it!
         host::send(self.node_id, self.id);
     }
 
+    /// Send a message to the process, or to the [dead-letter handler](crate::dead_letter) if
+    /// the process is gone.
+    ///
+    /// Returns `false` if the message wasn't delivered. The process is [monitored](Self::monitor)
+    /// from the first checked message on, so the check doesn't block, but messages sent before
+    /// the monitor reports the process as gone are still lost. The [`ProcessDown`] message of
+    /// the monitor waits in the caller's mailbox until the next checked message to the process.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be serialized into `M`
+    /// with serializer `S`.
+    ///
+    /// [`ProcessDown`]: crate::ProcessDown
+    pub fn send_checked(&self, message: M) -> bool
+    where
+        Bincode: Serializer<DeadLetter<M>>,
+    {
+        if dead_letter::is_down(self.node_id, self.id) {
+            dead_letter::forward(self, message);
+            false
+        } else {
+            self.send(message);
+            true
+        }
+    }
+
+    /// Send all `messages` to the process, in order.
+    ///
+    /// The host doesn't support sending multiple messages at once, so every message still needs
+    /// its own host call. Data that is always consumed together should rather be sent as a single
+    /// message (e.g. a `Vec<M>`).
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if one of the messages can't be serialized into `M`
+    /// with serializer `S`.
+    pub fn send_batch<I>(&self, messages: I)
+    where
+        I: IntoIterator<Item = M>,
+    {
+        for message in messages {
+            self.send(message);
+        }
+    }
+
     /// Send a message to the process after the specified duration has passed.
     ///
     /// # Panics
@@ -311,7 +389,7 @@ where
     /// with serializer `S`.
     pub fn send_after(&self, message: M, duration: Duration) -> TimerRef {
         // Create new message.
-        unsafe { host::api::message::create_data(Tag::none().id(), 0) };
+        host::create_message::<M, S>(Tag::none().id(), 0);
         // During serialization resources will add themselves to the message.
         S::encode(&message).unwrap();
         // Send it!
@@ -320,6 +398,38 @@ where
         TimerRef::new(timer_id)
     }
 
+    /// Send a copy of the message to the process every time the `period` passes.
+    ///
+    /// The interval is driven by a helper process, so the caller needs to have the permission to
+    /// spawn processes. The message can't contain resources (e.g. a TCP stream). The interval
+    /// runs until it's [canceled](IntervalRef::cancel), or this process or the caller fails.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be serialized into `M`
+    /// with serializer `S`.
+    pub fn send_interval(&self, message: M, period: Duration) -> IntervalRef {
+        IntervalRef::start(self.node_id, self.id, period, S::TRACE_CONTEXT, || {
+            S::encode(&message).unwrap();
+        })
+    }
+
+    /// Send a message to the process with a `priority`.
+    ///
+    /// [`Mailbox::receive`](crate::Mailbox::receive) returns [`Priority::High`] messages before
+    /// all other messages, so that control messages don't need to wait behind queued up data.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be serialized into `M`
+    /// with serializer `S`.
+    pub fn send_priority(&self, message: M, priority: Priority) {
+        match priority {
+            Priority::Normal => self.send(message),
+            Priority::High => self.tag_send(Tag::high_priority(), message),
+        }
+    }
+
     /// Send message to process with a specific tag.
     ///
     /// # Panics
@@ -328,7 +438,7 @@ where
     /// with serializer `S`.
     pub fn tag_send(&self, tag: Tag, message: M) {
         // Create new message.
-        unsafe { host::api::message::create_data(tag.id(), 0) };
+        host::create_message::<M, S>(tag.id(), 0);
         // During serialization resources will add themselves to the message.
         S::encode(&message).unwrap();
         // Send it!
@@ -336,6 +446,219 @@ where
     }
 }
 
+impl Process<Vec<u8>, Bytes> {
+    /// Send the raw `data` to the process.
+    ///
+    /// Contrary to [`send`](Self::send) the data doesn't need to be owned, it's copied directly
+    /// into the message.
+    pub fn send_bytes(&self, data: &[u8]) {
+        // Create new message with enough capacity for the data.
+        host::create_message::<Vec<u8>, Bytes>(Tag::none().id(), data.len() as u64);
+        unsafe { host::api::message::write_data(data.as_ptr(), data.len()) };
+        // Send it!
+        host::send(self.node_id, self.id);
+    }
+}
+
+impl<M> Process<M>
+where
+    M: Serialize + DeserializeOwned,
+{
+    /// Spawn a process that notifies `notify` when it finishes.
+    ///
+    /// After `entry` returns, a `()` message tagged with `tag` is sent to `notify`. This allows
+    /// fire-and-forget jobs to be tracked, e.g. by waiting for the notification with
+    /// [`tag_receive`](Mailbox::tag_receive). If the process fails, no notification is sent, use
+    /// [`monitor`](Self::monitor) to also track failures.
+    pub fn spawn_notify<C>(
+        capture: C,
+        entry: fn(C, Mailbox<M>),
+        notify: Process<()>,
+        tag: Tag,
+    ) -> Self
+    where
+        C: Serialize + DeserializeOwned,
+    {
+        Process::spawn((notify, tag, capture, entry as usize), notified)
+    }
+
+    /// Spawn a process and wait until its `init` function returns.
+    ///
+    /// `init` is called with `capture` inside of the new process, and its result is passed on to
+    /// `entry`. Because the call only returns once `init` finished, everything that it sets up
+    /// (e.g. a bound socket or a registered name) is ready before the caller sends the first
+    /// message.
+    ///
+    /// Returns `None` if the process fails before `init` returns.
+    pub fn spawn_init<C, T>(capture: C, init: fn(C) -> T, entry: fn(T, Mailbox<M>)) -> Option<Self>
+    where
+        C: Serialize + DeserializeOwned,
+    {
+        let tag = Tag::new();
+        let this = Process::<(), Bincode>::this();
+        let process = Process::spawn(
+            (this, tag, capture, init as usize, entry as usize),
+            initialized::<C, T, M>,
+        );
+        let monitor = process.monitor();
+        // Temporarily cast to right mailbox type, the `ProcessDown` message is only used for its tag.
+        let mailbox: Mailbox<(), Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[tag, monitor.tag()]));
+        let finished = unsafe { host::api::message::get_tag() } == tag.id();
+        monitor.demonitor();
+        finished.then_some(process)
+    }
+
+    /// Spawn a linked process and wait until its `init` function returns.
+    ///
+    /// Same as [`spawn_init`](Self::spawn_init), but if `init` fails the caller fails too.
+    pub fn spawn_link_init<C, T>(capture: C, init: fn(C) -> T, entry: fn(T, Mailbox<M>)) -> Self
+    where
+        C: Serialize + DeserializeOwned,
+    {
+        let tag = Tag::new();
+        let this = Process::<(), Bincode>::this();
+        let process = Process::spawn_link(
+            (this, tag, capture, init as usize, entry as usize),
+            initialized::<C, T, M>,
+        );
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<(), Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[tag]));
+        process
+    }
+}
+
+/// Process to confirm to, tag of the confirmation, capture, `init` and `entry` function.
+type InitArg<C> = (Process<(), Bincode>, Tag, C, usize, usize);
+
+/// Entry point of processes spawned with [`Process::spawn_init`].
+fn initialized<C, T, M>((parent, tag, capture, init, entry): InitArg<C>, mailbox: Mailbox<M>)
+where
+    M: Serialize + DeserializeOwned,
+{
+    let init: fn(C) -> T = unsafe { std::mem::transmute(init) };
+    let entry: fn(T, Mailbox<M>) = unsafe { std::mem::transmute(entry) };
+    let state = init(capture);
+    parent.tag_send(tag, ());
+    entry(state, mailbox);
+}
+
+/// Entry point of processes spawned with [`Process::spawn_notify`].
+fn notified<C, M>((notify, tag, capture, entry): (Process<()>, Tag, C, usize), mailbox: Mailbox<M>)
+where
+    M: Serialize + DeserializeOwned,
+{
+    let entry: fn(C, Mailbox<M>) = unsafe { std::mem::transmute(entry) };
+    entry(capture, mailbox);
+    notify.tag_send(tag, ());
+}
+
+/// Priority of a message sent with [`Process::send_priority`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Priority {
+    /// Same as a message sent with [`Process::send`].
+    Normal,
+    /// Received before all normal priority messages.
+    High,
+}
+
+/// A request received by a mailbox based process, sent with [`Process::request`].
+///
+/// The response is sent back to the requester with [`reply`](Self::reply). It's tagged with a
+/// unique tag of the request, so that multiple requests can be in flight at the same time.
+///
+/// # Example
+///
+/// ```
+/// let adder = Process::spawn((), |_, mailbox: Mailbox<ProcessRequest<(i32, i32), i32>>| loop {
+///     let request = mailbox.receive();
+///     let (a, b) = *request.data();
+///     request.reply(a + b);
+/// });
+///
+/// assert_eq!(adder.request((1, 2)), 3);
+/// ```
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "Req: Serialize", deserialize = "Req: Deserialize<'de>"))]
+pub struct ProcessRequest<Req, Resp, S = Bincode> {
+    data: Req,
+    tag: Tag,
+    sender: Process<Resp, S>,
+}
+
+impl<Req, Resp, S> ProcessRequest<Req, Resp, S> {
+    /// Returns the data of the request.
+    pub fn data(&self) -> &Req {
+        &self.data
+    }
+
+    /// Returns the process that sent the request.
+    pub fn sender(&self) -> &Process<Resp, S> {
+        &self.sender
+    }
+}
+
+impl<Req, Resp, S> ProcessRequest<Req, Resp, S>
+where
+    S: Serializer<Resp>,
+{
+    /// Sends the `response` back to the requester.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the response can't be serialized into `Resp`
+    /// with serializer `S`.
+    pub fn reply(self, response: Resp) {
+        self.sender.tag_send(self.tag, response);
+    }
+}
+
+impl<Req, Resp, S> Process<ProcessRequest<Req, Resp, S>, S>
+where
+    Resp: 'static,
+    S: Serializer<ProcessRequest<Req, Resp, S>> + Serializer<Resp>,
+{
+    /// Sends a request to the process and blocks until the response arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the request can't be serialized or the response can't be
+    /// deserialized with serializer `S`.
+    pub fn request(&self, request: Req) -> Resp {
+        self.request_timeout_(request, None)
+            .expect("no timeout specified")
+    }
+
+    /// Same as [`request`](Self::request), but only waits for the duration of timeout.
+    ///
+    /// If the timeout is reached [`ReceiveError::Timeout`] is returned. The process may still
+    /// reply later, in that case the response ends up in the caller's mailbox, tagged with the
+    /// unique tag of the request.
+    pub fn request_timeout(&self, request: Req, timeout: Duration) -> Result<Resp, ReceiveError> {
+        self.request_timeout_(request, Some(timeout))
+    }
+
+    fn request_timeout_(
+        &self,
+        request: Req,
+        timeout: Option<Duration>,
+    ) -> Result<Resp, ReceiveError> {
+        let tag = Tag::new();
+        self.send(ProcessRequest {
+            data: request,
+            tag,
+            sender: Process::this(),
+        });
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<Resp, S> = unsafe { Mailbox::new() };
+        match timeout {
+            Some(timeout) => mailbox.tag_receive_timeout(Some(&[tag]), timeout),
+            None => Ok(mailbox.tag_receive(Some(&[tag]))),
+        }
+    }
+}
+
 /// Processes are equal if their process id and node id are equal.
 impl<M, S> PartialEq for Process<M, S> {
     fn eq(&self, other: &Self) -> bool {
@@ -0,0 +1,255 @@
+//! Named groups of processes that messages can be broadcast to.
+//!
+//! Any process can [`join`](ProcessGroup::join) or [`leave`](ProcessGroup::leave) a group, and
+//! anyone can [`broadcast`](ProcessGroup::broadcast) a message to all of its members. This is the
+//! building block for patterns that need fan-out, like chat rooms or cache invalidation.
+//!
+//! Groups are backed by a registry process that is started on the node the first time it's
+//! needed. Like names in the [`registry`](crate::registry), groups are local to the node, but
+//! processes from other nodes can be members. Members that fail are removed from their groups
+//! automatically, processes that finish normally should leave their groups before.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{group::ProcessGroup, Mailbox, Process};
+//!
+//! let room = ProcessGroup::<String>::new("room");
+//! let member = Process::spawn((), |_, mailbox: Mailbox<String>| {
+//!     assert_eq!(mailbox.receive(), "Hello!");
+//! });
+//! room.join(&member);
+//! room.broadcast("Hello!".to_owned());
+//! ```
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    host,
+    process::{AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler},
+    registry,
+    serializer::{Bincode, Serializer},
+    LinkMailbox, Mailbox, Process, Tag,
+};
+
+/// Name under which the group registry of a node is registered.
+const REGISTRY_NAME: &str = "lunatic::group::registry";
+
+/// A named group of processes receiving messages of type `M`.
+///
+/// The type of the messages is encoded into the group name, same as for the
+/// [`registry`](crate::registry). A `ProcessGroup<String>` and a `ProcessGroup<u64>` with the
+/// same name are different groups.
+#[derive(Serialize, Deserialize)]
+pub struct ProcessGroup<M, S = Bincode> {
+    name: String,
+    #[serde(skip_serializing, default)]
+    serializer_type: PhantomData<(M, S)>,
+}
+
+impl<M, S> ProcessGroup<M, S> {
+    /// Returns a handle to the group `name`.
+    ///
+    /// Groups don't need to be created, they exist as long as they have members.
+    pub fn new(name: &str) -> Self {
+        ProcessGroup {
+            name: format!(
+                "{} + ProcessGroup + {}/{}",
+                name,
+                std::any::type_name::<M>(),
+                std::any::type_name::<S>()
+            ),
+            serializer_type: PhantomData,
+        }
+    }
+
+    /// Adds `process` to the group.
+    ///
+    /// Joining a group multiple times has no effect, each member receives a broadcast only once.
+    pub fn join(&self, process: &Process<M, S>) {
+        registry().request(Join(self.name.clone(), untyped(process)));
+    }
+
+    /// Removes `process` from the group.
+    ///
+    /// Returns `false` if the process wasn't a member of the group.
+    pub fn leave(&self, process: &Process<M, S>) -> bool {
+        registry().request(Leave(self.name.clone(), untyped(process)))
+    }
+
+    /// Returns all members of the group, in the order they joined.
+    pub fn members(&self) -> Vec<Process<M, S>> {
+        registry()
+            .request(Members(self.name.clone()))
+            .into_iter()
+            .map(|member| Process::new(member.node_id(), member.id()))
+            .collect()
+    }
+}
+
+impl<M, S> ProcessGroup<M, S>
+where
+    S: Serializer<M>,
+{
+    /// Sends `message` to all members of the group.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the message can't be serialized into `M` with serializer `S`.
+    pub fn broadcast(&self, message: M) {
+        for member in registry().request(Members(self.name.clone())) {
+            // The message is consumed by the send, so it needs to be encoded for each member.
+            host::create_message::<M, S>(Tag::none().id(), 0);
+            S::encode(&message).unwrap();
+            host::send(member.node_id(), member.id());
+        }
+    }
+}
+
+impl<M, S> Clone for ProcessGroup<M, S> {
+    fn clone(&self) -> Self {
+        ProcessGroup {
+            name: self.name.clone(),
+            serializer_type: PhantomData,
+        }
+    }
+}
+
+impl<M, S> std::fmt::Debug for ProcessGroup<M, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProcessGroup")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+fn untyped<M, S>(process: &Process<M, S>) -> Process<()> {
+    Process::new(process.node_id(), process.id())
+}
+
+/// Returns the group registry of this node, starting it if it's not running yet.
+fn registry() -> ProcessRef<GroupRegistry> {
+    registry::get_or_start(REGISTRY_NAME, ())
+}
+
+struct Member {
+    process: Process<()>,
+    // Tag identifying this membership in the `MemberDown` message.
+    tag: Tag,
+    watcher: Process<()>,
+}
+
+/// Process keeping track of the members of all groups on the node.
+struct GroupRegistry {
+    this: ProcessRef<GroupRegistry>,
+    groups: HashMap<String, Vec<Member>>,
+}
+
+impl AbstractProcess for GroupRegistry {
+    type Arg = ();
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, _: ()) -> Self::State {
+        GroupRegistry {
+            this,
+            groups: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Join(String, Process<()>);
+impl RequestHandler<Join> for GroupRegistry {
+    type Response = ();
+
+    fn handle(state: &mut Self::State, Join(group, process): Join) {
+        let members = state.groups.entry(group.clone()).or_default();
+        if members.iter().any(|member| member.process == process) {
+            return;
+        }
+        let tag = Tag::new();
+        let watcher = Process::spawn_node(
+            process.node_id(),
+            (state.this.clone(), group, tag, process.id()),
+            watcher,
+        );
+        members.push(Member {
+            process,
+            tag,
+            watcher,
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Leave(String, Process<()>);
+impl RequestHandler<Leave> for GroupRegistry {
+    type Response = bool;
+
+    fn handle(state: &mut Self::State, Leave(group, process): Leave) -> bool {
+        let members = match state.groups.get_mut(&group) {
+            Some(members) => members,
+            None => return false,
+        };
+        let member = match members.iter().position(|member| member.process == process) {
+            Some(index) => members.remove(index),
+            None => return false,
+        };
+        if members.is_empty() {
+            state.groups.remove(&group);
+        }
+        // Stop watching the process.
+        member.watcher.send(());
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Members(String);
+impl RequestHandler<Members> for GroupRegistry {
+    type Response = Vec<Process<()>>;
+
+    fn handle(state: &mut Self::State, Members(group): Members) -> Self::Response {
+        match state.groups.get(&group) {
+            Some(members) => members
+                .iter()
+                .map(|member| member.process.clone())
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MemberDown(String, Tag);
+impl MessageHandler<MemberDown> for GroupRegistry {
+    fn handle(state: &mut Self::State, MemberDown(group, tag): MemberDown) {
+        // The member could have left or rejoined in the meantime, only remove this membership.
+        if let Some(members) = state.groups.get_mut(&group) {
+            members.retain(|member| member.tag != tag);
+            if members.is_empty() {
+                state.groups.remove(&group);
+            }
+        }
+    }
+}
+
+/// Entry point of the helper process that removes a failed member from the group.
+fn watcher(
+    (registry, group, tag, id): (ProcessRef<GroupRegistry>, String, Tag, u64),
+    _: Mailbox<()>,
+) {
+    // The watcher should survive the death of the member.
+    unsafe { host::api::process::die_when_link_dies(0) };
+    unsafe { host::api::process::link(tag.id(), id) };
+
+    let mailbox: LinkMailbox<(), Bincode> = unsafe { LinkMailbox::new() };
+    match mailbox.tag_receive(None) {
+        // The member left the group.
+        Ok(()) => unsafe { host::api::process::unlink(id) },
+        Err(_) => registry.send(MemberDown(group, tag)),
+    }
+}
@@ -15,10 +15,8 @@ pub mod message {
         pub fn create_data(tag: i64, capacity: u64);
         pub fn write_data(data: *const u8, data_len: usize) -> usize;
         pub fn read_data(data: *mut u8, data_len: usize) -> usize;
-        #[allow(dead_code)]
         pub fn seek_data(position: u64);
         pub fn get_tag() -> i64;
-        #[allow(dead_code)]
         pub fn data_size() -> u64;
         pub fn push_tcp_stream(tcp_stream_id: u64) -> u64;
         pub fn take_tcp_stream(index: u64) -> u64;
@@ -142,6 +140,10 @@ pub mod networking {
         pub fn get_udp_socket_broadcast(udp_socket_id: u64) -> i32;
         pub fn clone_udp_socket(udp_socket_id: u64) -> u64;
         pub fn tcp_flush(tcp_stream_id: u64, error_id: *mut u64) -> u32;
+        pub fn set_read_timeout(tcp_stream_id: u64, duration: u64);
+        pub fn set_write_timeout(tcp_stream_id: u64, duration: u64);
+        pub fn get_read_timeout(tcp_stream_id: u64) -> u64;
+        pub fn get_write_timeout(tcp_stream_id: u64) -> u64;
     }
 }
 
@@ -191,10 +193,28 @@ pub mod registry {
             node_id: *mut u64,
             process_id: *mut u64,
         ) -> u32;
+        pub fn get_or_put_later(
+            name: *const u8,
+            name_len: usize,
+            node_id: *mut u64,
+            process_id: *mut u64,
+        ) -> u32;
         pub fn remove(name: *const u8, name_len: usize);
     }
 }
 
+pub mod metrics {
+    #[link(wasm_import_module = "lunatic::metrics")]
+    extern "C" {
+        pub fn counter(name: *const u8, name_len: usize, value: u64);
+        pub fn increment_counter(name: *const u8, name_len: usize);
+        pub fn gauge(name: *const u8, name_len: usize, value: f64);
+        pub fn increment_gauge(name: *const u8, name_len: usize, value: f64);
+        pub fn decrement_gauge(name: *const u8, name_len: usize, value: f64);
+        pub fn histogram(name: *const u8, name_len: usize, value: f64);
+    }
+}
+
 pub mod wasi {
     #[link(wasm_import_module = "lunatic::wasi")]
     extern "C" {
@@ -210,6 +230,25 @@ pub mod wasi {
     }
 }
 
+pub mod random {
+    // Provided to every process by the WASI implementation of the runtime.
+    #[link(wasm_import_module = "wasi_snapshot_preview1")]
+    extern "C" {
+        pub fn random_get(buf: *mut u8, buf_len: usize) -> i32;
+    }
+}
+
+pub mod clock {
+    pub const REALTIME: u32 = 0;
+    pub const MONOTONIC: u32 = 1;
+
+    // Provided to every process by the WASI implementation of the runtime.
+    #[link(wasm_import_module = "wasi_snapshot_preview1")]
+    extern "C" {
+        pub fn clock_time_get(clock_id: u32, precision: u64, time: *mut u64) -> i32;
+    }
+}
+
 pub mod distributed {
     #[link(wasm_import_module = "lunatic::distributed")]
     extern "C" {
@@ -4,6 +4,7 @@ pub mod api;
 
 use crate::{
     module::{params_to_vec, Param, WasmModule},
+    serializer::Serializer,
     LunaticError, ProcessConfig, Tag,
 };
 
@@ -60,8 +61,10 @@ pub(crate) fn spawn(
 
     if result == 0 {
         Ok(id)
+    } else if node.is_some() {
+        Err(LunaticError::NodeSpawnFailed(id))
     } else {
-        Err(LunaticError::from(id))
+        Err(LunaticError::SpawnFailed(id))
     }
 }
 
@@ -79,6 +82,37 @@ pub fn node_id() -> u64 {
     unsafe { api::distributed::node_id() }
 }
 
+/// Creates the buffer of a new message, starting with the [trace context](crate::trace) of the
+/// current process.
+pub fn create_data(tag: i64, capacity: u64) {
+    unsafe { api::message::create_data(tag, capacity) };
+    crate::trace::write_header();
+}
+
+/// Creates the buffer of a new message, whose payload is encoded with `S`.
+///
+/// The [trace context](crate::trace) is left out if `S` doesn't use it, so that the payload is
+/// the whole message.
+pub fn create_message<M, S>(tag: i64, capacity: u64)
+where
+    S: Serializer<M>,
+{
+    unsafe { api::message::create_data(tag, capacity) };
+    if S::TRACE_CONTEXT {
+        crate::trace::write_header();
+    }
+}
+
+/// Receives the next message tagged with one of the `tags` and restores its
+/// [trace context](crate::trace).
+pub fn receive(tags: &[i64], timeout: u64) -> u32 {
+    let result = unsafe { api::message::receive(tags.as_ptr(), tags.len(), timeout) };
+    if result == 0 {
+        crate::trace::read_header();
+    }
+    result
+}
+
 pub fn send(node: u64, process_id: u64) {
     if node_id() == node {
         unsafe { api::message::send(process_id) }
@@ -88,9 +122,13 @@ pub fn send(node: u64, process_id: u64) {
 }
 
 pub fn send_receive_skip_search(node: u64, process_id: u64, timeout: u64) -> u32 {
-    if node_id() == node {
+    let result = if node_id() == node {
         unsafe { api::message::send_receive_skip_search(process_id, timeout) }
     } else {
         unsafe { api::distributed::send_receive_skip_search(node, process_id, timeout) }
+    };
+    if result == 0 {
+        crate::trace::read_header();
     }
+    result
 }
@@ -0,0 +1,317 @@
+use std::{
+    io::{BufReader, Error, ErrorKind, Read, Result, Write},
+    time::Duration,
+};
+
+use super::{find_header, read_head, write_chunked, write_head, Body, BodyLength, Method};
+use crate::net::TcpStream;
+
+/// A blocking HTTP/1.1 client.
+///
+/// Every request is sent over a new [`TcpStream`] that is closed after the response has been
+/// read. Default headers and a timeout can be configured on the client and are used for all
+/// requests created by it.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::http::Client;
+///
+/// let client = Client::new();
+/// let response = client.get("http://example.com/").send().unwrap();
+/// assert_eq!(response.status(), 200);
+/// println!("{}", response.text().unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    timeout: Option<Duration>,
+    headers: Vec<(String, String)>,
+}
+
+impl Client {
+    /// Creates a new client without a timeout or default headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the timeout used for connecting, and for each read and write on the connection.
+    ///
+    /// If the value specified is [`None`], requests will block indefinitely.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the timeout of this client.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Adds a header that will be sent with every request.
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_owned(), value.to_owned()));
+    }
+
+    /// Starts building a `GET` request to `url`.
+    pub fn get(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Get, url)
+    }
+
+    /// Starts building a `POST` request to `url`.
+    pub fn post(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Post, url)
+    }
+
+    /// Starts building a `PUT` request to `url`.
+    pub fn put(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Put, url)
+    }
+
+    /// Starts building a `DELETE` request to `url`.
+    pub fn delete(&self, url: &str) -> RequestBuilder<'_> {
+        self.request(Method::Delete, url)
+    }
+
+    /// Starts building a request with `method` to `url`.
+    pub fn request(&self, method: Method, url: &str) -> RequestBuilder<'_> {
+        RequestBuilder {
+            client: self,
+            method,
+            url: url.to_owned(),
+            headers: Vec::new(),
+            body: RequestBody::Empty,
+        }
+    }
+}
+
+enum RequestBody<'a> {
+    Empty,
+    Bytes(Vec<u8>),
+    Reader(Box<dyn Read + 'a>),
+}
+
+/// A request that is being built, created by one of the [`Client`] methods.
+pub struct RequestBuilder<'a> {
+    client: &'a Client,
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: RequestBody<'a>,
+}
+
+impl<'a> RequestBuilder<'a> {
+    /// Adds a header to the request.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Sets the body of the request. The `Content-Length` header is set automatically.
+    #[must_use]
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = RequestBody::Bytes(body.into());
+        self
+    }
+
+    /// Streams the body of the request from `reader`, using the chunked transfer encoding.
+    #[must_use]
+    pub fn body_reader<R: Read + 'a>(mut self, reader: R) -> Self {
+        self.body = RequestBody::Reader(Box::new(reader));
+        self
+    }
+
+    /// Sends the request and waits for the head of the response.
+    ///
+    /// The body is not read by this function, it can be read from the returned
+    /// [`ClientResponse`].
+    pub fn send(self) -> Result<ClientResponse> {
+        let url = Url::parse(&self.url, "http")?;
+        let stream = match self.client.timeout {
+            Some(timeout) => TcpStream::connect_timeout(url.address(), timeout)?,
+            None => TcpStream::connect(url.address())?,
+        };
+        stream.set_read_timeout(self.client.timeout)?;
+        stream.set_write_timeout(self.client.timeout)?;
+
+        let mut headers = vec![("Host".to_owned(), url.authority.clone())];
+        headers.extend(self.client.headers.iter().cloned());
+        headers.extend(self.headers);
+        if find_header(&headers, "Connection").is_none() {
+            headers.push(("Connection".to_owned(), "close".to_owned()));
+        }
+        match self.body {
+            RequestBody::Empty => {}
+            RequestBody::Bytes(ref body) => {
+                headers.push(("Content-Length".to_owned(), body.len().to_string()));
+            }
+            RequestBody::Reader(_) => {
+                headers.push(("Transfer-Encoding".to_owned(), "chunked".to_owned()));
+            }
+        }
+
+        let mut writer = stream.clone();
+        let start = format!("{} {} HTTP/1.1", self.method, url.target);
+        write_head(&mut writer, &start, &headers)?;
+        match self.body {
+            RequestBody::Empty => {}
+            RequestBody::Bytes(body) => writer.write_all(&body)?,
+            RequestBody::Reader(mut reader) => write_chunked(&mut reader, &mut writer)?,
+        }
+
+        let mut reader = BufReader::new(stream);
+        loop {
+            let (start, headers) = read_head(&mut reader)?;
+            let (status, reason) = parse_status_line(&start)?;
+            // Skip informational responses, like `100 Continue`.
+            if (100..200).contains(&status) && status != 101 {
+                continue;
+            }
+            let length = if self.method == Method::Head || status == 204 || status == 304 {
+                BodyLength::Fixed(0)
+            } else {
+                BodyLength::from_headers(&headers, BodyLength::Close)?
+            };
+            return Ok(ClientResponse {
+                status,
+                reason,
+                headers,
+                body: Body::new(reader, length),
+            });
+        }
+    }
+}
+
+/// The response to a request sent by a [`Client`].
+///
+/// The body of the response is streamed from the connection, it can be read through the [`Read`]
+/// implementation or all at once with [`bytes`](ClientResponse::bytes) and
+/// [`text`](ClientResponse::text).
+#[derive(Debug)]
+pub struct ClientResponse {
+    status: u16,
+    reason: String,
+    headers: Vec<(String, String)>,
+    body: Body<BufReader<TcpStream>>,
+}
+
+impl ClientResponse {
+    /// Returns the status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Returns the reason phrase of the response, e.g. `"Not Found"`.
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Returns all headers of the response, in the order they were received.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Reads the rest of the body.
+    pub fn bytes(mut self) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        self.read_to_end(&mut body)?;
+        Ok(body)
+    }
+
+    /// Reads the rest of the body as an UTF-8 string.
+    pub fn text(mut self) -> Result<String> {
+        let mut body = String::new();
+        self.read_to_string(&mut body)?;
+        Ok(body)
+    }
+}
+
+impl Read for ClientResponse {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+fn parse_status_line(line: &str) -> Result<(u16, String)> {
+    let invalid = || Error::new(ErrorKind::InvalidData, "invalid HTTP status line");
+    let mut parts = line.splitn(3, ' ');
+    match parts.next() {
+        Some(version) if version.starts_with("HTTP/1.") => {}
+        _ => return Err(invalid()),
+    }
+    let status = parts
+        .next()
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(invalid)?;
+    let reason = parts.next().unwrap_or_default().to_owned();
+    Ok((status, reason))
+}
+
+/// The parts of an `http://` URL needed to send a request.
+pub(crate) struct Url {
+    /// Host and optional port, used for the `Host` header.
+    pub(crate) authority: String,
+    /// Whether `authority` contains a port.
+    has_port: bool,
+    /// Path and query.
+    pub(crate) target: String,
+}
+
+impl Url {
+    /// Parses an URL with the plain-text `scheme`, e.g. `http`.
+    pub(crate) fn parse(url: &str, scheme: &str) -> Result<Self> {
+        let rest = match url.split_once("://") {
+            Some((found, rest)) if found.eq_ignore_ascii_case(scheme) => rest,
+            Some((found, _)) if found.eq_ignore_ascii_case(&format!("{}s", scheme)) => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    format!(
+                        "{} URLs are not supported, lunatic doesn't provide TLS yet",
+                        found
+                    ),
+                ))
+            }
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!("expected an {}:// URL", scheme),
+                ))
+            }
+        };
+        // The fragment is never sent to the server.
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (authority, target) = match rest.find(['/', '?']) {
+            Some(index) if rest[index..].starts_with('?') => {
+                (&rest[..index], format!("/{}", &rest[index..]))
+            }
+            Some(index) => (&rest[..index], rest[index..].to_owned()),
+            None => (rest, "/".to_owned()),
+        };
+        if authority.is_empty() || authority.contains('@') {
+            return Err(Error::new(ErrorKind::InvalidInput, "invalid URL host"));
+        }
+        // The port follows the last `:`, unless it's part of an IPv6 address (`[::1]`).
+        let has_port = match authority.rfind(':') {
+            Some(index) => !authority[index..].contains(']'),
+            None => false,
+        };
+        Ok(Url {
+            authority: authority.to_owned(),
+            has_port,
+            target,
+        })
+    }
+
+    /// Returns the `host:port` address to connect to.
+    pub(crate) fn address(&self) -> String {
+        if self.has_port {
+            self.authority.clone()
+        } else {
+            format!("{}:80", self.authority)
+        }
+    }
+}
@@ -0,0 +1,276 @@
+//! A minimal HTTP/1.1 implementation on top of lunatic's [`net`](crate::net) primitives.
+//!
+//! Async HTTP libraries depend on runtimes that don't work inside of lunatic processes. This
+//! module provides a blocking [`Client`] that can be used from any process, and a [`Server`] that
+//! handles each connection in a separate process. Only plain `http://` is supported, because the
+//! runtime doesn't provide TLS yet.
+
+mod client;
+mod server;
+
+use std::fmt;
+use std::io::{BufRead, Error, ErrorKind, Read, Result, Write};
+
+pub(crate) use client::Url;
+pub use client::{Client, ClientResponse, RequestBuilder};
+pub use server::{Request, Response, Server};
+
+/// Maximum number of headers accepted in a request or response.
+const MAX_HEADERS: usize = 128;
+/// Maximum length of the start line or a single header line.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+/// HTTP request method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Patch,
+    Options,
+}
+
+impl Method {
+    /// Returns the method as it's written on the wire, e.g. `"GET"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Patch => "PATCH",
+            Method::Options => "OPTIONS",
+        }
+    }
+
+    pub(crate) fn parse(method: &str) -> Option<Method> {
+        match method {
+            "GET" => Some(Method::Get),
+            "HEAD" => Some(Method::Head),
+            "POST" => Some(Method::Post),
+            "PUT" => Some(Method::Put),
+            "DELETE" => Some(Method::Delete),
+            "PATCH" => Some(Method::Patch),
+            "OPTIONS" => Some(Method::Options),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returns the value of the first header matching `name` (case-insensitive).
+pub(crate) fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+fn invalid_data(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Reads a single line terminated by `\r\n` (or `\n`), without the line ending.
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String> {
+    let mut line = String::new();
+    let read = reader.take(MAX_LINE_LENGTH).read_line(&mut line)?;
+    if read == 0 {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed while reading HTTP message",
+        ));
+    }
+    if !line.ends_with('\n') {
+        return Err(invalid_data("HTTP line is too long"));
+    }
+    line.pop();
+    if line.ends_with('\r') {
+        line.pop();
+    }
+    Ok(line)
+}
+
+/// Reads the start line and the headers of an HTTP message.
+pub(crate) fn read_head<R: BufRead>(reader: &mut R) -> Result<(String, Vec<(String, String)>)> {
+    let start = read_line(reader)?;
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            return Ok((start, headers));
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(invalid_data("too many HTTP headers"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid_data("malformed HTTP header"))?;
+        headers.push((name.trim().to_owned(), value.trim().to_owned()));
+    }
+}
+
+/// Writes the start line and the headers of an HTTP message, followed by an empty line.
+pub(crate) fn write_head<W: Write>(
+    writer: &mut W,
+    start: &str,
+    headers: &[(String, String)],
+) -> Result<()> {
+    let mut head = format!("{}\r\n", start);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes())
+}
+
+/// Copies `reader` to `writer` using the chunked transfer encoding.
+pub(crate) fn write_chunked<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> Result<()> {
+    let mut buffer = [0; 8 * 1024];
+    loop {
+        let size = match reader.read(&mut buffer) {
+            Ok(size) => size,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        if size == 0 {
+            return writer.write_all(b"0\r\n\r\n");
+        }
+        writer.write_all(format!("{:x}\r\n", size).as_bytes())?;
+        writer.write_all(&buffer[..size])?;
+        writer.write_all(b"\r\n")?;
+    }
+}
+
+/// How the length of a message body is determined.
+#[derive(Debug)]
+pub(crate) enum BodyLength {
+    /// `Content-Length` bytes.
+    Fixed(u64),
+    /// `Transfer-Encoding: chunked`, with the bytes remaining in the current chunk.
+    Chunked { remaining: u64, done: bool },
+    /// Until the connection is closed.
+    Close,
+}
+
+impl BodyLength {
+    /// Determines the body length from the message headers.
+    ///
+    /// If neither `Transfer-Encoding` nor `Content-Length` is present, `default` is used.
+    pub(crate) fn from_headers(headers: &[(String, String)], default: BodyLength) -> Result<Self> {
+        if let Some(encoding) = find_header(headers, "Transfer-Encoding") {
+            if encoding
+                .rsplit(',')
+                .next()
+                .map(|last| last.trim().eq_ignore_ascii_case("chunked"))
+                .unwrap_or(false)
+            {
+                return Ok(BodyLength::Chunked {
+                    remaining: 0,
+                    done: false,
+                });
+            }
+            return Ok(BodyLength::Close);
+        }
+        match find_header(headers, "Content-Length") {
+            Some(length) => length
+                .parse()
+                .map(BodyLength::Fixed)
+                .map_err(|_| invalid_data("invalid Content-Length header")),
+            None => Ok(default),
+        }
+    }
+}
+
+/// A reader over a message body, that stops at the end of the body.
+#[derive(Debug)]
+pub(crate) struct Body<R> {
+    reader: R,
+    length: BodyLength,
+}
+
+impl<R: BufRead> Body<R> {
+    pub(crate) fn new(reader: R, length: BodyLength) -> Self {
+        Body { reader, length }
+    }
+
+    /// Reads the size line of the next chunk, and the trailers if it's the last one.
+    fn next_chunk(&mut self) -> Result<u64> {
+        let line = read_line(&mut self.reader)?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size =
+            u64::from_str_radix(size, 16).map_err(|_| invalid_data("invalid HTTP chunk size"))?;
+        if size == 0 {
+            // Skip trailers.
+            while !read_line(&mut self.reader)?.is_empty() {}
+        }
+        Ok(size)
+    }
+}
+
+impl<R: BufRead> Read for Body<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.length {
+            BodyLength::Fixed(0) => Ok(0),
+            BodyLength::Fixed(ref mut remaining) => {
+                let max = (*remaining).min(buf.len() as u64) as usize;
+                let read = self.reader.read(&mut buf[..max])?;
+                if read == 0 {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "connection closed before the end of the HTTP body",
+                    ));
+                }
+                *remaining -= read as u64;
+                Ok(read)
+            }
+            BodyLength::Chunked { done: true, .. } => Ok(0),
+            BodyLength::Chunked { remaining, .. } => {
+                let remaining = if remaining == 0 {
+                    let size = self.next_chunk()?;
+                    if size == 0 {
+                        self.length = BodyLength::Chunked {
+                            remaining: 0,
+                            done: true,
+                        };
+                        return Ok(0);
+                    }
+                    size
+                } else {
+                    remaining
+                };
+                let max = remaining.min(buf.len() as u64) as usize;
+                let read = self.reader.read(&mut buf[..max])?;
+                if read == 0 {
+                    return Err(Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "connection closed before the end of the HTTP body",
+                    ));
+                }
+                let remaining = remaining - read as u64;
+                if remaining == 0 {
+                    // Each chunk is followed by an empty line.
+                    if !read_line(&mut self.reader)?.is_empty() {
+                        return Err(invalid_data("malformed HTTP chunk"));
+                    }
+                }
+                self.length = BodyLength::Chunked {
+                    remaining,
+                    done: false,
+                };
+                Ok(read)
+            }
+            BodyLength::Close => self.reader.read(buf),
+        }
+    }
+}
@@ -0,0 +1,332 @@
+use std::{
+    io::{BufReader, ErrorKind, Read, Result, Write},
+    net::SocketAddr,
+};
+
+use super::{find_header, read_head, write_head, Body, BodyLength, Method};
+use crate::{
+    net::{accept_key, TcpListener, TcpStream, ToSocketAddrs, WebSocket},
+    Mailbox, Process,
+};
+
+/// An HTTP/1.1 server running each connection in its own process.
+///
+/// For every accepted connection a new process is spawned that parses the incoming requests and
+/// calls the handler with each of them. Because the connection processes are not linked to the
+/// server, a handler failing (e.g. panicking) only closes the connection it was serving.
+///
+/// # Example
+///
+/// ```no_run
+/// use lunatic::http::{Request, Response, Server};
+///
+/// fn hello(request: Request) -> Response {
+///     Response::new(200).body(format!("Hello from {}", request.path()))
+/// }
+///
+/// let server = Server::bind("127.0.0.1:8080").unwrap();
+/// server.serve(hello).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Server {
+    listener: TcpListener,
+}
+
+impl Server {
+    /// Creates a new server listening on the given address.
+    pub fn bind<A>(addr: A) -> Result<Self>
+    where
+        A: ToSocketAddrs,
+    {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    /// Creates a new server from an existing listener.
+    pub fn from_listener(listener: TcpListener) -> Self {
+        Server { listener }
+    }
+
+    /// Returns the local address that this server is bound to.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections forever, spawning a process for each of them that calls `handler`
+    /// with every received request.
+    ///
+    /// Returns an error only if accepting a connection fails.
+    pub fn serve(&self, handler: fn(Request) -> Response) -> Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept()?;
+            // The handler is passed as a function pointer, which stays valid in the new process
+            // because it's spawned from the same module.
+            Process::spawn((stream, handler as usize), connection);
+        }
+    }
+}
+
+/// Entry point of the process handling a connection.
+fn connection((stream, handler): (TcpStream, usize), _: Mailbox<()>) {
+    let handler: fn(Request) -> Response = unsafe { std::mem::transmute(handler) };
+    let mut reader = BufReader::new(stream.clone());
+    let mut writer = stream;
+    loop {
+        let request = match Request::read(&mut reader) {
+            Ok(request) => request,
+            // The client closed the connection.
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return,
+            Err(err) => {
+                let status = if err.kind() == ErrorKind::Unsupported {
+                    501
+                } else {
+                    400
+                };
+                let _ = Response::new(status).write(&mut writer, false, true);
+                return;
+            }
+        };
+        let head = request.method == Method::Head;
+        let close = !request.keep_alive();
+        let response = handler(request);
+        if let Some(websocket) = response.websocket {
+            if response.write(&mut writer, false, false).is_ok() {
+                let websocket_handler: fn(WebSocket) = unsafe { std::mem::transmute(websocket) };
+                websocket_handler(WebSocket::new(reader, writer, false));
+            }
+            return;
+        }
+        if response.write(&mut writer, head, close).is_err() || close {
+            return;
+        }
+    }
+}
+
+/// A request received by the [`Server`].
+///
+/// The whole body is read before the request is passed to the handler.
+#[derive(Debug, Clone)]
+pub struct Request {
+    method: Method,
+    target: String,
+    version: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn read(reader: &mut BufReader<TcpStream>) -> Result<Self> {
+        let invalid = || std::io::Error::new(ErrorKind::InvalidData, "invalid HTTP request line");
+        let (start, headers) = read_head(reader)?;
+        let mut parts = start.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version)) if parts.next().is_none() => {
+                (method, target, version)
+            }
+            _ => return Err(invalid()),
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(invalid());
+        }
+        let method = Method::parse(method).ok_or_else(|| {
+            std::io::Error::new(ErrorKind::Unsupported, "unsupported HTTP method")
+        })?;
+
+        if find_header(&headers, "Expect")
+            .map(|expect| expect.eq_ignore_ascii_case("100-continue"))
+            .unwrap_or(false)
+        {
+            reader
+                .get_mut()
+                .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+        }
+        let length = match BodyLength::from_headers(&headers, BodyLength::Fixed(0))? {
+            // Requests can't be delimited by closing the connection.
+            BodyLength::Close => {
+                return Err(std::io::Error::new(
+                    ErrorKind::InvalidData,
+                    "unsupported transfer encoding",
+                ))
+            }
+            length => length,
+        };
+        let mut body = Vec::new();
+        Body::new(reader, length).read_to_end(&mut body)?;
+
+        Ok(Request {
+            method,
+            target: target.to_owned(),
+            version: version.to_owned(),
+            headers,
+            body,
+        })
+    }
+
+    /// Returns `true` if the connection should stay open after this request.
+    fn keep_alive(&self) -> bool {
+        match find_header(&self.headers, "Connection") {
+            Some(connection) if connection.eq_ignore_ascii_case("close") => false,
+            Some(connection) if connection.eq_ignore_ascii_case("keep-alive") => true,
+            _ => self.version != "HTTP/1.0",
+        }
+    }
+
+    /// Returns the method of the request.
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Returns the request target, including the query string, e.g. `/search?q=lunatic`.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns the path of the request target, without the query string.
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// Returns the query string of the request target, if there is one.
+    pub fn query(&self) -> Option<&str> {
+        self.target.split_once('?').map(|(_, query)| query)
+    }
+
+    /// Returns all headers of the request, in the order they were received.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Returns the value of the first header matching `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+
+    /// Returns the body of the request.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+/// A response returned by the handler of a [`Server`].
+///
+/// The `Content-Length` header is set automatically from the body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    // Handler taking over the connection after a WebSocket upgrade.
+    websocket: Option<usize>,
+}
+
+impl Response {
+    /// Creates a new response with `status` and an empty body.
+    pub fn new(status: u16) -> Self {
+        Response {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+            websocket: None,
+        }
+    }
+
+    /// Accepts a WebSocket upgrade `request`.
+    ///
+    /// After the response is sent, `handler` is called with the [`WebSocket`] in the process
+    /// handling the connection. If `request` is not a valid WebSocket upgrade request, a
+    /// `400 Bad Request` response is returned instead.
+    pub fn websocket(request: &Request, handler: fn(WebSocket)) -> Self {
+        let upgrade = request
+            .header("Upgrade")
+            .map(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+            .unwrap_or(false);
+        let connection = request
+            .header("Connection")
+            .map(|connection| {
+                connection
+                    .split(',')
+                    .any(|option| option.trim().eq_ignore_ascii_case("upgrade"))
+            })
+            .unwrap_or(false);
+        match request.header("Sec-WebSocket-Key") {
+            Some(key)
+                if upgrade
+                    && connection
+                    && request.method == Method::Get
+                    && request.header("Sec-WebSocket-Version") == Some("13") =>
+            {
+                let mut response = Response::new(101)
+                    .header("Upgrade", "websocket")
+                    .header("Connection", "Upgrade")
+                    .header("Sec-WebSocket-Accept", &accept_key(key));
+                response.websocket = Some(handler as usize);
+                response
+            }
+            _ => Response::new(400),
+        }
+    }
+
+    /// Adds a header to the response.
+    #[must_use]
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Sets the body of the response.
+    #[must_use]
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Returns the status code of the response.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    fn write<W: Write>(&self, writer: &mut W, head: bool, close: bool) -> Result<()> {
+        let start = format!("HTTP/1.1 {} {}", self.status, reason(self.status));
+        let mut headers = self.headers.clone();
+        // Informational responses don't have a body.
+        if self.status >= 200 {
+            headers.push(("Content-Length".to_owned(), self.body.len().to_string()));
+        }
+        if close {
+            headers.push(("Connection".to_owned(), "close".to_owned()));
+        }
+        write_head(writer, &start, &headers)?;
+        if !head {
+            writer.write_all(&self.body)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the reason phrase of common status codes.
+fn reason(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "",
+    }
+}
@@ -0,0 +1,493 @@
+//! A queue of background jobs, processed by a pool of workers.
+//!
+//! A [`JobQueue`] holds serializable [`Job`]s and hands them out to a fixed number of worker
+//! processes. A job that returns an error, or whose worker fails while running it, is retried
+//! after a delay that doubles with each attempt. After the maximum number of attempts the job is
+//! moved to the dead jobs of the queue, where it can be inspected and
+//! [`retry`](ProcessRef::retry)ed.
+//!
+//! If the queue is configured with a [`path`](JobQueueConfig::path), all jobs are written to this
+//! file on each change and are loaded from it again when the queue is started. This way jobs
+//! survive a restart of the node. Jobs that were running at the time of the restart are run again,
+//! so jobs should be idempotent. The queue process needs access to the directory of the file, see
+//! [`ProcessConfig::preopen_dir`](crate::ProcessConfig::preopen_dir).
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{
+//!     job_queue::{Job, JobQueue, JobQueueConfig},
+//!     process::StartProcess,
+//! };
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize, Clone)]
+//! struct SendEmail {
+//!     to: String,
+//! }
+//!
+//! impl Job for SendEmail {
+//!     fn run(self) -> Result<(), String> {
+//!         println!("Sending email to {}", self.to);
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let queue = JobQueue::<SendEmail>::start_link(JobQueueConfig::new(4), None);
+//! queue.enqueue(SendEmail {
+//!     to: "user@example.com".to_owned(),
+//! });
+//! ```
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    fs,
+    process::{
+        AbstractProcess, DeferredRequest, DeferredRequestHandler, Message, MessageHandler,
+        ProcessRef, ReplyHandle, Request, RequestHandler,
+    },
+    serializer::Bincode,
+    Mailbox, Process, Tag,
+};
+
+/// A unit of work processed by a [`JobQueue`].
+///
+/// The queue keeps a copy of each job until it succeeds, so that it can be retried.
+pub trait Job: Serialize + DeserializeOwned + Clone {
+    /// Runs the job.
+    ///
+    /// Returning an error or panicking counts as a failed attempt.
+    fn run(self) -> Result<(), String>;
+}
+
+/// The configuration a [`JobQueue`] is started with.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct JobQueueConfig {
+    /// Number of worker processes running jobs at the same time.
+    pub workers: usize,
+    /// How often a job is attempted before it's moved to the dead jobs.
+    pub max_attempts: u32,
+    /// Delay before the first retry of a failed job, it doubles with each further attempt.
+    pub backoff: Duration,
+    /// File the jobs are persisted to.
+    pub path: Option<PathBuf>,
+}
+
+impl JobQueueConfig {
+    /// Creates a configuration for a queue with `workers` workers.
+    ///
+    /// Jobs are attempted 3 times with a backoff starting at one second, and aren't persisted.
+    pub fn new(workers: usize) -> Self {
+        JobQueueConfig {
+            workers,
+            max_attempts: 3,
+            backoff: Duration::from_secs(1),
+            path: None,
+        }
+    }
+
+    /// Sets how often a job is attempted before it's moved to the dead jobs.
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Sets the delay before the first retry of a failed job.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Persists the jobs to the file at `path`.
+    pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.path = Some(path.into());
+        self
+    }
+}
+
+/// A job that failed on all of its attempts.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DeadJob<J> {
+    /// ID the job got when it was enqueued.
+    pub id: u64,
+    /// The job itself.
+    pub job: J,
+    /// Number of failed attempts.
+    pub attempts: u32,
+    /// The error of the last attempt.
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry<J> {
+    job: J,
+    attempts: u32,
+}
+
+/// The part of the state that is written to the file of a persistent queue.
+#[derive(Serialize, Deserialize)]
+struct Persisted<J> {
+    next_id: u64,
+    jobs: BTreeMap<u64, Entry<J>>,
+    dead: Vec<DeadJob<J>>,
+}
+
+struct Worker {
+    process: Process<(), Bincode>,
+    // ID of the job the worker is currently running.
+    job: Option<u64>,
+}
+
+/// A process queuing jobs of type `J` and running them on its workers.
+///
+/// It's started with a [`JobQueueConfig`]. Jobs are handed out in the order they were enqueued,
+/// retried jobs are queued again once their backoff passed. Shutting down the queue kills the
+/// workers, running jobs are attempted again the next time a persistent queue is started.
+pub struct JobQueue<J>
+where
+    J: Job,
+{
+    this: ProcessRef<JobQueue<J>>,
+    config: JobQueueConfig,
+    next_id: u64,
+    jobs: BTreeMap<u64, Entry<J>>,
+    // IDs of the jobs waiting for a worker.
+    ready: VecDeque<u64>,
+    dead: Vec<DeadJob<J>>,
+    workers: HashMap<Tag, Worker>,
+    // Workers waiting for a job.
+    idle: VecDeque<(Tag, ReplyHandle<(u64, J)>)>,
+}
+
+impl<J> JobQueue<J>
+where
+    J: Job,
+{
+    fn start_worker(&mut self) {
+        let tag = Tag::new();
+        let process = Process::spawn_link_tag((self.this.clone(), tag), tag, worker::<J>);
+        self.workers.insert(tag, Worker { process, job: None });
+    }
+
+    /// Hands out ready jobs to idle workers.
+    fn dispatch(&mut self) {
+        while !self.idle.is_empty() {
+            let id = match self.ready.pop_front() {
+                Some(id) => id,
+                None => break,
+            };
+            // Skip jobs that were removed in the meantime.
+            let job = match self.jobs.get(&id) {
+                Some(entry) => entry.job.clone(),
+                None => continue,
+            };
+            let (tag, reply) = self.idle.pop_front().unwrap();
+            if let Some(worker) = self.workers.get_mut(&tag) {
+                worker.job = Some(id);
+            }
+            reply.reply((id, job));
+        }
+    }
+
+    /// Records a failed attempt of the job `id` and schedules a retry or gives up on it.
+    fn fail(&mut self, id: u64, error: String) {
+        let entry = match self.jobs.get_mut(&id) {
+            Some(entry) => entry,
+            None => return,
+        };
+        entry.attempts += 1;
+        if entry.attempts >= self.config.max_attempts {
+            let entry = self.jobs.remove(&id).unwrap();
+            self.dead.push(DeadJob {
+                id,
+                job: entry.job,
+                attempts: entry.attempts,
+                error,
+            });
+        } else {
+            let backoff = self
+                .config
+                .backoff
+                .saturating_mul(1 << (entry.attempts - 1).min(16));
+            self.this.send_after(Retry(id), backoff);
+        }
+        self.persist();
+    }
+
+    fn persist(&self) {
+        let path = match &self.config.path {
+            Some(path) => path,
+            None => return,
+        };
+        let persisted = Persisted {
+            next_id: self.next_id,
+            jobs: self.jobs.clone(),
+            dead: self.dead.clone(),
+        };
+        let data = bincode::serialize(&persisted).unwrap();
+        // Replace the file at once, so that a crash doesn't leave a partially written file behind.
+        let temp = path.with_extension("tmp");
+        if let Err(err) = fs::write(&temp, data).and_then(|_| fs::rename(&temp, path)) {
+            panic!(
+                "JobQueue<{}> failed to persist jobs to {}: {}",
+                std::any::type_name::<J>(),
+                path.display(),
+                err
+            );
+        }
+    }
+
+    fn load(config: &JobQueueConfig) -> Option<Persisted<J>> {
+        let path = config.path.as_ref()?;
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return None,
+            Err(err) => panic!(
+                "JobQueue<{}> failed to load jobs from {}: {}",
+                std::any::type_name::<J>(),
+                path.display(),
+                err
+            ),
+        };
+        match bincode::deserialize(&data) {
+            Ok(persisted) => Some(persisted),
+            Err(err) => panic!(
+                "JobQueue<{}> failed to load jobs from {}: {}",
+                std::any::type_name::<J>(),
+                path.display(),
+                err
+            ),
+        }
+    }
+}
+
+impl<J> AbstractProcess for JobQueue<J>
+where
+    J: Job,
+{
+    type Arg = JobQueueConfig;
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, config: JobQueueConfig) -> Self::State {
+        if config.workers == 0 {
+            panic!(
+                "JobQueue<{}> needs at least one worker",
+                std::any::type_name::<J>()
+            );
+        }
+
+        let persisted = Self::load(&config).unwrap_or(Persisted {
+            next_id: 0,
+            jobs: BTreeMap::new(),
+            dead: Vec::new(),
+        });
+        let mut queue = JobQueue {
+            this,
+            ready: persisted.jobs.keys().copied().collect(),
+            next_id: persisted.next_id,
+            jobs: persisted.jobs,
+            dead: persisted.dead,
+            workers: HashMap::with_capacity(config.workers),
+            idle: VecDeque::with_capacity(config.workers),
+            config,
+        };
+        for _ in 0..queue.config.workers {
+            queue.start_worker();
+        }
+        queue
+    }
+
+    fn terminate(state: Self::State) {
+        state
+            .workers
+            .values()
+            .for_each(|worker| worker.process.kill());
+    }
+
+    // The queue shouldn't die if a worker dies.
+    fn trap_links() -> bool {
+        true
+    }
+
+    fn handle_link_trapped(state: &mut Self::State, tag: Tag) {
+        let worker = match state.workers.remove(&tag) {
+            Some(worker) => worker,
+            None => panic!(
+                "JobQueue<{}> received kill signal from a died link",
+                std::any::type_name::<J>()
+            ),
+        };
+        state.idle.retain(|(idle, _)| *idle != tag);
+        state.start_worker();
+        if let Some(id) = worker.job {
+            state.fail(id, "the worker failed while running the job".to_owned());
+        }
+    }
+}
+
+/// Entry point of the worker processes, running one job after the other.
+fn worker<J>((queue, tag): (ProcessRef<JobQueue<J>>, Tag), _: Mailbox<()>)
+where
+    J: Job,
+{
+    loop {
+        let (id, job) = queue.deferred_request(Pull(tag));
+        let result = job.run();
+        queue.send(Done(tag, id, result));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Pull(Tag);
+impl<J> DeferredRequestHandler<Pull> for JobQueue<J>
+where
+    J: Job,
+{
+    type Response = (u64, J);
+
+    fn handle(state: &mut Self::State, Pull(tag): Pull, reply: ReplyHandle<(u64, J)>) {
+        state.idle.push_back((tag, reply));
+        state.dispatch();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Done(Tag, u64, Result<(), String>);
+impl<J> MessageHandler<Done> for JobQueue<J>
+where
+    J: Job,
+{
+    fn handle(state: &mut Self::State, Done(tag, id, result): Done) {
+        if let Some(worker) = state.workers.get_mut(&tag) {
+            worker.job = None;
+        }
+        match result {
+            Ok(()) => {
+                state.jobs.remove(&id);
+                state.persist();
+            }
+            Err(error) => state.fail(id, error),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Enqueue<J>(J);
+impl<J> RequestHandler<Enqueue<J>> for JobQueue<J>
+where
+    J: Job,
+{
+    type Response = u64;
+
+    fn handle(state: &mut Self::State, Enqueue(job): Enqueue<J>) -> u64 {
+        let id = state.next_id;
+        state.next_id += 1;
+        state.jobs.insert(id, Entry { job, attempts: 0 });
+        // Persist before handing out the job, so that it isn't lost if the node goes down.
+        state.persist();
+        state.ready.push_back(id);
+        state.dispatch();
+        id
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Retry(u64);
+impl<J> MessageHandler<Retry> for JobQueue<J>
+where
+    J: Job,
+{
+    fn handle(state: &mut Self::State, Retry(id): Retry) {
+        state.ready.push_back(id);
+        state.dispatch();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RetryDead(u64);
+impl<J> RequestHandler<RetryDead> for JobQueue<J>
+where
+    J: Job,
+{
+    type Response = bool;
+
+    fn handle(state: &mut Self::State, RetryDead(id): RetryDead) -> bool {
+        let index = match state.dead.iter().position(|dead| dead.id == id) {
+            Some(index) => index,
+            None => return false,
+        };
+        let dead = state.dead.remove(index);
+        state.jobs.insert(
+            id,
+            Entry {
+                job: dead.job,
+                attempts: 0,
+            },
+        );
+        state.persist();
+        state.ready.push_back(id);
+        state.dispatch();
+        true
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetDeadJobs;
+impl<J> RequestHandler<GetDeadJobs> for JobQueue<J>
+where
+    J: Job,
+{
+    type Response = Vec<DeadJob<J>>;
+
+    fn handle(state: &mut Self::State, _: GetDeadJobs) -> Vec<DeadJob<J>> {
+        state.dead.clone()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetPending;
+impl<J> RequestHandler<GetPending> for JobQueue<J>
+where
+    J: Job,
+{
+    type Response = usize;
+
+    fn handle(state: &mut Self::State, _: GetPending) -> usize {
+        state.jobs.len()
+    }
+}
+
+impl<J> ProcessRef<JobQueue<J>>
+where
+    J: Job,
+{
+    /// Adds a job to the queue and returns its ID.
+    ///
+    /// The job is persisted before this function returns.
+    pub fn enqueue(&self, job: J) -> u64 {
+        self.request(Enqueue(job))
+    }
+
+    /// Returns the number of jobs that didn't succeed yet, excluding the dead ones.
+    pub fn pending(&self) -> usize {
+        self.request(GetPending)
+    }
+
+    /// Returns the jobs that failed on all of their attempts.
+    pub fn dead_jobs(&self) -> Vec<DeadJob<J>> {
+        self.request(GetDeadJobs)
+    }
+
+    /// Moves the dead job `id` back into the queue, with a fresh set of attempts.
+    ///
+    /// Returns `false` if there is no dead job with this ID.
+    pub fn retry(&self, id: u64) -> bool {
+        self.request(RetryDead(id))
+    }
+}
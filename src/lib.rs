@@ -20,6 +20,10 @@ function.
     handling.
 * **[`Supervisor`](supervisor::Supervisor)** - A process that can supervise others and re-spawn
     them if they fail.
+* **[`DynamicSupervisor`](supervisor::DynamicSupervisor)** - A supervisor whose children are
+  started and stopped at runtime.
+* **[`Pool`](pool::Pool)** - A process managing a group of identical workers that are handed out
+  in round-robin order and restarted if they fail.
 
 ### Linking
 
@@ -28,6 +32,33 @@ killed too.
 
 To spawn a linked process use the [`spawn_link`] function.
 
+Processes that should not outlive a piece of code can be spawned inside of a [`scope`]. They are
+linked to the caller and the scope waits for all of them to finish.
+
+### Monitoring
+
+If a process only needs to be notified when another one fails, without dying itself, it can
+[`monitor`](Process::monitor) it instead.
+
+### Process groups
+
+Processes can [`join`](group::ProcessGroup::join) named groups, so that messages can be
+[`broadcast`](group::ProcessGroup::broadcast) to all members of a group at once.
+
+### Distributed lunatic
+
+Processes can also be spawned on other nodes of a lunatic cluster with
+[`spawn_node`](Process::spawn_node). Messages work across nodes the same way they work locally,
+but links are local to a node. Processes on other nodes can be [monitored](Process::monitor)
+instead, which also reports if their node leaves the cluster. The [`distributed`] module exposes
+information about the available nodes.
+
+### Process local storage
+
+Per process state (e.g. caches, random number generators or connection handles) can be declared
+with the [`process_local!`] macro. It works like [`thread_local!`], each process gets its own
+lazily initialized instance of the value.
+
 ### Process configuration
 
 Spawn functions have a variant that takes a [`ProcessConfig`]. This configuration can be used
@@ -93,34 +124,64 @@ directory for examples.
 
 mod config;
 mod error;
+mod link_set;
+mod lz4;
 mod macros;
 mod mailbox;
 mod module;
+mod monitor;
 mod process_local;
+mod scope;
 mod tag;
 
+pub mod channel;
+pub mod dead_letter;
 pub mod distributed;
+pub mod env;
+pub mod executor;
+pub mod fs;
 pub mod function;
+pub mod group;
 pub mod host;
+pub mod http;
+pub mod job_queue;
+#[cfg(feature = "log")]
+pub mod logger;
+pub mod metrics;
 pub mod net;
+pub mod pool;
 pub mod process;
 pub mod protocol;
+pub mod rand;
+pub mod rate_limiter;
+pub mod registry;
+pub mod router;
 pub mod serializer;
+pub mod service;
+pub mod shutdown;
+pub mod storage;
+pub mod stream;
 pub mod supervisor;
+pub mod task;
+pub mod time;
 pub mod timer;
+pub mod trace;
 
 pub use config::ProcessConfig;
-pub use error::LunaticError;
-pub use function::process::Process;
-pub use mailbox::{Mailbox, ReceiveError};
+pub use error::{LunaticError, LunaticErrorKind};
+pub use function::process::{Priority, Process, ProcessRequest};
+pub use link_set::{ChildExit, ExitReason, LinkSet};
+pub use mailbox::{Dispatch, LinkMailbox, LinkTrapped, Mailbox, ReceiveError};
 pub use module::WasmModule;
+pub use monitor::{DownReason, Monitor, ProcessDown};
+pub use scope::{scope, Scope};
 pub use tag::Tag;
 
 #[doc(hidden)]
 pub use process_local::statik::Key as __StaticProcessLocalInner;
 pub use process_local::ProcessLocal;
 
-pub use lunatic_macros::{abstract_process, main};
+pub use lunatic_macros::{abstract_process, main, MessageEnum};
 pub use lunatic_test::test;
 
 /// Implemented for all resources held by the VM.
@@ -140,3 +201,13 @@ pub trait Resource {
 pub fn sleep(duration: std::time::Duration) {
     unsafe { host::api::process::sleep_ms(duration.as_millis() as u64) };
 }
+
+/// Lets the other processes on the node run before the current one continues.
+///
+/// The runtime preempts processes regardless, but CPU-bound processes can call this inside of
+/// long loops to keep latency-critical processes on the same node responsive. See
+/// [`ProcessConfig`] for more about scheduling.
+pub fn yield_now() {
+    // Sleeping for 0 ms puts the process back at the end of the run queue.
+    unsafe { host::api::process::sleep_ms(0) };
+}
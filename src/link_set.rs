@@ -0,0 +1,190 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    mailbox::LinkMailbox,
+    serializer::{Bincode, Serializer},
+    Mailbox, Process, Tag,
+};
+
+/// Why a child of a [`LinkSet`] stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExitReason {
+    /// The entry function of the child returned.
+    Finished,
+    /// The child failed (panicked, trapped or was killed).
+    Failed,
+}
+
+/// A child of a [`LinkSet`] that stopped, returned by [`LinkSet::wait_any`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChildExit {
+    /// Position of the child in the set, in the order the children were spawned.
+    pub index: usize,
+    /// The stopped child.
+    pub process: Process<()>,
+    /// Why the child stopped.
+    pub reason: ExitReason,
+}
+
+#[derive(Debug)]
+struct Child {
+    process: Process<()>,
+    tag: Tag,
+    running: bool,
+}
+
+/// A batch of children linked to the current process.
+///
+/// Coordinators that start an ad-hoc group of processes often only need to know when each of
+/// them is done and which ones failed, without the restart strategies of a
+/// [`Supervisor`](crate::supervisor::Supervisor). A `LinkSet` spawns the children linked to the
+/// current process and reports each child that stops with [`wait_any`](Self::wait_any), or all of
+/// them at once with [`wait_all`](Self::wait_all).
+///
+/// The runtime only reports links of failed processes and doesn't say which process failed, only
+/// the tag of the link. Each child is therefore linked with its own tag, and reports back to the
+/// set once its entry function returns. Because a failing child must not kill the coordinator, a
+/// link set can only be created by processes that [catch link
+/// failures](crate::Mailbox::catch_link_failure).
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{ExitReason, LinkSet, Mailbox};
+///
+/// fn coordinator(mailbox: Mailbox<()>) {
+///     let mailbox = mailbox.catch_link_failure();
+///     let mut set = LinkSet::new(&mailbox);
+///     for i in 0..4 {
+///         set.spawn(i, |i, _: Mailbox<()>| assert!(i % 2 == 0));
+///     }
+///     let failed = set
+///         .wait_all()
+///         .into_iter()
+///         .filter(|exit| exit.reason == ExitReason::Failed)
+///         .count();
+///     assert_eq!(failed, 2);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LinkSet {
+    // Tag of the messages reporting finished children.
+    tag: Tag,
+    children: Vec<Child>,
+}
+
+impl LinkSet {
+    /// Creates an empty link set.
+    ///
+    /// The `mailbox` proves that the current process catches link failures.
+    pub fn new<M, S>(_mailbox: &LinkMailbox<M, S>) -> Self
+    where
+        S: Serializer<M>,
+    {
+        LinkSet {
+            tag: Tag::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Spawns a child linked to the current process, running `entry` with the argument `capture`.
+    pub fn spawn<C, M>(&mut self, capture: C, entry: fn(C, Mailbox<M>)) -> Process<M>
+    where
+        C: Serialize + DeserializeOwned,
+        M: Serialize + DeserializeOwned,
+    {
+        let tag = Tag::new();
+        let index = self.children.len() as u64;
+        let parent = Process::<u64, Bincode>::this();
+        let process = Process::spawn_link_tag(
+            (parent, self.tag, index, entry as usize, capture),
+            tag,
+            child::<C, M>,
+        );
+        self.children.push(Child {
+            process: Process::new(process.node_id(), process.id()),
+            tag,
+            running: true,
+        });
+        process
+    }
+
+    /// Returns the number of children in the set.
+    pub fn len(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns `true` if the set has no children.
+    pub fn is_empty(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the number of children that are still running.
+    pub fn running(&self) -> usize {
+        self.children.iter().filter(|child| child.running).count()
+    }
+
+    /// Blocks until the next child stops.
+    ///
+    /// Returns `None` if no child is running anymore.
+    pub fn wait_any(&mut self) -> Option<ChildExit> {
+        let mut tags: Vec<i64> = self
+            .children
+            .iter()
+            .filter(|child| child.running)
+            .map(|child| child.tag.id())
+            .collect();
+        if tags.is_empty() {
+            return None;
+        }
+        tags.push(self.tag.id());
+
+        // Temporarily cast to right mailbox type.
+        let mailbox: LinkMailbox<u64, Bincode> = unsafe { LinkMailbox::new() };
+        let (index, reason) = match mailbox.receive_(Some(&tags), None).unwrap() {
+            Ok(index) => (index as usize, ExitReason::Finished),
+            Err(trapped) => {
+                let index = self
+                    .children
+                    .iter()
+                    .position(|child| child.tag == trapped.tag())
+                    .unwrap();
+                (index, ExitReason::Failed)
+            }
+        };
+        let child = &mut self.children[index];
+        child.running = false;
+        Some(ChildExit {
+            index,
+            process: child.process.clone(),
+            reason,
+        })
+    }
+
+    /// Blocks until all children stopped and returns them in the order they stopped.
+    pub fn wait_all(&mut self) -> Vec<ChildExit> {
+        std::iter::from_fn(|| self.wait_any()).collect()
+    }
+
+    /// Kills all children that are still running.
+    ///
+    /// The killed children are reported as [`Failed`](ExitReason::Failed).
+    pub fn kill_all(&self) {
+        for child in self.children.iter().filter(|child| child.running) {
+            child.process.kill();
+        }
+    }
+}
+
+/// Entry point of the children, reporting back to the set once `entry` returns.
+fn child<C, M>(
+    (parent, tag, index, entry, capture): (Process<u64, Bincode>, Tag, u64, usize, C),
+    mailbox: Mailbox<M>,
+) where
+    C: Serialize + DeserializeOwned,
+    M: Serialize + DeserializeOwned,
+{
+    let entry: fn(C, Mailbox<M>) = unsafe { std::mem::transmute(entry) };
+    entry(capture, mailbox);
+    parent.tag_send(tag, index);
+}
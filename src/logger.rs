@@ -0,0 +1,99 @@
+//! A [`log`] logger that adds the identity of the current process to each record.
+//!
+//! Output of hundreds of processes writing to the same stream is hard to follow without knowing
+//! which process wrote a line. [`ProcessLogger`] prefixes each record with the ID of the process
+//! and, if it's set, the name of the process. Names of [`AbstractProcess`]es started with a name
+//! are set automatically, other processes can set it with [`set_process_name`].
+//!
+//! The runtime doesn't provide a logging API, so records are written to the standard error of
+//! the process.
+//!
+//! This module is only available with the `log` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::logger::{self, ProcessLogger};
+//!
+//! ProcessLogger::init(log::LevelFilter::Info).unwrap();
+//! logger::set_process_name("worker");
+//! // Prints: `INFO [process 1 "worker"] my_app: Hello`
+//! log::info!("Hello");
+//! ```
+//!
+//! [`AbstractProcess`]: crate::process::AbstractProcess
+
+use std::cell::RefCell;
+use std::io::Write;
+
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+use crate::host::process_id;
+
+crate::process_local! {
+    /// Name of the current process, used in log records.
+    static NAME: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Sets the name of the current process used in log records.
+pub fn set_process_name(name: &str) {
+    NAME.with(|process_name| *process_name.borrow_mut() = Some(name.to_owned()));
+}
+
+/// A logger writing records together with the identity of the process to standard error.
+#[derive(Debug)]
+pub struct ProcessLogger {
+    level: LevelFilter,
+}
+
+impl ProcessLogger {
+    /// Creates a new logger for records up to `level`.
+    pub fn new(level: LevelFilter) -> Self {
+        ProcessLogger { level }
+    }
+
+    /// Installs a new logger for records up to `level` as the global logger.
+    ///
+    /// Every process has its own memory and needs to install the logger for itself. Processes
+    /// spawned from a process with a logger don't inherit it.
+    pub fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+        log::set_boxed_logger(Box::new(ProcessLogger::new(level)))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl Log for ProcessLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = NAME.with(|name| match name.borrow().as_ref() {
+            Some(name) => format!(
+                "{} [process {} {:?}] {}: {}\n",
+                record.level(),
+                process_id(),
+                name,
+                record.target(),
+                record.args()
+            ),
+            None => format!(
+                "{} [process {}] {}: {}\n",
+                record.level(),
+                process_id(),
+                record.target(),
+                record.args()
+            ),
+        });
+        // Write the whole record at once, so that it doesn't interleave with other output.
+        let _ = std::io::stderr().write_all(line.as_bytes());
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
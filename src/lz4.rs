@@ -0,0 +1,167 @@
+//! A small implementation of the LZ4 block format, used for compressed messages.
+//!
+//! The compressor is a simple greedy matcher with a single hash table. It doesn't reach the ratio
+//! of the reference implementation, but its output can be decoded by any LZ4 block decoder.
+
+/// Shortest match that is encoded.
+const MIN_MATCH: usize = 4;
+/// The last match must start at least this many bytes before the end of the block.
+const MF_LIMIT: usize = 12;
+/// The last bytes of a block are always literals.
+const LAST_LITERALS: usize = 5;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_LOG: u32 = 12;
+
+/// Compresses `input` into an LZ4 block.
+pub(crate) fn compress(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() / 2 + 16);
+    // Last position of each hashed sequence of 4 bytes.
+    let mut table = vec![0; 1 << HASH_LOG];
+    let mut anchor = 0;
+    let mut pos = 0;
+    if input.len() > MF_LIMIT {
+        let match_limit = input.len() - MF_LIMIT;
+        let end_limit = input.len() - LAST_LITERALS;
+        while pos < match_limit {
+            let sequence = read_u32(input, pos);
+            let hash = (sequence.wrapping_mul(2654435761) >> (32 - HASH_LOG)) as usize;
+            let candidate = table[hash];
+            table[hash] = pos;
+            if candidate >= pos
+                || pos - candidate > MAX_OFFSET
+                || read_u32(input, candidate) != sequence
+            {
+                pos += 1;
+                continue;
+            }
+            let offset = pos - candidate;
+            let mut end = pos + MIN_MATCH;
+            while end < end_limit && input[end] == input[end - offset] {
+                end += 1;
+            }
+            write_sequence(&mut output, &input[anchor..pos], Some((offset, end - pos)));
+            pos = end;
+            anchor = end;
+        }
+    }
+    write_sequence(&mut output, &input[anchor..], None);
+    output
+}
+
+/// Decompresses an LZ4 block that decodes to `size` bytes.
+///
+/// Returns `None` if the block is malformed or doesn't decode to `size` bytes.
+pub(crate) fn decompress(input: &[u8], size: usize) -> Option<Vec<u8>> {
+    let mut output = Vec::with_capacity(size);
+    let mut pos = 0;
+    loop {
+        let token = *input.get(pos)?;
+        pos += 1;
+
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals += read_length(input, &mut pos)?;
+        }
+        if output.len() + literals > size {
+            return None;
+        }
+        output.extend_from_slice(input.get(pos..pos.checked_add(literals)?)?);
+        pos += literals;
+        // The last sequence only contains literals.
+        if pos == input.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes([*input.get(pos)?, *input.get(pos + 1)?]) as usize;
+        pos += 2;
+        let mut length = (token & 15) as usize;
+        if length == 15 {
+            length += read_length(input, &mut pos)?;
+        }
+        let length = length + MIN_MATCH;
+        if offset == 0 || offset > output.len() || output.len() + length > size {
+            return None;
+        }
+        // Matches can overlap with the bytes they produce, so they are copied one by one.
+        let start = output.len() - offset;
+        for i in start..start + length {
+            output.push(output[i]);
+        }
+    }
+    (output.len() == size).then_some(output)
+}
+
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]])
+}
+
+/// Writes the literals, followed by the offset and length of a match, if there is one.
+fn write_sequence(output: &mut Vec<u8>, literals: &[u8], r#match: Option<(usize, usize)>) {
+    let length = r#match.map_or(0, |(_, length)| length - MIN_MATCH);
+    output.push(((literals.len().min(15) as u8) << 4) | length.min(15) as u8);
+    if literals.len() >= 15 {
+        write_length(output, literals.len() - 15);
+    }
+    output.extend_from_slice(literals);
+    if let Some((offset, _)) = r#match {
+        output.extend_from_slice(&(offset as u16).to_le_bytes());
+        if length >= 15 {
+            write_length(output, length - 15);
+        }
+    }
+}
+
+fn write_length(output: &mut Vec<u8>, mut length: usize) {
+    while length >= 255 {
+        output.push(255);
+        length -= 255;
+    }
+    output.push(length as u8);
+}
+
+fn read_length(input: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut length = 0;
+    loop {
+        let byte = *input.get(*pos)?;
+        *pos += 1;
+        length += byte as usize;
+        if byte != 255 {
+            return Some(length);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+    use lunatic_test::test;
+
+    #[test]
+    fn round_trip() {
+        let inputs: Vec<Vec<u8>> = vec![
+            Vec::new(),
+            b"short".to_vec(),
+            vec![7; 100_000],
+            (0..100_000).map(|i| (i % 251) as u8).collect(),
+            b"lunatic ".repeat(1000),
+        ];
+        for input in inputs {
+            let compressed = compress(&input);
+            assert_eq!(decompress(&compressed, input.len()), Some(input));
+        }
+    }
+
+    #[test]
+    fn compresses_repetitions() {
+        let input = vec![0; 64 * 1024];
+        assert!(compress(&input).len() < 1024);
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        let compressed = compress(&[1; 1000]);
+        assert_eq!(decompress(&compressed, 999), None);
+        assert_eq!(decompress(&compressed[..compressed.len() - 1], 1000), None);
+        assert_eq!(decompress(&[0x0f, 0, 0], 10), None);
+    }
+}
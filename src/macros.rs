@@ -33,6 +33,9 @@ macro_rules! spawn_link_config {
 /// spawn!(|local_var| assert_eq!(local_var, "Hello"));
 /// // Give variable during invocation
 /// spawn!(|local_var = {"Hello".to_owned()}| assert_eq!(local_var, "Hello"));
+/// // Capture multiple local vars & mailbox process
+/// let (a, b) = (1, 2);
+/// spawn!(|a, b, mailbox: Mailbox<i32>| assert_eq!(a + b, mailbox.receive()));
 /// // Background process with config
 /// let config = ProcessConfig::new();
 /// spawn!(&config, || {});
@@ -82,6 +85,21 @@ macro_rules! spawn {
             |$argument, $mailbox: lunatic::Mailbox<$mailbox_ty>| $body,
         )
     };
+    // A process with a mailbox capturing multiple variables. The captured variables are collected
+    // one by one, until the mailbox argument is reached.
+    ($(&$config:ident,)? |$argument:ident, $($rest:tt)*) => {
+        lunatic::spawn!(@captures [$($config)?] [$argument] $($rest)*)
+    };
+    (@captures [$($config:ident)?] [$($captured:ident)*] $mailbox:ident : Mailbox<$mailbox_ty:ty>| $body:expr) => {
+        lunatic::spawn_link_config!($($config)?) (
+            $(&$config,)?
+            ($($captured),*),
+            |($($captured),*), $mailbox: lunatic::Mailbox<$mailbox_ty>| $body,
+        )
+    };
+    (@captures [$($config:ident)?] [$($captured:ident)*] $argument:ident, $($rest:tt)*) => {
+        lunatic::spawn!(@captures [$($config)?] [$($captured)* $argument] $($rest)*)
+    };
 }
 
 /// Helper macro for spawning linked processes.
@@ -107,6 +125,9 @@ macro_rules! spawn {
 /// // Protocol, capture local_var
 /// let local_var = "Hello".to_owned();
 /// spawn_link!(|local_var, _proto: Protocol<End>| assert_eq!(local_var, "Hello"));
+/// // Capture multiple local vars & mailbox process
+/// let (a, b) = (1, 2);
+/// spawn_link!(|a, b, mailbox: Mailbox<i32>| assert_eq!(a + b, mailbox.receive()));
 /// // Background process with config
 /// let config = ProcessConfig::new();
 /// spawn_link!(&config, || {});
@@ -219,4 +240,158 @@ macro_rules! spawn_link {
             |$argument, $protocol: lunatic::protocol::Protocol<$proto_ty>| $body,
         )
     };
+    // A process with a mailbox or protocol capturing multiple variables. The captured variables are
+    // collected one by one, until the mailbox or protocol argument is reached.
+    ($(&$config:ident,)? |$argument:ident, $($rest:tt)*) => {
+        lunatic::spawn_link!(@captures [$($config)?] [$argument] $($rest)*)
+    };
+    (@captures [$($config:ident)?] [$($captured:ident)*] $mailbox:ident : Mailbox<$mailbox_ty:ty>| $body:expr) => {
+        lunatic::spawn_link_config!(@link $($config)?) (
+            $(&$config,)?
+            ($($captured),*),
+            |($($captured),*), $mailbox: lunatic::Mailbox<$mailbox_ty>| $body,
+        )
+    };
+    (@captures [$($config:ident)?] [$($captured:ident)*] $protocol:ident : Protocol<$proto_ty:ty>| $body:expr) => {
+        lunatic::spawn_link_config!(@link $($config)?) (
+            $(&$config,)?
+            ($($captured),*),
+            |($($captured),*), $protocol: lunatic::protocol::Protocol<$proto_ty>| $body,
+        )
+    };
+    (@captures [$($config:ident)?] [$($captured:ident)*] $argument:ident, $($rest:tt)*) => {
+        lunatic::spawn_link!(@captures [$($config)?] [$($captured)* $argument] $($rest)*)
+    };
+}
+
+/// Builds an N-ary [`Choose`](crate::protocol::Choose) protocol type.
+///
+/// `choose![A, B, C]` expands to `Choose<A, Choose<B, C>>`. The n-th option is selected by calling
+/// [`select_right`](crate::protocol::Protocol::select_right) n times, followed by
+/// [`select_left`](crate::protocol::Protocol::select_left) (except for the last option).
+///
+/// # Example
+///
+/// ```
+/// type Login = Recv<String, choose![Send<u64, End>, Send<String, End>, End]>;
+/// ```
+#[macro_export]
+macro_rules! choose {
+    ($last:ty $(,)?) => {
+        $last
+    };
+    ($first:ty, $($rest:ty),+ $(,)?) => {
+        lunatic::protocol::Choose<$first, lunatic::choose![$($rest),+]>
+    };
+}
+
+/// Builds an N-ary [`Offer`](crate::protocol::Offer) protocol type.
+///
+/// `offer![A, B, C]` expands to `Offer<A, Offer<B, C>>`. It's the dual of
+/// [`choose!`](crate::choose) and the branches can be handled with [`branch!`](crate::branch).
+#[macro_export]
+macro_rules! offer {
+    ($last:ty $(,)?) => {
+        $last
+    };
+    ($first:ty, $($rest:ty),+ $(,)?) => {
+        lunatic::protocol::Offer<$first, lunatic::offer![$($rest),+]>
+    };
+}
+
+/// Handles all branches of an N-ary [`offer!`](crate::offer) protocol.
+///
+/// Each branch is given a label, only used for readability, and an expression that is evaluated if
+/// the other side selected this branch. Inside the expression the protocol variable is re-bound
+/// to the continuation of the selected branch.
+///
+/// # Example
+///
+/// ```
+/// let token = branch! { protocol,
+///     Success => {
+///         let (_, token) = protocol.receive();
+///         Some(token)
+///     },
+///     Denied => None,
+/// };
+/// ```
+#[macro_export]
+macro_rules! branch {
+    ($protocol:ident, $label:ident => $body:expr $(,)?) => {
+        $body
+    };
+    ($protocol:ident, $label:ident => $body:expr, $($rest:tt)+) => {
+        match $protocol.offer() {
+            #[allow(unused_variables)]
+            lunatic::protocol::Branch::Left($protocol) => $body,
+            #[allow(unused_variables)]
+            lunatic::protocol::Branch::Right($protocol) => lunatic::branch!($protocol, $($rest)+),
+        }
+    };
+}
+
+/// Selectively receives the first message from a [`Mailbox`](crate::Mailbox) that matches one of
+/// the patterns.
+///
+/// Messages that don't match any pattern stay in the mailbox in their order of arrival, same as
+/// with [`Mailbox::receive_where`](crate::Mailbox::receive_where). The body of the first matching
+/// pattern is evaluated with the bindings of the pattern. An optional `after` arm at the end waits
+/// only for the given duration and is evaluated if no matching message arrives in time.
+///
+/// All patterns match the message type of the mailbox. Different kinds of messages can be
+/// received together by wrapping them into an enum.
+///
+/// # Example
+///
+/// ```
+/// let reply = receive! { mailbox,
+///     Message::Ping(n) => Some(n),
+///     Message::Stop => None,
+///     after Duration::from_secs(1) => None,
+/// };
+/// ```
+#[macro_export]
+macro_rules! receive {
+    (@arms $mailbox:ident, [$([$pat:pat] => $body:expr,)+] after $timeout:expr => $after:expr $(,)?) => {
+        match $mailbox.receive_where_timeout(lunatic::receive!(@predicate $($pat),+), $timeout) {
+            Ok(message) => match message {
+                $($pat => $body,)+
+                #[allow(unreachable_patterns)]
+                _ => unreachable!(),
+            },
+            Err(lunatic::ReceiveError::Timeout) => $after,
+            Err(err) => panic!("{}: {:?}", err, err),
+        }
+    };
+    (@arms $mailbox:ident, [$([$pat:pat] => $body:expr,)+]) => {
+        match $mailbox.receive_where(lunatic::receive!(@predicate $($pat),+)) {
+            $($pat => $body,)+
+            #[allow(unreachable_patterns)]
+            _ => unreachable!(),
+        }
+    };
+    // Returns true if the message matches one of the patterns.
+    (@predicate $($pat:pat),+) => {
+        |message| match message {
+            $(
+                #[allow(unused_variables)]
+                $pat => true,
+            )+
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    };
+    (@arms $mailbox:ident, [$($arms:tt)*] , $($rest:tt)*) => {
+        lunatic::receive!(@arms $mailbox, [$($arms)*] $($rest)*)
+    };
+    (@arms $mailbox:ident, [$($arms:tt)*] $pat:pat => $body:block $($rest:tt)*) => {
+        lunatic::receive!(@arms $mailbox, [$($arms)* [$pat] => $body,] $($rest)*)
+    };
+    (@arms $mailbox:ident, [$($arms:tt)*] $pat:pat => $body:expr $(, $($rest:tt)*)?) => {
+        lunatic::receive!(@arms $mailbox, [$($arms)* [$pat] => $body,] $($($rest)*)?)
+    };
+    ($mailbox:ident, $($arms:tt)+) => {
+        lunatic::receive!(@arms $mailbox, [] $($arms)+)
+    };
 }
@@ -1,18 +1,39 @@
-use std::{marker::PhantomData, time::Duration};
+use std::{any::Any, cell::RefCell, collections::VecDeque, marker::PhantomData, time::Duration};
 
 use thiserror::Error;
 
 use crate::{
+    executor::ReceiveFuture,
     function::process::{IntoProcess, NoLink},
     host::{self, api::message},
     serializer::{Bincode, DecodeError, Serializer},
+    time::Deadline,
+    trace::{self, TraceContext},
     Process, ProcessConfig, Tag,
 };
 
-const LINK_TRAPPED: u32 = 1;
-const TIMEOUT: u32 = 9027;
+pub(crate) const LINK_TRAPPED: u32 = 1;
+pub(crate) const TIMEOUT: u32 = 9027;
+
+crate::process_local! {
+    /// Messages that were already taken out of the mailbox, but not returned to the caller yet
+    /// (e.g. skipped by [`Mailbox::receive_where`]).
+    static DEFERRED: RefCell<VecDeque<Deferred>> = RefCell::new(VecDeque::new());
+}
+
+/// A message in [`DEFERRED`], together with the tag and trace context it was received with.
+struct Deferred {
+    tag: Tag,
+    context: Option<TraceContext>,
+    message: Box<dyn Any>,
+}
 
 /// Mailbox of a [`Process`](crate::Process).
+///
+/// Mailboxes are unbounded, the lunatic runtime doesn't limit the number of queued messages or
+/// report it to the sender. If a fast producer can overwhelm a slow consumer, use
+/// [requests](crate::process::Request) instead of messages, so that the producer waits for each
+/// message to be handled before sending the next one.
 #[derive(Debug, Clone, Copy)]
 pub struct Mailbox<M, S = Bincode>
 where
@@ -30,16 +51,82 @@ where
         Process::new(host::node_id(), host::process_id())
     }
 
+    /// Create a mailbox with a specific type.
+    ///
+    /// ### Safety
+    ///
+    /// It's not safe to mix different types of mailboxes inside one process. This function should
+    /// never be used directly. The only reason it's public is that it's used inside the `main`
+    /// macro and needs to be available outside this crate.
+    pub unsafe fn new() -> Self {
+        Self {
+            phantom: PhantomData {},
+        }
+    }
+
+    /// Stops the process from dying when a linked process fails.
+    ///
+    /// Link failures are delivered as [`LinkTrapped`] errors through the returned
+    /// [`LinkMailbox`] instead.
+    pub fn catch_link_failure(self) -> LinkMailbox<M, S> {
+        unsafe { host::api::process::die_when_link_dies(0) };
+        unsafe { LinkMailbox::new() }
+    }
+
+    /// Takes the next message directly from the host, skipping deferred messages.
+    pub(crate) fn receive_host(
+        &self,
+        tags: Option<&[i64]>,
+        timeout: Option<Duration>,
+    ) -> Result<M, ReceiveError> {
+        let tags = if let Some(tags) = tags { tags } else { &[] };
+        let timeout_ms = match timeout {
+            Some(timeout) => timeout.as_millis() as u64,
+            None => u64::MAX,
+        };
+        let message_type = host::receive(tags, timeout_ms);
+        // Mailbox can't receive LINK_TRAPPED messages.
+        assert_ne!(message_type, LINK_TRAPPED);
+        // In case of timeout, return error.
+        if message_type == TIMEOUT {
+            return Err(ReceiveError::Timeout);
+        }
+        if !S::TRACE_CONTEXT {
+            trace::unread_header();
+        }
+        S::decode().map_err(|err| err.into())
+    }
+}
+
+impl<M, S> Mailbox<M, S>
+where
+    M: 'static,
+    S: Serializer<M>,
+{
     /// Gets next message from process' mailbox.
     ///
     /// If the mailbox is empty, this function will block until a new message arrives.
+    /// [High priority](crate::Priority::High) messages are returned before all other messages.
     ///
     /// # Panics
     ///
     /// This function will panic if the received message can't be deserialized into `M`
     /// with serializer `S`.
     pub fn receive(&self) -> M {
-        self.receive_(Some(&[1]), None).unwrap()
+        self.receive_untagged(None).unwrap()
+    }
+
+    /// Returns a future that resolves to the next message of process' mailbox.
+    ///
+    /// The future can be awaited inside of [`executor::block_on`](crate::executor::block_on). It
+    /// takes the same messages as [`receive`](Self::receive).
+    ///
+    /// # Panics
+    ///
+    /// The future will panic if the received message can't be deserialized into `M` with
+    /// serializer `S`.
+    pub fn recv(&self) -> ReceiveFuture<'_, M, S> {
+        ReceiveFuture { mailbox: self }
     }
 
     /// Gets next message from process' mailbox that is tagged with one of the `tags`.
@@ -62,8 +149,166 @@ where
     }
 
     /// Same as `receive`, but only waits for the duration of timeout for the message.
+    ///
+    /// Like `receive`, it only takes untagged messages out of the mailbox.
     pub fn receive_timeout(&self, timeout: Duration) -> Result<M, ReceiveError> {
-        self.receive_(None, Some(timeout))
+        self.receive_untagged(Some(timeout))
+    }
+
+    /// Gets next message from process' mailbox and passes it to the matching method of `handler`.
+    ///
+    /// The message type needs to implement [`Dispatch`], usually by deriving
+    /// [`MessageEnum`](crate::MessageEnum).
+    pub fn receive_dispatch<H>(&self, handler: &mut H)
+    where
+        H: ?Sized,
+        M: Dispatch<H>,
+    {
+        self.receive().dispatch(handler);
+    }
+
+    /// Same as [`receive_dispatch`](Self::receive_dispatch), but only waits for the duration of
+    /// timeout for the message.
+    pub fn receive_dispatch_timeout<H>(
+        &self,
+        handler: &mut H,
+        timeout: Duration,
+    ) -> Result<(), ReceiveError>
+    where
+        H: ?Sized,
+        M: Dispatch<H>,
+    {
+        self.receive_timeout(timeout)?.dispatch(handler);
+        Ok(())
+    }
+
+    /// Gets next message from process' mailbox, without blocking.
+    ///
+    /// Returns `None` if there is no message in the mailbox.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be deserialized into `M`
+    /// with serializer `S`.
+    pub fn try_receive(&self) -> Option<M> {
+        match self.receive_untagged(Some(Duration::ZERO)) {
+            Ok(message) => Some(message),
+            Err(ReceiveError::Timeout) => None,
+            Err(err) => panic!("{}: {:?}", err, err),
+        }
+    }
+
+    /// Takes all messages out of process' mailbox, without blocking.
+    ///
+    /// Like `receive`, it only takes untagged messages and returns
+    /// [high priority](crate::Priority::High) messages first. This can be used by shutdown
+    /// handlers to process or log pending messages before the process finishes.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if one of the messages can't be deserialized into `M`
+    /// with serializer `S`.
+    pub fn drain(&self) -> Vec<M> {
+        std::iter::from_fn(|| self.try_receive()).collect()
+    }
+
+    /// Returns the number of messages waiting in process' mailbox.
+    ///
+    /// Only messages taken by [`receive`](Self::receive) are counted, the untagged and
+    /// [high priority](crate::Priority::High) ones. The runtime doesn't expose the size of the mailbox, so
+    /// all waiting messages are taken out of it and kept inside of the process until they are
+    /// received.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if one of the messages can't be deserialized into `M`
+    /// with serializer `S`.
+    pub fn len(&self) -> usize {
+        let tags = [Tag::none().id(), Tag::high_priority().id()];
+        loop {
+            match self.receive_host(Some(&tags), Some(Duration::ZERO)) {
+                Ok(message) => defer(message),
+                Err(ReceiveError::Timeout) => break,
+                Err(err) => panic!("{}: {:?}", err, err),
+            }
+        }
+        DEFERRED.with(|deferred| {
+            deferred
+                .borrow()
+                .iter()
+                .filter(|deferred| tags.contains(&deferred.tag.id()) && deferred.message.is::<M>())
+                .count()
+        })
+    }
+
+    /// Returns `true` if there are no messages waiting in process' mailbox.
+    ///
+    /// See [`len`](Self::len) for details.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a clone of the next message, without taking it out of process' mailbox.
+    ///
+    /// The returned message is the one the next [`receive`](Self::receive) would return. If the
+    /// mailbox is empty, this function will block until a new message arrives. Like
+    /// [`len`](Self::len), it takes all waiting messages out of the host.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if one of the messages can't be deserialized into `M`
+    /// with serializer `S`.
+    pub fn peek(&self) -> M
+    where
+        M: Clone,
+    {
+        self.peek_(None).unwrap()
+    }
+
+    /// Same as [`peek`](Self::peek), but only waits for the duration of timeout for a message.
+    pub fn peek_timeout(&self, timeout: Duration) -> Result<M, ReceiveError>
+    where
+        M: Clone,
+    {
+        self.peek_(Some(timeout))
+    }
+
+    fn peek_(&self, timeout: Option<Duration>) -> Result<M, ReceiveError>
+    where
+        M: Clone,
+    {
+        let high = Tag::high_priority();
+        let tags = [Tag::none().id(), high.id()];
+        if self.is_empty() {
+            defer(self.receive_host(Some(&tags), timeout)?);
+        }
+        DEFERRED.with(|deferred| {
+            let deferred = deferred.borrow();
+            let next = |tags: &[i64]| {
+                deferred
+                    .iter()
+                    .filter(|deferred| tags.contains(&deferred.tag.id()))
+                    .find_map(|deferred| deferred.message.downcast_ref::<M>())
+            };
+            // High priority messages are received first.
+            let message = next(&[high.id()]).or_else(|| next(&tags)).unwrap();
+            Ok(message.clone())
+        })
+    }
+
+    /// Puts `message` back to the front of process' mailbox.
+    ///
+    /// The message is put back untagged, so it's returned by the next receive that takes untagged
+    /// messages, like [`receive`](Self::receive) or [`LinkMailbox::receive`]. Only
+    /// [high priority](crate::Priority::High) messages are received before it, receives waiting
+    /// for other tags leave it in place. This can be used to defer a message after looking at it.
+    pub fn requeue(&self, message: M) {
+        let deferred = Deferred {
+            tag: Tag::none(),
+            context: trace::current(),
+            message: Box::new(message),
+        };
+        DEFERRED.with(|queue| queue.borrow_mut().push_front(deferred));
     }
 
     /// Same as `tag_receive`, but only waits for the duration of timeout for the message.
@@ -81,36 +326,126 @@ where
         }
     }
 
-    fn receive_(&self, tags: Option<&[i64]>, timeout: Option<Duration>) -> Result<M, ReceiveError> {
-        let tags = if let Some(tags) = tags { tags } else { &[] };
-        let timeout_ms = match timeout {
-            Some(timeout) => timeout.as_millis() as u64,
-            None => u64::MAX,
-        };
-        let message_type = unsafe { message::receive(tags.as_ptr(), tags.len(), timeout_ms) };
-        // Mailbox can't receive LINK_TRAPPED messages.
-        assert_ne!(message_type, LINK_TRAPPED);
-        // In case of timeout, return error.
-        if message_type == TIMEOUT {
-            return Err(ReceiveError::Timeout);
-        }
-        S::decode().map_err(|err| err.into())
+    /// Waits for the duration of timeout for a message tagged with `tag`, e.g. the reply to a
+    /// request.
+    ///
+    /// All other messages stay in the mailbox in their order of arrival, also if the timeout is
+    /// reached.
+    pub fn receive_tagged_timeout(&self, tag: Tag, timeout: Duration) -> Result<M, ReceiveError> {
+        self.tag_receive_timeout(Some(&[tag]), timeout)
     }
 
-    /// Create a mailbox with a specific type.
+    /// Gets the next message from process' mailbox that satisfies the `predicate`.
     ///
-    /// ### Safety
+    /// Messages that don't satisfy the `predicate` are kept in the order of arrival and will be
+    /// returned by the next receive calls. If no matching message exists, this function will
+    /// block until a new message arrives. Message priorities are not considered, the first
+    /// matching message is returned.
     ///
-    /// It's not safe to mix different types of mailboxes inside one process. This function should
-    /// never be used directly. The only reason it's public is that it's used inside the `main`
-    /// macro and needs to be available outside this crate.
-    pub unsafe fn new() -> Self {
-        Self {
-            phantom: PhantomData {},
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be deserialized into `M`
+    /// with serializer `S`.
+    pub fn receive_where<P>(&self, predicate: P) -> M
+    where
+        P: Fn(&M) -> bool,
+    {
+        self.receive_where_(predicate, None).unwrap()
+    }
+
+    /// Same as [`receive_where`](Self::receive_where), but only waits for the duration of timeout
+    /// for a matching message.
+    pub fn receive_where_timeout<P>(
+        &self,
+        predicate: P,
+        timeout: Duration,
+    ) -> Result<M, ReceiveError>
+    where
+        P: Fn(&M) -> bool,
+    {
+        self.receive_where_(predicate, Some(timeout))
+    }
+
+    fn receive_where_<P>(&self, predicate: P, timeout: Option<Duration>) -> Result<M, ReceiveError>
+    where
+        P: Fn(&M) -> bool,
+    {
+        let tags = [Tag::none().id(), Tag::high_priority().id()];
+        if let Some(message) = take_deferred(Some(&tags), &predicate) {
+            return Ok(message);
+        }
+        let deadline = timeout.map(Deadline::after);
+        loop {
+            let timeout = deadline.map(|deadline| deadline.remaining());
+            let message = self.receive_host(Some(&tags), timeout)?;
+            if predicate(&message) {
+                return Ok(message);
+            }
+            defer(message);
+        }
+    }
+
+    /// Takes the next untagged message, preferring high priority ones.
+    fn receive_untagged(&self, timeout: Option<Duration>) -> Result<M, ReceiveError> {
+        let high = Tag::high_priority().id();
+        match self.receive_(Some(&[high]), Some(Duration::ZERO)) {
+            Err(ReceiveError::Timeout) => (),
+            result => return result,
+        }
+        self.receive_(Some(&[Tag::none().id(), high]), timeout)
+    }
+
+    fn receive_(&self, tags: Option<&[i64]>, timeout: Option<Duration>) -> Result<M, ReceiveError> {
+        if let Some(message) = take_deferred(tags, |_: &M| true) {
+            return Ok(message);
         }
+        self.receive_host(tags, timeout)
     }
 }
 
+/// Puts the last received `message` aside, keeping its tag and trace context.
+fn defer<M: 'static>(message: M) {
+    let deferred = Deferred {
+        tag: Tag::from(unsafe { message::get_tag() }),
+        context: trace::received(),
+        message: Box::new(message),
+    };
+    DEFERRED.with(|queue| queue.borrow_mut().push_back(deferred));
+}
+
+/// Removes the first deferred message of type `M` that is tagged with one of the `tags` and
+/// satisfies the `predicate`, restoring its trace context.
+fn take_deferred<M, P>(tags: Option<&[i64]>, predicate: P) -> Option<M>
+where
+    M: 'static,
+    P: Fn(&M) -> bool,
+{
+    DEFERRED.with(|deferred| {
+        let mut deferred = deferred.borrow_mut();
+        let index = deferred.iter().position(|deferred| {
+            let tag_matches = match tags {
+                Some(tags) if !tags.is_empty() => tags.contains(&deferred.tag.id()),
+                _ => true,
+            };
+            tag_matches
+                && matches!(deferred.message.downcast_ref::<M>(), Some(message) if predicate(message))
+        })?;
+        let deferred = deferred.remove(index)?;
+        if deferred.context.is_some() {
+            trace::set_current(deferred.context);
+        }
+        deferred.message.downcast().ok().map(|message| *message)
+    })
+}
+
+/// A message that can be passed to the matching method of a handler `H`.
+///
+/// Usually implemented with `#[derive(MessageEnum)]`, see [`MessageEnum`](crate::MessageEnum).
+pub trait Dispatch<H: ?Sized> {
+    /// Calls the method of `handler` that handles this message.
+    fn dispatch(self, handler: &mut H);
+}
+
 /// Error while receiving a message.
 #[derive(Error, Debug)]
 pub enum ReceiveError {
@@ -118,11 +453,21 @@ pub enum ReceiveError {
     DeserializationFailed(#[from] DecodeError),
     #[error("Timed out while waiting for message")]
     Timeout,
+    /// The process shed the request because of its
+    /// [`OverloadPolicy`](crate::process::OverloadPolicy).
+    #[error("Process is overloaded")]
+    Overloaded,
 }
 
 /// A special Mailbox that can catch if links trapped.
+///
+/// It's created with [`Mailbox::catch_link_failure`]. Instead of dying together with a failed
+/// linked process, the process receives a [`LinkTrapped`] error containing the tag of the link.
+///
+/// The lunatic runtime doesn't carry any information about why a linked process died, so the
+/// tag is the only data delivered.
 #[derive(Debug)]
-pub(crate) struct LinkMailbox<M, S = Bincode>
+pub struct LinkMailbox<M, S = Bincode>
 where
     S: Serializer<M>,
 {
@@ -145,6 +490,26 @@ where
         }
     }
 
+    /// Returns a reference to the currently running process
+    pub fn this(&self) -> Process<M, S> {
+        Process::new(host::node_id(), host::process_id())
+    }
+
+    /// Gets next message or link failure from process' mailbox.
+    ///
+    /// Link failures can carry any tag, so this function takes the first available message
+    /// regardless of its tag.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the received message can't be deserialized into `M`.
+    pub fn receive(&self) -> Result<M, LinkTrapped>
+    where
+        M: 'static,
+    {
+        self.tag_receive(None)
+    }
+
     /// Gets next message from process' mailbox that is tagged with one of the `tags`.
     ///
     /// If no such message exists, this function will block until a new message arrives.
@@ -153,7 +518,10 @@ where
     /// # Panics
     ///
     /// This function will panic if the received message can't be deserialized into `M`.
-    pub fn tag_receive(&self, tags: Option<&[Tag]>) -> Result<M, LinkTrapped> {
+    pub fn tag_receive(&self, tags: Option<&[Tag]>) -> Result<M, LinkTrapped>
+    where
+        M: 'static,
+    {
         match tags {
             Some(tags) => {
                 let tags: Vec<i64> = tags.iter().map(|tag| tag.id()).collect();
@@ -163,17 +531,23 @@ where
         }
     }
 
-    fn receive_(
+    pub(crate) fn receive_(
         &self,
         tags: Option<&[i64]>,
         timeout: Option<Duration>,
-    ) -> Result<Result<M, LinkTrapped>, ReceiveError> {
+    ) -> Result<Result<M, LinkTrapped>, ReceiveError>
+    where
+        M: 'static,
+    {
         let tags = if let Some(tags) = tags { tags } else { &[] };
+        if let Some(message) = take_deferred(Some(tags), |_: &M| true) {
+            return Ok(Ok(message));
+        }
         let timeout_ms = match timeout {
             Some(timeout) => timeout.as_millis() as u64,
             None => u64::MAX,
         };
-        let message_type = unsafe { message::receive(tags.as_ptr(), tags.len(), timeout_ms) };
+        let message_type = host::receive(tags, timeout_ms);
         // If we received a LINK_TRAPPED message return
         if message_type == LINK_TRAPPED {
             return Ok(Err(LinkTrapped(Tag::from(unsafe { message::get_tag() }))));
@@ -182,6 +556,9 @@ where
         if message_type == TIMEOUT {
             return Err(ReceiveError::Timeout);
         }
+        if !S::TRACE_CONTEXT {
+            trace::unread_header();
+        }
         match S::decode() {
             Ok(message) => Ok(Ok(message)),
             Err(err) => Err(err.into()),
@@ -189,12 +566,14 @@ where
     }
 }
 
+/// A linked process failed.
 #[derive(Error, Debug)]
 #[error("The link trapped")]
-pub(crate) struct LinkTrapped(Tag);
+pub struct LinkTrapped(Tag);
 
 impl LinkTrapped {
-    pub(crate) fn tag(&self) -> Tag {
+    /// Returns the tag of the link.
+    pub fn tag(&self) -> Tag {
         self.0
     }
 }
@@ -250,7 +629,9 @@ where
     let captured = if std::mem::size_of::<C>() == 0 {
         unsafe { std::mem::MaybeUninit::<C>::zeroed().assume_init() }
     } else {
-        unsafe { Mailbox::<C, S>::new() }.receive()
+        unsafe { Mailbox::<C, S>::new() }
+            .receive_host(Some(&[Tag::none().id()]), None)
+            .unwrap()
     };
     let mailbox = unsafe { Mailbox::new() };
     let function: fn(C, Mailbox<M, S>) = unsafe { std::mem::transmute(function) };
@@ -0,0 +1,128 @@
+//! Metrics of the current process and the node.
+//!
+//! Applications can emit their own [counters](counter), [gauges](gauge) and
+//! [histograms](histogram). They are recorded by the runtime and show up next to the metrics of
+//! the node, e.g. in its Prometheus endpoint. The values emitted by all processes under the same
+//! name are combined into one metric.
+//!
+//! The runtime doesn't expose metrics about other processes yet, like the number of running
+//! processes on a node or the number of messages waiting in a mailbox. Only the values that a
+//! process can observe about itself are available.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::metrics;
+//!
+//! metrics::counter("app.requests").increment(1);
+//! metrics::gauge("app.queue_length").set(12.0);
+//! metrics::histogram("app.latency_ms").record(3.5);
+//! println!("Process uses {} bytes of memory", metrics::memory_usage());
+//! ```
+
+use crate::host::api::metrics;
+
+/// Size of a WebAssembly memory page in bytes.
+const PAGE_SIZE: usize = 64 * 1024;
+
+/// Returns the size of the current process' memory in bytes.
+///
+/// WebAssembly memories only grow, so this is the peak memory usage of the process and not the
+/// amount of memory that is currently allocated. It can be compared against the limit set with
+/// [`ProcessConfig::set_max_memory`](crate::ProcessConfig::set_max_memory).
+pub fn memory_usage() -> usize {
+    core::arch::wasm32::memory_size(0) * PAGE_SIZE
+}
+
+/// Returns the counter `name`.
+pub fn counter(name: &str) -> Counter {
+    Counter {
+        name: name.to_owned(),
+    }
+}
+
+/// Returns the gauge `name`.
+pub fn gauge(name: &str) -> Gauge {
+    Gauge {
+        name: name.to_owned(),
+    }
+}
+
+/// Returns the histogram `name`.
+pub fn histogram(name: &str) -> Histogram {
+    Histogram {
+        name: name.to_owned(),
+    }
+}
+
+/// A metric that only goes up, like the number of handled requests.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Counter {
+    name: String,
+}
+
+impl Counter {
+    /// Returns the name of the counter.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Increments the counter by `value`.
+    pub fn increment(&self, value: u64) {
+        let name = self.name.as_bytes();
+        match value {
+            1 => unsafe { metrics::increment_counter(name.as_ptr(), name.len()) },
+            _ => unsafe { metrics::counter(name.as_ptr(), name.len(), value) },
+        }
+    }
+}
+
+/// A metric that can go up and down, like the number of open connections.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Gauge {
+    name: String,
+}
+
+impl Gauge {
+    /// Returns the name of the gauge.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Sets the gauge to `value`.
+    pub fn set(&self, value: f64) {
+        let name = self.name.as_bytes();
+        unsafe { metrics::gauge(name.as_ptr(), name.len(), value) };
+    }
+
+    /// Increments the gauge by `value`.
+    pub fn increment(&self, value: f64) {
+        let name = self.name.as_bytes();
+        unsafe { metrics::increment_gauge(name.as_ptr(), name.len(), value) };
+    }
+
+    /// Decrements the gauge by `value`.
+    pub fn decrement(&self, value: f64) {
+        let name = self.name.as_bytes();
+        unsafe { metrics::decrement_gauge(name.as_ptr(), name.len(), value) };
+    }
+}
+
+/// A metric that records the distribution of values, like the latencies of requests.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Histogram {
+    name: String,
+}
+
+impl Histogram {
+    /// Returns the name of the histogram.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Records `value` in the histogram.
+    pub fn record(&self, value: f64) {
+        let name = self.name.as_bytes();
+        unsafe { metrics::histogram(name.as_ptr(), name.len(), value) };
+    }
+}
@@ -4,12 +4,22 @@ use crate::{
     error::LunaticError,
     host::{self, api::distributed::node_id},
     serializer::Serializer,
-    Process,
+    Process, ProcessConfig,
 };
 
 /// A compiled instance of a WebAssembly module.
 ///
 /// Creating a module will also JIT compile it, this can be a compute intensive tasks.
+///
+/// Modules can be loaded at runtime, e.g. to run plugins inside of sandboxed processes:
+///
+/// ```no_run
+/// let data = std::fs::read("plugin.wasm").unwrap();
+/// let module = WasmModule::new(&data).unwrap();
+/// let mut config = ProcessConfig::new();
+/// config.set_max_memory(10_000_000);
+/// let plugin: Process<()> = module.spawn_config(&config, "run", &[Param::I32(42)]).unwrap();
+/// ```
 pub enum WasmModule {
     Module(u64),
     Inherit,
@@ -44,7 +54,7 @@ impl WasmModule {
         if result == -1 {
             Err(LunaticError::PermissionDenied)
         } else if result != 0 {
-            Err(LunaticError::from(module_or_error_id))
+            Err(LunaticError::InvalidModule(module_or_error_id))
         } else {
             Ok(WasmModule::Module(module_or_error_id))
         }
@@ -64,6 +74,8 @@ impl WasmModule {
 
     /// Spawn a new process and use `function` as the entry point. If the function takes arguments
     /// the passed in `params` need to exactly match their types.
+    ///
+    /// The configuration of the current process is inherited.
     pub fn spawn<M, S>(
         &self,
         function: &str,
@@ -72,43 +84,70 @@ impl WasmModule {
     where
         S: Serializer<M>,
     {
-        let mut process_or_error_id = 0;
-        let params: Vec<u8> = params_to_vec(params);
-        let result = unsafe {
-            host::api::process::spawn(
-                0,
-                -1,
-                self.id(),
-                function.as_ptr(),
-                function.len(),
-                params.as_ptr(),
-                params.len(),
-                &mut process_or_error_id as *mut u64,
-            )
-        };
-
-        if result == 0 {
-            Ok(unsafe { Process::new(node_id(), process_or_error_id) })
-        } else {
-            Err(LunaticError::from(process_or_error_id))
-        }
+        self.spawn_(0, None, function, params)
     }
 
     /// Spawn a new process and link it to the current one.
+    ///
+    /// If the process fails, the current process will fail too, unless it's trapping link
+    /// failures (e.g. a [`Supervisor`](crate::supervisor::Supervisor)).
     pub fn spawn_link<M, S>(
         &self,
         function: &str,
         params: &[Param],
     ) -> Result<Process<M, S>, LunaticError>
+    where
+        S: Serializer<M>,
+    {
+        self.spawn_(1, None, function, params)
+    }
+
+    /// Same as [`spawn`](Self::spawn), but the process is spawned with `config`.
+    ///
+    /// This can be used to restrict the permissions and resources of untrusted modules, e.g.
+    /// plugins.
+    pub fn spawn_config<M, S>(
+        &self,
+        config: &ProcessConfig,
+        function: &str,
+        params: &[Param],
+    ) -> Result<Process<M, S>, LunaticError>
+    where
+        S: Serializer<M>,
+    {
+        self.spawn_(0, Some(config), function, params)
+    }
+
+    /// Same as [`spawn_link`](Self::spawn_link), but the process is spawned with `config`.
+    pub fn spawn_link_config<M, S>(
+        &self,
+        config: &ProcessConfig,
+        function: &str,
+        params: &[Param],
+    ) -> Result<Process<M, S>, LunaticError>
+    where
+        S: Serializer<M>,
+    {
+        self.spawn_(1, Some(config), function, params)
+    }
+
+    fn spawn_<M, S>(
+        &self,
+        link: i64,
+        config: Option<&ProcessConfig>,
+        function: &str,
+        params: &[Param],
+    ) -> Result<Process<M, S>, LunaticError>
     where
         S: Serializer<M>,
     {
         let mut process_or_error_id = 0;
         let params: Vec<u8> = params_to_vec(params);
+        let config_id = config.map_or(-1, |config| config.id());
         let result = unsafe {
             host::api::process::spawn(
-                1,
-                -1,
+                link,
+                config_id,
                 self.id(),
                 function.as_ptr(),
                 function.len(),
@@ -121,11 +160,12 @@ impl WasmModule {
         if result == 0 {
             Ok(unsafe { Process::new(node_id(), process_or_error_id) })
         } else {
-            Err(LunaticError::from(process_or_error_id))
+            Err(LunaticError::SpawnFailed(process_or_error_id))
         }
     }
 }
 
+/// An argument passed to the entry function of a process spawned from a [`WasmModule`].
 pub enum Param {
     I32(i32),
     I64(i64),
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    distributed::{self, POLL_INTERVAL},
+    host,
+    mailbox::LinkMailbox,
+    serializer::Bincode,
+    Mailbox, Process, ReceiveError, Tag,
+};
+
+/// The reason why a monitored process went down.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DownReason {
+    /// The process failed (panicked, trapped or was killed), or it didn't exist anymore at the
+    /// time the monitor was set up.
+    Failed,
+    /// The node with this ID left the cluster, or wasn't known at the time the monitor was set
+    /// up. The monitored process may still be running, but it can't be reached anymore.
+    NodeDown(u64),
+}
+
+/// Message delivered to the monitoring process when the monitored process goes down.
+///
+/// The message is tagged with the [`tag`](Monitor::tag) of the [`Monitor`] that produced it, so
+/// it can be selectively received with [`Mailbox::tag_receive`] or awaited with
+/// [`Monitor::wait`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProcessDown {
+    /// ID of the process that went down.
+    pub id: u64,
+    /// Tag of the monitor that observed the process.
+    pub tag: Tag,
+    /// Why the process went down.
+    pub reason: DownReason,
+}
+
+/// A monitor observing another process, created with [`Process::monitor`].
+///
+/// Contrary to links, monitors are one-directional. The monitoring process is never killed when
+/// the monitored one fails, it will only receive a [`ProcessDown`] message. Like links, monitors
+/// are only triggered if the monitored process fails and not when it finishes normally.
+///
+/// Monitors are implemented with a helper process that is spawned on the same node as the
+/// monitored process. This means that the caller needs to have the permission to spawn processes.
+///
+/// Processes on other nodes can be monitored too. In that case a second helper process on the node
+/// of the caller also checks if the remote node is still part of the cluster, and reports
+/// [`DownReason::NodeDown`] if it leaves. The list of nodes is polled, so it can take a moment
+/// until a lost node is noticed.
+///
+/// The monitor is stopped when it's dropped, so it needs to be kept around for as long as the
+/// process should be observed.
+#[derive(Debug)]
+#[must_use = "the monitor is stopped when it's dropped"]
+pub struct Monitor {
+    watcher: Process<(), Bincode>,
+    tag: Tag,
+}
+
+impl Monitor {
+    pub(crate) fn new(node_id: u64, process_id: u64) -> Self {
+        let tag = Tag::new();
+        let this = Process::<ProcessDown, Bincode>::this();
+        let watcher = if node_id == distributed::node_id() {
+            Process::spawn((this, tag, process_id), watcher)
+        } else {
+            Process::spawn((this, tag, node_id, process_id), node_watcher)
+        };
+        Monitor { watcher, tag }
+    }
+
+    /// Returns the tag used for the [`ProcessDown`] message.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Blocks until the monitored process goes down.
+    pub fn wait(&self) -> ProcessDown {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ProcessDown, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[self.tag]))
+    }
+
+    /// Same as [`wait`](Self::wait), but only waits for the duration of timeout.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<ProcessDown, ReceiveError> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ProcessDown, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive_timeout(Some(&[self.tag]), timeout)
+    }
+
+    /// Stops the monitor, same as dropping it.
+    ///
+    /// A [`ProcessDown`] message that was already delivered, but not yet received, stays in the
+    /// mailbox.
+    pub fn demonitor(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        // If the watcher already reported the process as down, the message is simply dropped.
+        self.watcher.send(());
+    }
+}
+
+/// Entry point of the helper process that watches the monitored process.
+fn watcher((parent, tag, id): (Process<ProcessDown, Bincode>, Tag, u64), _: Mailbox<()>) {
+    // The watcher should survive the death of the monitored process.
+    unsafe { host::api::process::die_when_link_dies(0) };
+    unsafe { host::api::process::link(tag.id(), id) };
+
+    let mailbox: LinkMailbox<(), Bincode> = unsafe { LinkMailbox::new() };
+    match mailbox.tag_receive(None) {
+        // Demonitored, remove the link so that the monitored process doesn't get notified.
+        Ok(()) => unsafe { host::api::process::unlink(id) },
+        Err(_) => parent.tag_send(
+            tag,
+            ProcessDown {
+                id,
+                tag,
+                reason: DownReason::Failed,
+            },
+        ),
+    }
+}
+
+/// Entry point of the local helper process that watches a process on another node.
+///
+/// It forwards the report of the remote watcher and reports a lost node itself.
+fn node_watcher(
+    (parent, tag, node_id, id): (Process<ProcessDown, Bincode>, Tag, u64, u64),
+    mailbox: Mailbox<()>,
+) {
+    let node_down = ProcessDown {
+        id,
+        tag,
+        reason: DownReason::NodeDown(node_id),
+    };
+    if !distributed::nodes().contains(&node_id) {
+        return parent.tag_send(tag, node_down);
+    }
+    let this = Process::<ProcessDown, Bincode>::this();
+    let remote = Process::<(), Bincode>::spawn_node(node_id, (this, tag, id), watcher);
+
+    // Temporarily cast to right mailbox type.
+    let down_mailbox: Mailbox<ProcessDown, Bincode> = unsafe { Mailbox::new() };
+    loop {
+        if let Ok(down) = down_mailbox.tag_receive_timeout(Some(&[tag]), POLL_INTERVAL) {
+            return parent.tag_send(tag, down);
+        }
+        // Any untagged message stops the monitor.
+        if mailbox.receive_timeout(Duration::ZERO).is_ok() {
+            return remote.send(());
+        }
+        if !distributed::nodes().contains(&node_id) {
+            return parent.tag_send(tag, node_down);
+        }
+    }
+}
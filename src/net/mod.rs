@@ -1,9 +1,13 @@
 //! Networking related functions.
+//!
+//! Only plain TCP, UDP and WebSocket connections are provided. The lunatic runtime doesn't expose a TLS
+//! capability to guest processes yet, so encrypted connections are not supported by this module.
 
 mod resolver;
 mod tcp_listener;
 mod tcp_stream;
 mod udp;
+mod websocket;
 
 use std::io::{Error, ErrorKind, Result};
 use std::iter::Cloned;
@@ -13,8 +17,10 @@ use std::slice::Iter;
 
 pub use resolver::{resolve, resolve_timeout, SocketAddrIterator};
 pub use tcp_listener::TcpListener;
-pub use tcp_stream::TcpStream;
+pub use tcp_stream::{TcpReadHalf, TcpStream, TcpWriteHalf};
 pub use udp::UdpSocket;
+pub(crate) use websocket::accept_key;
+pub use websocket::{WebSocket, WebSocketMessage};
 
 /// A trait for objects which can be converted or resolved to one or more
 /// [`SocketAddr`] values.
@@ -171,6 +171,119 @@ impl TcpStream {
         let lunatic_error = LunaticError::from(id);
         Err(Error::new(ErrorKind::Other, lunatic_error))
     }
+
+    /// Sets the read timeout.
+    ///
+    /// If the value specified is [`None`], then [`read`](Read::read) calls will block
+    /// indefinitely. Otherwise a read that takes longer than the timeout will return an error.
+    /// The timeout is shared between all clones of the stream.
+    pub fn set_read_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        unsafe { host::api::networking::set_read_timeout(self.id, to_timeout_ms(duration)) };
+        Ok(())
+    }
+
+    /// Sets the write timeout.
+    ///
+    /// If the value specified is [`None`], then [`write`](Write::write) calls will block
+    /// indefinitely. Otherwise a write that takes longer than the timeout will return an error.
+    /// The timeout is shared between all clones of the stream.
+    pub fn set_write_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        unsafe { host::api::networking::set_write_timeout(self.id, to_timeout_ms(duration)) };
+        Ok(())
+    }
+
+    /// Returns the read timeout of this stream.
+    pub fn read_timeout(&self) -> Result<Option<Duration>> {
+        let timeout_ms = unsafe { host::api::networking::get_read_timeout(self.id) };
+        Ok(from_timeout_ms(timeout_ms))
+    }
+
+    /// Returns the write timeout of this stream.
+    pub fn write_timeout(&self) -> Result<Option<Duration>> {
+        let timeout_ms = unsafe { host::api::networking::get_write_timeout(self.id) };
+        Ok(from_timeout_ms(timeout_ms))
+    }
+
+    /// Splits the stream into a read and a write half.
+    ///
+    /// Both halves are independent handles to the same socket and can be sent to different
+    /// processes, e.g. one process reading requests from the connection while another one writes
+    /// the responses. The socket is closed once both halves are dropped, dropping only the write
+    /// half doesn't shut down the writing direction of the connection.
+    pub fn split(self) -> (TcpReadHalf, TcpWriteHalf) {
+        let write = self.clone();
+        (TcpReadHalf(self), TcpWriteHalf(write))
+    }
+}
+
+/// The read half of a [`TcpStream`], created by [`split`](TcpStream::split).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcpReadHalf(TcpStream);
+
+impl TcpReadHalf {
+    /// Sets the read timeout of the stream.
+    ///
+    /// See [`TcpStream::set_read_timeout`].
+    pub fn set_read_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        self.0.set_read_timeout(duration)
+    }
+
+    /// Returns the read timeout of the stream.
+    pub fn read_timeout(&self) -> Result<Option<Duration>> {
+        self.0.read_timeout()
+    }
+}
+
+impl Read for TcpReadHalf {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+/// The write half of a [`TcpStream`], created by [`split`](TcpStream::split).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TcpWriteHalf(TcpStream);
+
+impl TcpWriteHalf {
+    /// Sets the write timeout of the stream.
+    ///
+    /// See [`TcpStream::set_write_timeout`].
+    pub fn set_write_timeout(&self, duration: Option<Duration>) -> Result<()> {
+        self.0.set_write_timeout(duration)
+    }
+
+    /// Returns the write timeout of the stream.
+    pub fn write_timeout(&self) -> Result<Option<Duration>> {
+        self.0.write_timeout()
+    }
+}
+
+impl Write for TcpWriteHalf {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> Result<usize> {
+        self.0.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.0.flush()
+    }
+}
+
+fn to_timeout_ms(duration: Option<Duration>) -> u64 {
+    match duration {
+        Some(duration) => duration.as_millis() as u64,
+        None => u64::MAX,
+    }
+}
+
+fn from_timeout_ms(timeout_ms: u64) -> Option<Duration> {
+    match timeout_ms {
+        u64::MAX => None,
+        timeout_ms => Some(Duration::from_millis(timeout_ms)),
+    }
 }
 
 impl Write for TcpStream {
@@ -1,7 +1,7 @@
 use std::{
     cell::UnsafeCell,
     io::{Error, ErrorKind, Result},
-    net::SocketAddr,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 
 use super::SocketAddrIterator;
@@ -514,4 +514,39 @@ impl UdpSocket {
     pub fn take_error(&self) -> Result<Option<LunaticError>> {
         Ok(None)
     }
+    /// Joins an IPv4 multicast group.
+    ///
+    /// Multicast is not supported by the lunatic runtime yet, this function always returns an
+    /// error of kind [`ErrorKind::Unsupported`].
+    pub fn join_multicast_v4(&self, _multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> Result<()> {
+        Err(multicast_unsupported())
+    }
+    /// Joins an IPv6 multicast group.
+    ///
+    /// Multicast is not supported by the lunatic runtime yet, this function always returns an
+    /// error of kind [`ErrorKind::Unsupported`].
+    pub fn join_multicast_v6(&self, _multiaddr: &Ipv6Addr, _interface: u32) -> Result<()> {
+        Err(multicast_unsupported())
+    }
+    /// Leaves an IPv4 multicast group.
+    ///
+    /// Multicast is not supported by the lunatic runtime yet, this function always returns an
+    /// error of kind [`ErrorKind::Unsupported`].
+    pub fn leave_multicast_v4(&self, _multiaddr: &Ipv4Addr, _interface: &Ipv4Addr) -> Result<()> {
+        Err(multicast_unsupported())
+    }
+    /// Leaves an IPv6 multicast group.
+    ///
+    /// Multicast is not supported by the lunatic runtime yet, this function always returns an
+    /// error of kind [`ErrorKind::Unsupported`].
+    pub fn leave_multicast_v6(&self, _multiaddr: &Ipv6Addr, _interface: u32) -> Result<()> {
+        Err(multicast_unsupported())
+    }
+}
+
+fn multicast_unsupported() -> Error {
+    Error::new(
+        ErrorKind::Unsupported,
+        "multicast is not supported by the lunatic runtime",
+    )
 }
@@ -0,0 +1,369 @@
+use std::io::{BufReader, Error, ErrorKind, Read, Result, Write};
+
+use super::TcpStream;
+use crate::{
+    http::{find_header, read_head, write_head, Url},
+    rand,
+};
+
+/// GUID used to compute the `Sec-WebSocket-Accept` header, defined by RFC 6455.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+/// Maximum size of a received message, including all of its fragments.
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A message sent or received over a [`WebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WebSocketMessage {
+    /// A UTF-8 text message.
+    Text(String),
+    /// A binary message.
+    Binary(Vec<u8>),
+    /// A ping. Received pings are answered automatically with a pong.
+    Ping(Vec<u8>),
+    /// A pong, the answer to a ping.
+    Pong(Vec<u8>),
+    /// A request to close the connection, with an optional status code and reason.
+    Close(Option<(u16, String)>),
+}
+
+/// A WebSocket connection, as specified in [IETF RFC 6455].
+///
+/// A client connection is opened with [`connect`](WebSocket::connect). On the server side,
+/// connections are accepted by answering a request to the [`http::Server`](crate::http::Server)
+/// with [`Response::websocket`](crate::http::Response::websocket).
+///
+/// Fragmented messages are reassembled before being returned from
+/// [`receive`](WebSocket::receive).
+///
+/// [IETF RFC 6455]: https://tools.ietf.org/html/rfc6455
+#[derive(Debug)]
+pub struct WebSocket {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    // Clients need to mask the frames they send, servers must not.
+    client: bool,
+    close_sent: bool,
+    // Opcode and payload of a fragmented message that is being received.
+    fragmented: Option<(u8, Vec<u8>)>,
+}
+
+impl WebSocket {
+    /// Opens a WebSocket connection to `url`, e.g. `ws://127.0.0.1:8080/chat`.
+    ///
+    /// Only `ws://` URLs are supported, because the runtime doesn't provide TLS yet.
+    pub fn connect(url: &str) -> Result<Self> {
+        let url = Url::parse(url, "ws")?;
+        let stream = TcpStream::connect(url.address())?;
+
+        let mut key = [0; 16];
+        rand::fill_bytes(&mut key);
+        let key = base64(&key);
+        let headers = [
+            ("Host".to_owned(), url.authority.clone()),
+            ("Upgrade".to_owned(), "websocket".to_owned()),
+            ("Connection".to_owned(), "Upgrade".to_owned()),
+            ("Sec-WebSocket-Key".to_owned(), key.clone()),
+            ("Sec-WebSocket-Version".to_owned(), "13".to_owned()),
+        ];
+        let mut writer = stream.clone();
+        write_head(
+            &mut writer,
+            &format!("GET {} HTTP/1.1", url.target),
+            &headers,
+        )?;
+
+        let mut reader = BufReader::new(stream);
+        let (status, headers) = read_head(&mut reader)?;
+        if status.split(' ').nth(1) != Some("101") {
+            return Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("WebSocket upgrade rejected: {}", status),
+            ));
+        }
+        if find_header(&headers, "Sec-WebSocket-Accept") != Some(&accept_key(&key)) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "invalid Sec-WebSocket-Accept header",
+            ));
+        }
+        Ok(WebSocket::new(reader, writer, true))
+    }
+
+    pub(crate) fn new(reader: BufReader<TcpStream>, writer: TcpStream, client: bool) -> Self {
+        WebSocket {
+            reader,
+            writer,
+            client,
+            close_sent: false,
+            fragmented: None,
+        }
+    }
+
+    /// Sends a message.
+    pub fn send(&mut self, message: WebSocketMessage) -> Result<()> {
+        match message {
+            WebSocketMessage::Text(text) => self.write_frame(0x1, text.as_bytes()),
+            WebSocketMessage::Binary(data) => self.write_frame(0x2, &data),
+            WebSocketMessage::Ping(data) => self.write_frame(0x9, &data),
+            WebSocketMessage::Pong(data) => self.write_frame(0xA, &data),
+            WebSocketMessage::Close(reason) => {
+                let mut payload = Vec::new();
+                if let Some((code, reason)) = reason {
+                    payload.extend_from_slice(&code.to_be_bytes());
+                    payload.extend_from_slice(reason.as_bytes());
+                }
+                self.close_sent = true;
+                self.write_frame(0x8, &payload)
+            }
+        }
+    }
+
+    /// Sends a close message with `code` and `reason`.
+    ///
+    /// The connection should be kept until the other side answers with a
+    /// [`Close`](WebSocketMessage::Close) message of their own.
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<()> {
+        self.send(WebSocketMessage::Close(Some((code, reason.to_owned()))))
+    }
+
+    /// Receives the next message.
+    ///
+    /// If the other side closes the connection, the received close message is answered
+    /// automatically (unless a close message was already sent) before it's returned.
+    ///
+    /// Control messages (pings, pongs and close) can be returned while a fragmented message is
+    /// being received. The fragments received so far are kept for the next call.
+    pub fn receive(&mut self) -> Result<WebSocketMessage> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                // Continuation
+                0x0 => {
+                    let (_, data) = self
+                        .fragmented
+                        .as_mut()
+                        .ok_or_else(|| protocol_error("unexpected continuation frame"))?;
+                    if data.len() as u64 + payload.len() as u64 > MAX_MESSAGE_SIZE {
+                        return Err(protocol_error("WebSocket message is too large"));
+                    }
+                    data.extend_from_slice(&payload);
+                    if fin {
+                        let (opcode, data) = self.fragmented.take().unwrap();
+                        return data_message(opcode, data);
+                    }
+                }
+                0x1 | 0x2 => {
+                    if self.fragmented.is_some() {
+                        return Err(protocol_error("expected continuation frame"));
+                    }
+                    if fin {
+                        return data_message(opcode, payload);
+                    }
+                    self.fragmented = Some((opcode, payload));
+                }
+                0x8 => {
+                    let reason = match payload.len() {
+                        0 => None,
+                        1 => return Err(protocol_error("invalid close frame")),
+                        _ => {
+                            let code = u16::from_be_bytes([payload[0], payload[1]]);
+                            let reason = String::from_utf8(payload[2..].to_vec())
+                                .map_err(|_| protocol_error("invalid UTF-8 in close frame"))?;
+                            Some((code, reason))
+                        }
+                    };
+                    if !self.close_sent {
+                        self.close_sent = true;
+                        self.write_frame(0x8, &payload[..payload.len().min(2)])?;
+                    }
+                    return Ok(WebSocketMessage::Close(reason));
+                }
+                0x9 => {
+                    self.write_frame(0xA, &payload)?;
+                    return Ok(WebSocketMessage::Ping(payload));
+                }
+                0xA => return Ok(WebSocketMessage::Pong(payload)),
+                _ => return Err(protocol_error("unknown WebSocket opcode")),
+            }
+        }
+    }
+
+    /// Reads a single frame and returns the FIN bit, opcode and unmasked payload.
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0; 2];
+        self.reader.read_exact(&mut head)?;
+        let fin = head[0] & 0x80 != 0;
+        if head[0] & 0x70 != 0 {
+            return Err(protocol_error("reserved WebSocket bits are set"));
+        }
+        let opcode = head[0] & 0x0F;
+        let masked = head[1] & 0x80 != 0;
+        // Frames sent by the client must be masked, frames sent by the server must not be.
+        if masked == self.client {
+            return Err(protocol_error("invalid WebSocket frame masking"));
+        }
+        let length = match head[1] & 0x7F {
+            126 => {
+                let mut length = [0; 2];
+                self.reader.read_exact(&mut length)?;
+                u16::from_be_bytes(length) as u64
+            }
+            127 => {
+                let mut length = [0; 8];
+                self.reader.read_exact(&mut length)?;
+                u64::from_be_bytes(length)
+            }
+            length => length as u64,
+        };
+        if opcode & 0x8 != 0 && (!fin || length > 125) {
+            return Err(protocol_error("invalid WebSocket control frame"));
+        }
+        if length > MAX_MESSAGE_SIZE {
+            return Err(protocol_error("WebSocket message is too large"));
+        }
+        let mut mask = [0; 4];
+        if masked {
+            self.reader.read_exact(&mut mask)?;
+        }
+        let mut payload = Vec::with_capacity(length as usize);
+        (&mut self.reader).take(length).read_to_end(&mut payload)?;
+        if payload.len() as u64 != length {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "connection closed in the middle of a WebSocket frame",
+            ));
+        }
+        if masked {
+            apply_mask(&mut payload, mask);
+        }
+        Ok((fin, opcode, payload))
+    }
+
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<()> {
+        let mut frame = Vec::with_capacity(payload.len() + 14);
+        frame.push(0x80 | opcode);
+        let mask_bit = if self.client { 0x80 } else { 0 };
+        match payload.len() {
+            length if length < 126 => frame.push(mask_bit | length as u8),
+            length if length <= u16::MAX as usize => {
+                frame.push(mask_bit | 126);
+                frame.extend_from_slice(&(length as u16).to_be_bytes());
+            }
+            length => {
+                frame.push(mask_bit | 127);
+                frame.extend_from_slice(&(length as u64).to_be_bytes());
+            }
+        }
+        if self.client {
+            let mut mask = [0; 4];
+            rand::fill_bytes(&mut mask);
+            frame.extend_from_slice(&mask);
+            let start = frame.len();
+            frame.extend_from_slice(payload);
+            apply_mask(&mut frame[start..], mask);
+        } else {
+            frame.extend_from_slice(payload);
+        }
+        self.writer.write_all(&frame)
+    }
+}
+
+fn protocol_error(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn data_message(opcode: u8, data: Vec<u8>) -> Result<WebSocketMessage> {
+    if opcode == 0x1 {
+        String::from_utf8(data)
+            .map(WebSocketMessage::Text)
+            .map_err(|_| protocol_error("invalid UTF-8 in text message"))
+    } else {
+        Ok(WebSocketMessage::Binary(data))
+    }
+}
+
+fn apply_mask(data: &mut [u8], mask: [u8; 4]) {
+    data.iter_mut()
+        .enumerate()
+        .for_each(|(i, byte)| *byte ^= mask[i % 4]);
+}
+
+/// Computes the `Sec-WebSocket-Accept` header value for `key`.
+pub(crate) fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{}{}", key, GUID).as_bytes()))
+}
+
+/// Returns the base64 encoding (with padding) of `data`.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let triple = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns the SHA-1 digest of `data`.
+///
+/// SHA-1 is only used for the opening handshake, where it's mandated by the protocol.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, value) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(value);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (chunk, h) in digest.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
@@ -0,0 +1,181 @@
+//! Pools of identical worker processes.
+//!
+//! A [`Pool`] starts a fixed number of [`AbstractProcess`] workers with the same argument and
+//! hands them out in a round-robin fashion. Workers are linked to the pool and are restarted with
+//! the original argument if they fail.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{
+//!     pool::Pool,
+//!     process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartProcess},
+//! };
+//!
+//! struct Adder;
+//!
+//! impl AbstractProcess for Adder {
+//!     type Arg = ();
+//!     type State = Self;
+//!
+//!     fn init(_: ProcessRef<Self>, _: ()) -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! impl RequestHandler<(i32, i32)> for Adder {
+//!     type Response = i32;
+//!
+//!     fn handle(_: &mut Self::State, (a, b): (i32, i32)) -> i32 {
+//!         a + b
+//!     }
+//! }
+//!
+//! // Start a pool of 4 `Adder` workers.
+//! let pool = Pool::<Adder>::start_link((4, ()), None);
+//! assert_eq!(pool.worker().request((1, 2)), 3);
+//! ```
+
+use crate::{
+    process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartFailableProcess},
+    Tag,
+};
+
+/// A process managing a pool of `T` workers.
+///
+/// The pool is started like any other [`AbstractProcess`], with a tuple of the worker count & the
+/// argument passed to each worker. The returned `ProcessRef<Pool<T>>` can be used to get a
+/// [`worker`](ProcessRef::worker) that the requests should be sent to.
+///
+/// Shutting down the pool shuts down all workers in reverse start order.
+pub struct Pool<T>
+where
+    T: AbstractProcess,
+{
+    arg: T::Arg,
+    workers: Vec<(ProcessRef<T>, Tag)>,
+    next: usize,
+}
+
+impl<T> Pool<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    fn start_worker(&self) -> (ProcessRef<T>, Tag) {
+        match T::start_link_or_fail(self.arg.clone(), None) {
+            Ok(result) => result,
+            Err(_) => panic!(
+                "Pool failed to start worker `{}`",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+}
+
+impl<T> AbstractProcess for Pool<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Arg = (usize, T::Arg);
+    type State = Self;
+
+    fn init(_: ProcessRef<Self>, (size, arg): Self::Arg) -> Self::State {
+        if size == 0 {
+            panic!(
+                "Pool<{}> needs at least one worker",
+                std::any::type_name::<T>()
+            );
+        }
+
+        let mut pool = Pool {
+            arg,
+            workers: Vec::with_capacity(size),
+            next: 0,
+        };
+        for _ in 0..size {
+            let worker = pool.start_worker();
+            pool.workers.push(worker);
+        }
+        pool
+    }
+
+    fn terminate(state: Self::State) {
+        state
+            .workers
+            .iter()
+            .rev()
+            .for_each(|(worker, _)| worker.shutdown());
+    }
+
+    // Pool shouldn't die if the workers die
+    fn trap_links() -> bool {
+        true
+    }
+
+    fn handle_link_trapped(state: &mut Self::State, tag: Tag) {
+        let index = match state
+            .workers
+            .iter()
+            .position(|(_, worker_tag)| *worker_tag == tag)
+        {
+            Some(index) => index,
+            None => panic!(
+                "Pool<{}> received kill signal from a died link",
+                std::any::type_name::<T>()
+            ),
+        };
+        state.workers[index] = state.start_worker();
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GetWorker;
+impl<T> RequestHandler<GetWorker> for Pool<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Response = ProcessRef<T>;
+
+    fn handle(state: &mut Self::State, _: GetWorker) -> Self::Response {
+        let worker = state.workers[state.next].0.clone();
+        state.next = (state.next + 1) % state.workers.len();
+        worker
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GetWorkers;
+impl<T> RequestHandler<GetWorkers> for Pool<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Response = Vec<ProcessRef<T>>;
+
+    fn handle(state: &mut Self::State, _: GetWorkers) -> Self::Response {
+        state
+            .workers
+            .iter()
+            .map(|(worker, _)| worker.clone())
+            .collect()
+    }
+}
+
+impl<T> ProcessRef<Pool<T>>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    /// Returns the next worker in round-robin order.
+    pub fn worker(&self) -> ProcessRef<T> {
+        self.request(GetWorker)
+    }
+
+    /// Returns all currently running workers.
+    pub fn workers(&self) -> Vec<ProcessRef<T>> {
+        self.request(GetWorkers)
+    }
+}
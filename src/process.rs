@@ -1,15 +1,24 @@
-use std::{marker::PhantomData, time::Duration};
+use std::{
+    backtrace::Backtrace, cell::RefCell, collections::VecDeque, marker::PhantomData, time::Duration,
+};
 
 use crate::{
     distributed::node_id,
+    env,
     host::{self, api},
-    mailbox::{LinkMailbox, LinkTrapped},
+    mailbox::{LinkMailbox, LinkTrapped, LINK_TRAPPED, TIMEOUT},
+    registry::{self, NameTaken},
     serializer::{Bincode, Serializer},
-    supervisor::{Supervisable, Supervisor, SupervisorConfig},
-    timer::TimerRef,
-    Mailbox, Process, ProcessConfig, ReceiveError, Tag,
+    supervisor::{CrashReport, Supervisable, Supervisor, SupervisorConfig},
+    timer::{IntervalRef, TimerRef},
+    trace, Mailbox, Monitor, Priority, Process, ProcessConfig, ReceiveError, Tag,
 };
 
+/// Status in front of the response to a request that was handled.
+const REPLIED: u8 = 0;
+/// Status in front of the response to a request that was shed because of an [`OverloadPolicy`].
+const OVERLOADED: u8 = 1;
+
 pub fn process_id() -> u64 {
     unsafe { api::process::process_id() }
 }
@@ -88,9 +97,89 @@ pub trait AbstractProcess {
     /// Called when a `shutdown` command is received.
     fn terminate(_state: Self::State) {}
 
-    /// This function will be called if the process is set to catch link deaths with
-    /// `host::api::process::die_when_link_dies(1)` and a linked process traps.
+    /// This function will be called if the process [traps links](Self::trap_links) and a linked
+    /// process fails. `tag` identifies the link, e.g. the one returned by
+    /// [`StartProcess::start_link`].
     fn handle_link_trapped(_state: &mut Self::State, _tag: Tag) {}
+
+    /// Returns `true` if the process should survive failures of linked processes.
+    ///
+    /// A trapped failure is passed to [`handle_link_trapped`](Self::handle_link_trapped) instead
+    /// of taking the process down. This lets processes that own linked resources, like one
+    /// process per TCP connection, clean up after them and continue. The setting is applied
+    /// before `init` is called, so it also covers links created inside of `init`. Defaults to
+    /// `false`.
+    fn trap_links() -> bool {
+        false
+    }
+
+    /// Returns the argument that should be passed to `init` if the process is restarted by a
+    /// [`Supervisor`](crate::supervisor::Supervisor).
+    ///
+    /// It's called after each handled message and request of a supervised process. The
+    /// supervisor keeps the latest returned snapshot and uses it instead of the original argument
+    /// on the next restart, so that the process can resume from its last good state. If `None`
+    /// is returned, the previous argument is kept.
+    fn snapshot(_state: &Self::State) -> Option<Self::Arg> {
+        None
+    }
+
+    /// Returns how long the process needs to be idle before [`hibernate`](Self::hibernate) is
+    /// called. Defaults to `None`, never hibernating.
+    fn hibernate_after() -> Option<Duration> {
+        None
+    }
+
+    /// Called once the process didn't receive any message or request for the duration of
+    /// [`hibernate_after`](Self::hibernate_after).
+    ///
+    /// Processes that are idle most of the time, like one process per mostly silent connection,
+    /// can use it to drop caches and shrink buffers (e.g. with `shrink_to_fit`). It's called
+    /// again only after the process handled something and became idle once more.
+    ///
+    /// WebAssembly memories can't shrink, so freed memory is not returned to the host. It's kept
+    /// by the allocator of the process and reused for future allocations.
+    fn hibernate(_state: &mut Self::State) {}
+
+    /// Returns the policy limiting how many messages and requests can wait to be handled.
+    /// Defaults to `None`, queueing without a limit.
+    ///
+    /// See [`OverloadPolicy`] for details.
+    fn overload_policy() -> Option<OverloadPolicy> {
+        None
+    }
+}
+
+/// Limits the number of messages and requests waiting in the mailbox of an [`AbstractProcess`].
+///
+/// If a process can't keep up with its callers, e.g. because a downstream service is slow, its
+/// mailbox grows without bound and each request waits behind all queued ones. With an overload
+/// policy the process sheds work once more than `max_queued` messages and requests are waiting.
+/// Callers of shed requests receive [`ReceiveError::Overloaded`] from
+/// [`try_request`](Request::try_request) or [`request_timeout`](Request::request_timeout), while
+/// shed messages are dropped. Shutdowns, supervision and link failures are never shed.
+///
+/// The runtime doesn't expose the size of a mailbox, so a process with an overload policy takes
+/// all waiting messages out of its mailbox and keeps a copy of them until they are handled.
+/// Resources inside of messages, like [`TcpStream`](crate::net::TcpStream)s, can't be copied and
+/// can't be sent to a process with an overload policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OverloadPolicy {
+    /// Sheds the messages and requests that arrive while `max_queued` are already waiting.
+    Reject { max_queued: usize },
+    /// Sheds the oldest waiting messages and requests to make room for new ones. Messages sent
+    /// with [`Priority::High`] are only shed once all waiting messages have a high priority.
+    DropLowPriority { max_queued: usize },
+}
+
+impl OverloadPolicy {
+    /// Returns the maximum number of waiting messages and requests.
+    pub fn max_queued(&self) -> usize {
+        match self {
+            OverloadPolicy::Reject { max_queued } => *max_queued,
+            OverloadPolicy::DropLowPriority { max_queued } => *max_queued,
+        }
+    }
 }
 
 /// Defines a handler for a message of type `M`.
@@ -111,6 +200,149 @@ where
     fn handle(state: &mut Self::State, request: M) -> Self::Response;
 }
 
+/// Defines a handler for a request of type `M` that can be replied to at a later point.
+///
+/// Instead of returning the response, the handler receives a [`ReplyHandle`]. The handle can be
+/// stored in the state or sent to another process, and the response is delivered to the caller
+/// once [`ReplyHandle::reply`] is called. Requests of this kind are sent with
+/// [`DeferredRequest::deferred_request`].
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{
+///     process::{
+///         AbstractProcess, DeferredRequest, DeferredRequestHandler, ProcessRef, ReplyHandle,
+///         StartProcess,
+///     },
+///     Mailbox, Process,
+/// };
+///
+/// struct Doubler;
+///
+/// impl AbstractProcess for Doubler {
+///     type Arg = ();
+///     type State = Self;
+///
+///     fn init(_: ProcessRef<Self>, _: ()) -> Self {
+///         Self
+///     }
+/// }
+///
+/// impl DeferredRequestHandler<u32> for Doubler {
+///     type Response = u32;
+///
+///     fn handle(_: &mut Self::State, request: u32, reply: ReplyHandle<u32>) {
+///         // Delegate the work to a different process.
+///         Process::spawn((request, reply), |(request, reply), _: Mailbox<()>| {
+///             reply.reply(request * 2)
+///         });
+///     }
+/// }
+///
+/// let doubler = Doubler::start((), None);
+/// assert_eq!(doubler.deferred_request(2), 4);
+/// ```
+pub trait DeferredRequestHandler<M, S = Bincode>: AbstractProcess
+where
+    S: Serializer<M>,
+{
+    type Response;
+
+    fn handle(state: &mut Self::State, request: M, reply: ReplyHandle<Self::Response, S>);
+}
+
+/// A handle used to send the response to a deferred request.
+///
+/// See [`DeferredRequestHandler`] for more details.
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound = "")]
+pub struct ReplyHandle<R, S = Bincode> {
+    sender: Process<R, S>,
+    tag: Tag,
+}
+
+impl<R, S> ReplyHandle<R, S>
+where
+    S: Serializer<R>,
+{
+    /// Sends the `response` back to the process waiting on the request.
+    pub fn reply(self, response: R) {
+        host::create_message::<R, S>(self.tag.id(), 0);
+        unsafe { api::message::write_data(&REPLIED, 1) };
+        S::encode(&response).unwrap();
+        host::send(self.sender.node_id(), self.sender.id());
+    }
+}
+
+impl<R, S> std::fmt::Debug for ReplyHandle<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplyHandle")
+            .field("sender", &self.sender)
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
+/// A handle to the response of a request sent with [`request_async`](Request::request_async).
+///
+/// The response is tagged with the unique [`tag`](Self::tag) of the request, so multiple requests
+/// can be outstanding at the same time and their responses can be collected in any order.
+pub struct PendingReply<R, S = Bincode> {
+    tag: Tag,
+    phantom: PhantomData<(R, S)>,
+}
+
+impl<R, S> PendingReply<R, S>
+where
+    R: 'static,
+    S: Serializer<R>,
+{
+    /// Returns the tag of the request, that is also used for the response.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Blocks until the response arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the request was shed because of the [`OverloadPolicy`] of the
+    /// process.
+    pub fn wait(self) -> R {
+        match self.wait_(None) {
+            Ok(response) => response,
+            Err(err) => panic!("request failed: {}", err),
+        }
+    }
+
+    /// Same as [`wait`](Self::wait), but only waits for the duration of timeout.
+    ///
+    /// The request stays pending if the timeout is reached, so it can be waited on again.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<R, ReceiveError> {
+        self.wait_(Some(timeout))
+    }
+
+    fn wait_(&self, timeout: Option<Duration>) -> Result<R, ReceiveError> {
+        let timeout_ms = match timeout {
+            Some(timeout) => timeout.as_millis() as u64,
+            None => u64::MAX,
+        };
+        if host::receive(&[self.tag.id()], timeout_ms) == TIMEOUT {
+            return Err(ReceiveError::Timeout);
+        }
+        decode_reply::<R, S>()
+    }
+}
+
+impl<R, S> std::fmt::Debug for PendingReply<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PendingReply")
+            .field("tag", &self.tag)
+            .finish()
+    }
+}
+
 pub trait StartProcess<T>
 where
     T: AbstractProcess,
@@ -126,6 +358,8 @@ where
         node: u64,
         config: &ProcessConfig,
     ) -> ProcessRef<T>;
+    fn start_named(arg: T::Arg, name: &str) -> Result<ProcessRef<T>, NameTaken>;
+    fn start_link_named(arg: T::Arg, name: &str) -> Result<ProcessRef<T>, NameTaken>;
 }
 
 impl<T> StartProcess<T> for T
@@ -168,6 +402,30 @@ where
     ) -> ProcessRef<T> {
         start::<T>(arg, name, None, Some(config), Some(node)).unwrap()
     }
+
+    /// Start a process registered under `name`, unless another running process of the same type
+    /// is registered under it.
+    ///
+    /// The name is claimed before `init()` is called, so that only one of multiple processes
+    /// started at the same time with the same name wins. The other ones exit without calling
+    /// `init()`. Starting a process with the `name` argument of [`start`](Self::start) instead
+    /// replaces a registered process.
+    ///
+    /// Names of registered processes that failed are released shortly after the failure. Names
+    /// of processes on other nodes, and of processes that finished without removing their name
+    /// (e.g. registered with [`registry::register`]), stay taken until they are replaced or
+    /// removed.
+    fn start_named(arg: T::Arg, name: &str) -> Result<ProcessRef<T>, NameTaken> {
+        spawn_starter::<T>(arg, Some(name), true, None, None, None).unwrap()
+    }
+
+    /// Start a linked process registered under `name`, unless another running process of the
+    /// same type is registered under it.
+    ///
+    /// See [`start_named`](Self::start_named) for details.
+    fn start_link_named(arg: T::Arg, name: &str) -> Result<ProcessRef<T>, NameTaken> {
+        spawn_starter::<T>(arg, Some(name), true, Some(Tag::new()), None, None).unwrap()
+    }
 }
 
 pub trait SelfReference<T> {
@@ -238,6 +496,24 @@ fn start<T>(
     config: Option<&ProcessConfig>,
     node: Option<u64>,
 ) -> Result<ProcessRef<T>, LinkTrapped>
+where
+    T: AbstractProcess,
+{
+    // Names are replaced, so registering them never fails.
+    spawn_starter::<T>(arg, name, false, link, config, node).map(|process| process.unwrap())
+}
+
+/// Spawns the process and waits for `init()` to finish.
+///
+/// If `exclusive` is set, the process only starts if it can [`claim`] the name.
+fn spawn_starter<T>(
+    arg: T::Arg,
+    name: Option<&str>,
+    exclusive: bool,
+    link: Option<Tag>,
+    config: Option<&ProcessConfig>,
+    node: Option<u64>,
+) -> Result<Result<ProcessRef<T>, NameTaken>, LinkTrapped>
 where
     T: AbstractProcess,
 {
@@ -253,50 +529,63 @@ where
         // no link or config
         Process::<(), Bincode>::spawn_node(
             node,
-            (parent, tag, arg, name, T::init as usize as i32),
+            (parent, tag, arg, name, exclusive, T::init as usize as i32),
             starter::<T>,
         )
     } else if let Some(config) = config {
         if link.is_some() {
             Process::<(), Bincode>::spawn_link_config_tag(
                 config,
-                (parent, tag, arg, name, T::init as usize as i32),
+                (parent, tag, arg, name, exclusive, T::init as usize as i32),
                 tag,
                 starter::<T>,
             )
         } else {
             Process::<(), Bincode>::spawn_config(
                 config,
-                (parent, tag, arg, name, T::init as usize as i32),
+                (parent, tag, arg, name, exclusive, T::init as usize as i32),
                 starter::<T>,
             )
         }
     } else if link.is_some() {
         Process::<(), Bincode>::spawn_link_tag(
-            (parent, tag, arg, name, T::init as usize as i32),
+            (parent, tag, arg, name, exclusive, T::init as usize as i32),
             tag,
             starter::<T>,
         )
     } else {
         Process::<(), Bincode>::spawn(
-            (parent, tag, arg, name, T::init as usize as i32),
+            (parent, tag, arg, name, exclusive, T::init as usize as i32),
             starter::<T>,
         )
     };
 
     // Don't return until `init()` finishes
-    let mailbox: LinkMailbox<(), Bincode> = unsafe { LinkMailbox::new() };
-    mailbox.tag_receive(Some(&[tag]))?;
+    let mailbox: LinkMailbox<Result<(), NameTaken>, Bincode> = unsafe { LinkMailbox::new() };
+    if let Err(name_taken) = mailbox.tag_receive(Some(&[tag]))? {
+        return Ok(Err(name_taken));
+    }
 
-    Ok(ProcessRef {
+    Ok(Ok(ProcessRef {
         process,
         phantom: PhantomData,
-    })
+    }))
 }
 
+/// Parent, tag of the `init()` confirmation, argument, name, if the name is claimed exclusively
+/// and the `init()` function of the process.
+type StarterArg<T> = (
+    Process<(), Bincode>,
+    Tag,
+    <T as AbstractProcess>::Arg,
+    Option<String>,
+    bool,
+    i32,
+);
+
 /// Entry point of the process.
 fn starter<T>(
-    (parent, tag, capture, name, entry): (Process<(), Bincode>, Tag, T::Arg, Option<String>, i32),
+    (parent, tag, capture, name, exclusive, entry): StarterArg<T>,
     _: Mailbox<(), Bincode>,
 ) where
     T: AbstractProcess,
@@ -304,42 +593,102 @@ fn starter<T>(
     let entry: fn(this: ProcessRef<T>, arg: T::Arg) -> T::State =
         unsafe { std::mem::transmute(entry) };
     let this = unsafe { ProcessRef::new(node_id(), process_id()) };
+    let confirm = Process::<Result<(), NameTaken>, Bincode>::new(parent.node_id(), parent.id());
+
+    #[cfg(feature = "log")]
+    if let Some(name) = name.as_ref() {
+        crate::logger::set_process_name(name);
+    }
 
     // Register name
     let name = if let Some(name) = name {
         // Encode type information in name
         let name = format!("{} + ProcessRef + {}", name, std::any::type_name::<T>());
-        unsafe {
-            host::api::registry::put(
-                name.as_ptr(),
-                name.len(),
-                this.process.node_id(),
-                this.process.id(),
-            )
-        };
+        if exclusive {
+            let claimed = registry::claim(name.clone(), this.process.node_id(), this.process.id());
+            if claimed.is_err() {
+                confirm.tag_send(tag, claimed);
+                return;
+            }
+        } else {
+            unsafe {
+                host::api::registry::put(
+                    name.as_ptr(),
+                    name.len(),
+                    this.process.node_id(),
+                    this.process.id(),
+                )
+            };
+            registry::track(name.clone(), this.process.node_id(), this.process.id());
+        }
         Some(name)
     } else {
         None
     };
 
+    if T::trap_links() {
+        unsafe { host::api::process::die_when_link_dies(0) };
+    }
+
     let mut state = entry(this, capture);
     // Let parent know that the `init()` call finished
-    parent.tag_send(tag, ());
+    confirm.tag_send(tag, Ok(()));
 
+    // Handler of the supervisor that receives snapshots, if the process is supervised.
+    let mut snapshot_handler = None;
     let mailbox: LinkMailbox<Sendable, Bincode> = unsafe { LinkMailbox::new() };
+    // Messages taken out of the mailbox, if the process limits how many can wait.
+    let mut backlog = T::overload_policy().map(Backlog::new);
+    // The process already hibernated since it handled the last message.
+    let mut hibernated = false;
     // Run process forever and respond to requests.
     loop {
-        let dispatcher = mailbox.tag_receive(None);
+        let context = trace::current();
+        let timeout = if hibernated {
+            None
+        } else {
+            T::hibernate_after()
+        };
+        let received = match backlog.as_mut() {
+            Some(backlog) => backlog.receive(timeout),
+            // High priority messages are handled first, like in `Mailbox::receive`.
+            None => {
+                match mailbox.receive_(Some(&[Tag::high_priority().id()]), Some(Duration::ZERO)) {
+                    Err(ReceiveError::Timeout) => mailbox.receive_(None, timeout),
+                    received => received,
+                }
+                .map(|received| received.map_err(|link_trapped| link_trapped.tag()))
+            }
+        };
+        let dispatcher = match received {
+            Ok(dispatcher) => dispatcher,
+            Err(ReceiveError::Timeout) => {
+                T::hibernate(&mut state);
+                hibernated = true;
+                continue;
+            }
+            Err(err) => panic!("{}: {:?}", err, err),
+        };
+        hibernated = false;
+        // Each message is handled in the trace context it carries, untraced ones and link
+        // failures without one.
+        trace::set_current(dispatcher.as_ref().ok().and_then(|_| trace::received()));
         match dispatcher {
             Ok(dispatcher) => match dispatcher {
                 Sendable::Message(handler) => {
                     let handler: fn(state: &mut T::State) = unsafe { std::mem::transmute(handler) };
                     handler(&mut state);
+                    send_snapshot::<T>(&state, &parent, tag, snapshot_handler);
                 }
                 Sendable::Request(handler, sender) => {
                     let handler: fn(state: &mut T::State, sender: Process<()>) =
                         unsafe { std::mem::transmute(handler) };
                     handler(&mut state, sender);
+                    send_snapshot::<T>(&state, &parent, tag, snapshot_handler);
+                }
+                Sendable::Supervise(handler, crash_handler) => {
+                    snapshot_handler = Some(handler);
+                    report_crashes(parent.clone(), tag, crash_handler);
                 }
                 Sendable::Shutdown(sender) => {
                     // Get tag out of message first
@@ -350,39 +699,277 @@ fn starter<T>(
                     break;
                 }
             },
-            Err(link_trapped) => T::handle_link_trapped(&mut state, link_trapped.tag()),
+            Err(tag) => T::handle_link_trapped(&mut state, tag),
         }
+        trace::set_current(context);
     }
 
     // Unregister name
     if let Some(name) = name {
         unsafe { host::api::registry::remove(name.as_ptr(), name.len()) };
+        registry::untrack(name);
+    }
+}
+
+/// Messages and requests taken out of the mailbox of a process with an [`OverloadPolicy`].
+struct Backlog {
+    policy: OverloadPolicy,
+    queue: VecDeque<Queued>,
+    // Number of queued messages and requests that can be shed.
+    sheddable: usize,
+}
+
+/// A message in the [`Backlog`], or a link failure if `data` is `None`.
+struct Queued {
+    tag: Tag,
+    data: Option<Vec<u8>>,
+    sheddable: bool,
+    // Caller waiting on the response, if it's a request.
+    requester: Option<Process<()>>,
+}
+
+impl Backlog {
+    fn new(policy: OverloadPolicy) -> Self {
+        Backlog {
+            policy,
+            queue: VecDeque::new(),
+            sheddable: 0,
+        }
+    }
+
+    /// Takes all waiting messages out of the mailbox, sheds the ones over the limit and loads the
+    /// oldest remaining high priority message, or the oldest one if there is none, into the
+    /// message buffer.
+    ///
+    /// If no message is waiting, it waits for the duration of `timeout` for the next one.
+    fn receive(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> Result<Result<Sendable, Tag>, ReceiveError> {
+        loop {
+            let timeout_ms = match timeout {
+                _ if !self.queue.is_empty() => 0,
+                Some(timeout) => timeout.as_millis() as u64,
+                None => u64::MAX,
+            };
+            let message_type = host::receive(&[], timeout_ms);
+            if message_type == TIMEOUT {
+                break;
+            }
+            let tag = Tag::from(unsafe { api::message::get_tag() });
+            let queued = if message_type == LINK_TRAPPED {
+                Queued {
+                    tag,
+                    data: None,
+                    sheddable: false,
+                    requester: None,
+                }
+            } else {
+                let sendable: Sendable = Bincode::decode()?;
+                let (sheddable, requester) = match sendable {
+                    Sendable::Message(_) => (true, None),
+                    Sendable::Request(_, requester) => (true, Some(requester)),
+                    Sendable::Shutdown(_) | Sendable::Supervise(..) => (false, None),
+                };
+                // Keep the whole message, including its trace context.
+                let size = unsafe { api::message::data_size() } as usize;
+                let mut data = vec![0; size];
+                unsafe {
+                    api::message::seek_data(0);
+                    api::message::read_data(data.as_mut_ptr(), size);
+                }
+                Queued {
+                    tag,
+                    data: Some(data),
+                    sheddable,
+                    requester,
+                }
+            };
+            self.sheddable += queued.sheddable as usize;
+            self.queue.push_back(queued);
+            self.shed();
+        }
+
+        // High priority messages are handled first, the others in the order they arrived.
+        let index = self
+            .queue
+            .iter()
+            .position(|queued| queued.tag == Tag::high_priority())
+            .unwrap_or(0);
+        let queued = match self.queue.remove(index) {
+            Some(queued) => queued,
+            None => return Err(ReceiveError::Timeout),
+        };
+        self.sheddable -= queued.sheddable as usize;
+        let data = match queued.data {
+            Some(data) => data,
+            None => return Ok(Err(queued.tag)),
+        };
+        unsafe {
+            api::message::create_data(queued.tag.id(), data.len() as u64);
+            api::message::write_data(data.as_ptr(), data.len());
+            api::message::seek_data(0);
+        }
+        trace::read_header();
+        Ok(Ok(Bincode::decode()?))
+    }
+
+    /// Sheds messages until only the maximum number of them is waiting.
+    fn shed(&mut self) {
+        while self.sheddable > self.policy.max_queued() {
+            let newest = self.queue.iter().rposition(|queued| queued.sheddable);
+            let index = match self.policy {
+                OverloadPolicy::Reject { .. } => newest,
+                OverloadPolicy::DropLowPriority { .. } => self
+                    .queue
+                    .iter()
+                    .position(|queued| queued.sheddable && queued.tag != Tag::high_priority())
+                    .or(newest),
+            };
+            let queued = self.queue.remove(index.unwrap()).unwrap();
+            self.sheddable -= 1;
+            if let Some(requester) = queued.requester {
+                host::create_data(queued.tag.id(), 0);
+                unsafe { api::message::write_data(&OVERLOADED, 1) };
+                host::send(requester.node_id(), requester.id());
+            }
+        }
+    }
+}
+
+/// Sends the snapshot of the state to the supervising `parent`, tagged with the link `tag`.
+fn send_snapshot<T>(state: &T::State, parent: &Process<()>, tag: Tag, handler: Option<i32>)
+where
+    T: AbstractProcess,
+{
+    let handler = match handler {
+        Some(handler) => handler,
+        None => return,
+    };
+    if let Some(arg) = T::snapshot(state) {
+        host::create_data(Tag::none().id(), 0);
+        // The supervisor handles the snapshot like a regular message.
+        Bincode::encode(&Sendable::Message(handler)).unwrap();
+        Bincode::encode(&tag).unwrap();
+        Bincode::encode(&arg).unwrap();
+        host::send(parent.node_id(), parent.id());
+    }
+}
+
+crate::process_local! {
+    /// Supervisor receiving the crash report of the process, with the link tag and the handler of
+    /// the report.
+    static CRASH_REPORTER: RefCell<Option<(Process<()>, Tag, i32)>> = RefCell::new(None);
+}
+
+/// Sends a [`CrashReport`] to the supervising `parent` if the process panics, tagged with the link
+/// `tag`.
+///
+/// The panic hook is installed the first time, later calls only replace the supervisor.
+fn report_crashes(parent: Process<()>, tag: Tag, handler: i32) {
+    let installed = CRASH_REPORTER.with(|reporter| {
+        reporter
+            .borrow_mut()
+            .replace((parent, tag, handler))
+            .is_some()
+    });
+    if installed {
+        return;
     }
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let reporter = CRASH_REPORTER.with(|reporter| reporter.borrow().clone());
+        if let Some((parent, tag, handler)) = reporter {
+            let report = CrashReport {
+                process: Process::this(),
+                label: env::label(),
+                message: info.to_string(),
+                backtrace: Backtrace::force_capture().to_string(),
+            };
+            host::create_data(Tag::none().id(), 0);
+            // The supervisor handles the report like a regular message.
+            Bincode::encode(&Sendable::Message(handler)).unwrap();
+            Bincode::encode(&tag).unwrap();
+            Bincode::encode(&report).unwrap();
+            host::send(parent.node_id(), parent.id());
+        }
+        default_hook(info);
+    }));
 }
 
+/// Marks `child` as supervised, so that it starts sending its snapshots and crash reports to the
+/// caller.
+///
+/// The handlers are called in the caller with the state of the supervisor, and can decode the tag
+/// of the child & the snapshot or crash report from the message.
+pub(crate) fn supervise<T>(child: &ProcessRef<T>, snapshot_handler: i32, crash_handler: i32) {
+    host::create_data(Tag::none().id(), 0);
+    Bincode::encode(&Sendable::Supervise(snapshot_handler, crash_handler)).unwrap();
+    host::send(child.process.node_id(), child.process.id());
+}
+
+/// Sending messages handled by a [`MessageHandler`].
 pub trait Message<M, S>
 where
     S: Serializer<M>,
 {
+    /// Sends a message to the process without waiting for it to be handled.
     fn send(&self, message: M);
+    /// Sends a message with the given priority.
+    ///
+    /// Waiting [`Priority::High`] messages are handled before all other messages and requests, so
+    /// that control messages don't need to wait behind queued up data. The priority also decides
+    /// which messages are shed first by an [`OverloadPolicy`].
+    fn send_priority(&self, message: M, priority: Priority);
+    /// Sends a message to the process after the specified duration has passed.
     fn send_after(&self, message: M, duration: Duration) -> TimerRef;
+    /// Sends a copy of the message to the process every time the `period` passes.
+    ///
+    /// See [`Process::send_interval`] for more details.
+    fn send_interval(&self, message: M, period: Duration) -> IntervalRef;
 }
 
+/// Sending requests handled by a [`RequestHandler`].
 pub trait Request<M, S>
 where
     S: Serializer<M>,
 {
     type Result;
 
+    /// Sends a request to the process and blocks until the response arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the request was shed because of the [`OverloadPolicy`] of the
+    /// process.
     fn request(&self, request: M) -> Self::Result {
+        match self.request_timeout_(request, None) {
+            Ok(result) => result,
+            Err(err) => panic!("request failed: {}", err),
+        }
+    }
+
+    /// Same as [`request`](Self::request), but returns [`ReceiveError::Overloaded`] instead of
+    /// panicking if the request was shed because of the [`OverloadPolicy`] of the process.
+    fn try_request(&self, request: M) -> Result<Self::Result, ReceiveError> {
         self.request_timeout_(request, None)
-            .expect("no timeout specified")
     }
 
+    /// Same as [`request`](Self::request), but only waits for the duration of timeout.
+    ///
+    /// If the timeout is reached [`ReceiveError::Timeout`] is returned. The process may still
+    /// handle the request later, in that case the response ends up in the caller's mailbox,
+    /// tagged with the unique tag of the request.
     fn request_timeout(&self, request: M, timeout: Duration) -> Result<Self::Result, ReceiveError> {
         self.request_timeout_(request, Some(timeout))
     }
 
+    /// Sends a request to the process without waiting for the response.
+    ///
+    /// The response can be collected later with [`PendingReply::wait`]. This allows having
+    /// multiple requests to the same process outstanding at the same time.
+    fn request_async(&self, request: M) -> PendingReply<Self::Result, S>;
+
     #[doc(hidden)]
     fn request_timeout_(
         &self,
@@ -391,6 +978,44 @@ where
     ) -> Result<Self::Result, ReceiveError>;
 }
 
+/// Sending requests handled by a [`DeferredRequestHandler`].
+pub trait DeferredRequest<M, S>
+where
+    S: Serializer<M>,
+{
+    type Result;
+
+    /// Sends a request to the process and blocks until the response arrives.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if the request was shed because of the [`OverloadPolicy`] of the
+    /// process.
+    fn deferred_request(&self, request: M) -> Self::Result {
+        match self.deferred_request_timeout_(request, None) {
+            Ok(result) => result,
+            Err(err) => panic!("request failed: {}", err),
+        }
+    }
+
+    /// Same as [`deferred_request`](Self::deferred_request), but only waits for the duration of
+    /// timeout.
+    fn deferred_request_timeout(
+        &self,
+        request: M,
+        timeout: Duration,
+    ) -> Result<Self::Result, ReceiveError> {
+        self.deferred_request_timeout_(request, Some(timeout))
+    }
+
+    #[doc(hidden)]
+    fn deferred_request_timeout_(
+        &self,
+        request: M,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Result, ReceiveError>;
+}
+
 /// A reference to a running process.
 ///
 /// `ProcessRef<T>` is different from a `Process` in the ability to handle messages of different
@@ -406,7 +1031,7 @@ where
 
 impl<T> ProcessRef<T> {
     /// Construct a process from a raw ID.
-    unsafe fn new(node_id: u64, process_id: u64) -> Self {
+    pub(crate) unsafe fn new(node_id: u64, process_id: u64) -> Self {
         let process = <Process<()>>::new(node_id, process_id);
         ProcessRef {
             process,
@@ -419,6 +1044,11 @@ impl<T> ProcessRef<T> {
         self.process.id()
     }
 
+    /// Returns the ID of the node the process is running on.
+    pub fn node_id(&self) -> u64 {
+        self.process.node_id()
+    }
+
     pub fn lookup(name: &str) -> Option<Self> {
         let name = format!("{} + ProcessRef + {}", name, std::any::type_name::<T>());
         let mut id = 0;
@@ -439,6 +1069,13 @@ impl<T> ProcessRef<T> {
         unsafe { host::api::process::link(0, self.process.id()) };
     }
 
+    /// Link process to the one currently running, using `tag` as the tag of the link.
+    ///
+    /// See [`Process::link_with`].
+    pub fn link_with(&self, tag: Tag) {
+        self.process.link_with(tag);
+    }
+
     /// Unlink processes from the caller.
     pub fn unlink(&self) {
         unsafe { host::api::process::unlink(self.process.id()) };
@@ -448,6 +1085,23 @@ impl<T> ProcessRef<T> {
     pub fn kill(&self) {
         unsafe { host::api::process::kill(self.process.id()) };
     }
+
+    /// Start monitoring this process.
+    pub fn monitor(&self) -> Monitor {
+        self.process.monitor()
+    }
+
+    /// Stop monitoring this process.
+    pub fn demonitor(&self, monitor: Monitor) {
+        self.process.demonitor(monitor);
+    }
+
+    /// Returns `false` if the process is reported as gone within `timeout`.
+    ///
+    /// See [`Process::probe_alive`] for details.
+    pub fn probe_alive(&self, timeout: Duration) -> bool {
+        self.process.probe_alive(timeout)
+    }
 }
 
 impl<T> Clone for ProcessRef<T> {
@@ -473,10 +1127,24 @@ where
         self.shutdown_timeout_(Some(timeout))
     }
 
+    /// Shut down process, killing it if it doesn't finish shutting down inside the timeout.
+    ///
+    /// Returns `true` if the process shut down gracefully and `false` if it was killed. A killed
+    /// process is considered failed and will take down processes linked to it.
+    pub fn shutdown_or_kill(&self, timeout: Duration) -> bool {
+        match self.shutdown_timeout(timeout) {
+            Ok(()) => true,
+            Err(_) => {
+                self.kill();
+                false
+            }
+        }
+    }
+
     fn shutdown_timeout_(&self, timeout: Option<Duration>) -> Result<(), ReceiveError> {
         // Create new message buffer.
         let tag = Tag::new();
-        unsafe { host::api::message::create_data(tag.id(), 0) };
+        host::create_data(tag.id(), 0);
 
         // Create reference to self
         let this: Process<()> = Process::this();
@@ -506,6 +1174,9 @@ enum Sendable {
     // at the time of returning with the correct type.
     Request(i32, Process<()>),
     Shutdown(Process<()>),
+    // Sent by supervisors to their children, contains the handlers receiving snapshots and crash
+    // reports.
+    Supervise(i32, i32),
 }
 
 impl<M, S, T> Message<M, S> for ProcessRef<T>
@@ -516,6 +1187,11 @@ where
 {
     /// Send message to the process.
     fn send(&self, message: M) {
+        self.send_priority(message, Priority::Normal);
+    }
+
+    /// Send message to the process with a priority.
+    fn send_priority(&self, message: M, priority: Priority) {
         fn unpacker<TU, MU, SU>(this: &mut TU::State)
         where
             TU: MessageHandler<MU, SU>,
@@ -525,8 +1201,12 @@ where
             <TU as MessageHandler<MU, SU>>::handle(this, message);
         }
 
+        let tag = match priority {
+            Priority::Normal => Tag::none(),
+            Priority::High => Tag::high_priority(),
+        };
         // Create new message buffer.
-        unsafe { host::api::message::create_data(Tag::none().id(), 0) };
+        host::create_data(tag.id(), 0);
         // First encode the handler inside the message buffer.
         let handler = unpacker::<T, M, S> as usize as i32;
         let handler_message = Sendable::Message(handler);
@@ -549,7 +1229,7 @@ where
         }
 
         // Create new message buffer.
-        unsafe { host::api::message::create_data(Tag::none().id(), 0) };
+        host::create_data(Tag::none().id(), 0);
         // First encode the handler inside the message buffer.
         let handler = unpacker::<T, M, S> as usize as i32;
         let handler_message = Sendable::Message(handler);
@@ -561,6 +1241,33 @@ where
             unsafe { host::api::timer::send_after(self.process.id(), duration.as_millis() as u64) };
         TimerRef::new(timer_id)
     }
+
+    /// Send a copy of the message to the process every time the `period` passes.
+    fn send_interval(&self, message: M, period: Duration) -> IntervalRef {
+        fn unpacker<TU, MU, SU>(this: &mut TU::State)
+        where
+            TU: MessageHandler<MU, SU>,
+            SU: Serializer<MU>,
+        {
+            let message: MU = SU::decode().unwrap();
+            <TU as MessageHandler<MU, SU>>::handle(this, message);
+        }
+
+        let handler = unpacker::<T, M, S> as usize as i32;
+        IntervalRef::start(
+            self.process.node_id(),
+            self.process.id(),
+            period,
+            true,
+            || {
+                // First encode the handler inside the message buffer.
+                let handler_message = Sendable::Message(handler);
+                Bincode::encode(&handler_message).unwrap();
+                // Then the message itself.
+                S::encode(&message).unwrap();
+            },
+        )
+    }
 }
 
 impl<M, S, T> Request<M, S> for ProcessRef<T>
@@ -589,34 +1296,131 @@ where
             let tag = unsafe { host::api::message::get_tag() };
             let tag = Tag::from(tag);
             let result = <TU as RequestHandler<MU, SU>>::handle(this, message);
-            sender.tag_send(tag, result);
+            ReplyHandle { sender, tag }.reply(result);
+        }
+
+        let handler = unpacker::<T, M, S> as usize as i32;
+        send_request::<M, Self::Result, S>(&self.process, handler, request, timeout)
+    }
+
+    fn request_async(&self, request: M) -> PendingReply<Self::Result, S> {
+        fn unpacker<TU, MU, SU>(
+            this: &mut TU::State,
+            sender: Process<<TU as RequestHandler<MU, SU>>::Response, SU>,
+        ) where
+            TU: RequestHandler<MU, SU>,
+            SU: Serializer<MU> + Serializer<<TU as RequestHandler<MU, SU>>::Response>,
+        {
+            // Get content out of message
+            let message: MU = SU::decode().unwrap();
+            // Get tag out of message before the handler function maybe manipulates it.
+            let tag = unsafe { host::api::message::get_tag() };
+            let tag = Tag::from(tag);
+            let result = <TU as RequestHandler<MU, SU>>::handle(this, message);
+            ReplyHandle { sender, tag }.reply(result);
         }
 
         let tag = Tag::new();
-        // Create new message buffer.
-        unsafe { host::api::message::create_data(tag.id(), 0) };
-        // Create reference to self
-        let this: Process<()> = Process::new(node_id(), process_id());
-        // First encode the handler inside the message buffer.
         let handler = unpacker::<T, M, S> as usize as i32;
-        let handler_message = Sendable::Request(handler, this);
-        S::encode(&handler_message).unwrap();
-        // Then the message itself.
-        S::encode(&request).unwrap();
-        // Send it & wait on a response!
-        let timeout_ms = match timeout {
-            Some(timeout) => timeout.as_millis() as u64,
-            None => u64::MAX,
-        };
-        let result =
-            host::send_receive_skip_search(self.process.node_id(), self.process.id(), timeout_ms);
-        if result == 9027 {
-            return Err(ReceiveError::Timeout);
-        };
-        Ok(S::decode().unwrap())
+        create_request::<M, S>(tag, handler, request);
+        host::send(self.process.node_id(), self.process.id());
+        PendingReply {
+            tag,
+            phantom: PhantomData,
+        }
     }
 }
 
+impl<M, S, T> DeferredRequest<M, S> for ProcessRef<T>
+where
+    T: AbstractProcess,
+    T: DeferredRequestHandler<M, S>,
+    S: Serializer<M>
+        + Serializer<Sendable>
+        + Serializer<<T as DeferredRequestHandler<M, S>>::Response>,
+{
+    type Result = <T as DeferredRequestHandler<M, S>>::Response;
+
+    fn deferred_request_timeout_(
+        &self,
+        request: M,
+        timeout: Option<Duration>,
+    ) -> Result<Self::Result, ReceiveError> {
+        fn unpacker<TU, MU, SU>(
+            this: &mut TU::State,
+            sender: Process<<TU as DeferredRequestHandler<MU, SU>>::Response, SU>,
+        ) where
+            TU: DeferredRequestHandler<MU, SU>,
+            SU: Serializer<MU> + Serializer<<TU as DeferredRequestHandler<MU, SU>>::Response>,
+        {
+            // Get content out of message
+            let message: MU = SU::decode().unwrap();
+            // Get tag out of message before the handler function maybe manipulates it.
+            let tag = unsafe { host::api::message::get_tag() };
+            let tag = Tag::from(tag);
+            let reply = ReplyHandle { sender, tag };
+            <TU as DeferredRequestHandler<MU, SU>>::handle(this, message, reply);
+        }
+
+        let handler = unpacker::<T, M, S> as usize as i32;
+        send_request::<M, Self::Result, S>(&self.process, handler, request, timeout)
+    }
+}
+
+/// Sends a request to `process` that is going to be handled by `handler` and waits on the
+/// response.
+fn send_request<M, R, S>(
+    process: &Process<()>,
+    handler: i32,
+    request: M,
+    timeout: Option<Duration>,
+) -> Result<R, ReceiveError>
+where
+    S: Serializer<M> + Serializer<Sendable> + Serializer<R>,
+{
+    let tag = Tag::new();
+    create_request::<M, S>(tag, handler, request);
+    // Send it & wait on a response!
+    let timeout_ms = match timeout {
+        Some(timeout) => timeout.as_millis() as u64,
+        None => u64::MAX,
+    };
+    let result = host::send_receive_skip_search(process.node_id(), process.id(), timeout_ms);
+    if result == TIMEOUT {
+        return Err(ReceiveError::Timeout);
+    };
+    decode_reply::<R, S>()
+}
+
+/// Decodes the response to a request from the received message, following its status.
+fn decode_reply<R, S>() -> Result<R, ReceiveError>
+where
+    S: Serializer<R>,
+{
+    let mut status = REPLIED;
+    unsafe { api::message::read_data(&mut status, 1) };
+    if status == OVERLOADED {
+        return Err(ReceiveError::Overloaded);
+    }
+    Ok(S::decode().unwrap())
+}
+
+/// Writes a request with the tag `tag` into a new message buffer.
+fn create_request<M, S>(tag: Tag, handler: i32, request: M)
+where
+    S: Serializer<M> + Serializer<Sendable>,
+{
+    // Create new message buffer.
+    host::create_data(tag.id(), 0);
+    // Create reference to self
+    let this: Process<()> = Process::new(node_id(), process_id());
+    // First encode the handler inside the message buffer.
+    let handler_message = Sendable::Request(handler, this);
+    S::encode(&handler_message).unwrap();
+    // Then the message itself.
+    S::encode(&request).unwrap();
+}
+
 /// Subscriber represents a process that can be notified by a tagged message with the same tag that
 /// is used when registering the subscription.
 #[derive(Debug)]
@@ -660,7 +1464,7 @@ where
 
         let tag = Tag::new();
         // Create new message buffer.
-        unsafe { host::api::message::create_data(tag.id(), 0) };
+        host::create_data(tag.id(), 0);
         // Create reference to self
         let this: Process<()> = Process::this();
         // First encode the handler inside the message buffer.
@@ -19,7 +19,7 @@ use std::fmt;
 ///
 /// Initialization is dynamically performed on the first call to [`with`]
 /// within a process, and values are **never** destructed. This means if a process
-/// finishes normally or panics, the [`Drop`] implementation will never ba called.
+/// finishes normally or panics, the [`Drop`] implementation will never be called.
 ///
 /// A `ProcessLocal`'s initializer cannot recursively depend on itself, and using
 /// a `ProcessLocal` in this way will cause the initializer to infinitely recurse
@@ -1,10 +1,20 @@
-use std::{any::TypeId, marker::PhantomData, mem::ManuallyDrop, time::Duration};
+use std::{
+    any::{type_name, TypeId},
+    hash::Hasher,
+    marker::PhantomData,
+    mem::ManuallyDrop,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::{
     function::process::IntoProcess,
     host,
     serializer::{Bincode, Serializer},
-    Mailbox, Process, ProcessConfig, ReceiveError, Tag,
+    storage::Fnv,
+    LinkMailbox, Mailbox, Process, ProcessConfig, ReceiveError, Tag,
 };
 
 /// A value that the protocol captures from the parent process.
@@ -20,6 +30,117 @@ pub struct ProtocolCapture<C> {
     capture: C,
 }
 
+/// Sent in front of the [`ProtocolCapture`], so that the capture is only decoded if both sides
+/// agree on the protocol.
+#[derive(Serialize, Deserialize)]
+struct Handshake {
+    schema: u64,
+    // Where to confirm the handshake, if the parent waits for it.
+    reply: Option<(Process<()>, Tag)>,
+}
+
+/// The spawned process uses a different protocol than the parent.
+///
+/// Returned by [`Protocol::try_spawn_node`] if the protocol schemas of both ends don't match, e.g.
+/// because the remote node runs another version of the module during a rolling upgrade.
+#[derive(Error, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Protocol mismatch, expected schema {expected:#x} but the spawned process uses {found:#x}")]
+pub struct ProtocolMismatch {
+    /// Schema hash of the protocol the parent expects.
+    pub expected: u64,
+    /// Schema hash of the protocol of the spawned process.
+    pub found: u64,
+}
+
+/// The peer of a [resumable](Resumable) session failed before the session ended.
+///
+/// Returned by [`Protocol::try_receive`] and [`Protocol::try_offer`] instead of failing the
+/// current process. The session can be restarted from the beginning with
+/// [`Resumable::start`].
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("Protocol session aborted, the peer process failed")]
+pub struct SessionAborted;
+
+/// A session that can be restarted from the beginning after its peer crashed.
+///
+/// Sessions spawned with [`Process::spawn_link`] share the fate of their peer, if one side fails
+/// the other one is killed too. A `Resumable` keeps the capture and entry function of a session,
+/// so that a supervising process can [start](Self::start) it again. The sessions are linked to
+/// the current process, which needs to [catch link
+/// failures](crate::Mailbox::catch_link_failure). A crash of the spawned process is then reported
+/// as [`SessionAborted`] by the next receive of the session.
+///
+/// Only the spawning side survives a crash of its peer, the spawned process still fails together
+/// with the supervising process.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::protocol::{End, Protocol, Recv, Resumable, Send};
+/// use lunatic::Mailbox;
+///
+/// type Double = Recv<u64, Send<u64, End>>;
+///
+/// fn supervisor(mailbox: Mailbox<()>) {
+///     let mailbox = mailbox.catch_link_failure();
+///     let session = Resumable::new((), |_, protocol: Protocol<Double>| {
+///         let (protocol, value) = protocol.receive();
+///         let _ = protocol.send(value * 2);
+///     });
+///     let doubled = loop {
+///         match session.start(&mailbox).send(21).try_receive() {
+///             Ok((_, doubled)) => break doubled,
+///             Err(_) => continue, // Restart the session.
+///         }
+///     };
+///     assert_eq!(doubled, 42);
+/// }
+/// ```
+pub struct Resumable<P: 'static, C, S = Bincode> {
+    capture: C,
+    entry: fn(C, Protocol<P, S>),
+}
+
+impl<P, C, S> Resumable<P, C, S>
+where
+    P: HasDual + 'static,
+    C: Clone,
+    S: Serializer<ProtocolCapture<C>>,
+{
+    /// Creates a session running `entry` with the argument `capture`, without starting it yet.
+    pub fn new(capture: C, entry: fn(C, Protocol<P, S>)) -> Self {
+        Resumable { capture, entry }
+    }
+
+    /// Spawns a new process running the session from the beginning.
+    ///
+    /// The `mailbox` proves that the current process catches link failures.
+    pub fn start<M, S2>(&self, _mailbox: &LinkMailbox<M, S2>) -> Protocol<<P as HasDual>::Dual, S>
+    where
+        S2: Serializer<M>,
+    {
+        let link = Tag::new();
+        match spawn(self.capture.clone(), self.entry, Some(link), None, None) {
+            Ok(mut protocol) => {
+                protocol.link = Some(link);
+                protocol
+            }
+            Err(err) => panic!("Failed to spawn a process: {}", err),
+        }
+    }
+}
+
+/// Returns the schema hash of the session type `P` with the serializer `S`.
+///
+/// It covers the structure of the session and the names of the exchanged types, not their fields.
+/// Renaming a message type (e.g. `OrderV2`) is a way to change the schema of a protocol.
+pub fn schema<P, S>() -> u64 {
+    let mut hasher = Fnv::new();
+    hasher.write(type_name::<P>().as_bytes());
+    hasher.write(type_name::<S>().as_bytes());
+    hasher.finish()
+}
+
 /// A `Protocol` is a specific type of [`Process`](crate::Process).
 ///
 /// It uses session types to check during compile time that all messages exchanged between two
@@ -29,6 +150,8 @@ pub struct Protocol<P: 'static, S = Bincode> {
     id: u64,
     node_id: u64,
     tag: Tag,
+    // Tag of the link to the peer, if a crash of the peer aborts the session.
+    link: Option<Tag>,
     phantom: PhantomData<(P, S)>,
 }
 
@@ -53,10 +176,30 @@ impl<P, S> Protocol<P, S> {
             id: process.id(),
             node_id: process.node_id(),
             tag,
+            link: None,
             phantom: PhantomData,
         }
     }
 
+    /// Spawns a process with the protocol on node `node_id`, after checking that both ends use the
+    /// same protocol.
+    ///
+    /// Protocols spawned on other nodes always go through this handshake, but
+    /// [`Process::spawn_node`](crate::Process::spawn_node) panics on a mismatch. The handshake
+    /// compares the [`schema`] hashes of both ends, before the capture is decoded by the new
+    /// process.
+    pub fn try_spawn_node<C>(
+        node_id: u64,
+        capture: C,
+        entry: fn(C, Protocol<P, S>),
+    ) -> Result<Protocol<<P as HasDual>::Dual, S>, ProtocolMismatch>
+    where
+        P: HasDual + 'static,
+        S: Serializer<ProtocolCapture<C>>,
+    {
+        spawn(capture, entry, None, None, Some(node_id))
+    }
+
     /// Cast the protocol to another type.
     fn cast<P2>(self) -> Protocol<P2, S> {
         // Don't drop the session yet.
@@ -65,9 +208,47 @@ impl<P, S> Protocol<P, S> {
             id: self_.id,
             node_id: self_.node_id,
             tag: self_.tag,
+            link: self_.link,
             phantom: PhantomData,
         }
     }
+
+    /// Receives the next message of the session, or the failure of the peer of a resumable
+    /// session.
+    fn receive_session<A: 'static>(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<Result<A, SessionAborted>, ReceiveError>
+    where
+        S: Serializer<A>,
+    {
+        match self.link {
+            Some(link) => {
+                // Temporarily cast to right mailbox type.
+                let mailbox: LinkMailbox<A, S> = unsafe { LinkMailbox::new() };
+                let tags = [self.tag.id(), link.id()];
+                Ok(mailbox
+                    .receive_(Some(&tags), timeout)?
+                    .map_err(|_| SessionAborted))
+            }
+            None => {
+                // Temporarily cast to right mailbox type.
+                let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
+                match timeout {
+                    Some(timeout) => {
+                        Ok(Ok(mailbox.tag_receive_timeout(Some(&[self.tag]), timeout)?))
+                    }
+                    None => Ok(Ok(mailbox.tag_receive(Some(&[self.tag])))),
+                }
+            }
+        }
+    }
+
+    /// Ends an aborted session, without the check for the `End` state.
+    fn abort(self) -> SessionAborted {
+        std::mem::forget(self);
+        SessionAborted
+    }
 }
 
 impl<P, A, S> Protocol<Send<A, P>, S>
@@ -77,12 +258,10 @@ where
     /// Send a value of type `A` over the session. Returns a session with protocol `P`.
     #[must_use]
     pub fn send(self, message: A) -> Protocol<P, S> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<A, S> = Process::new(self_.node_id, self_.id);
-        process.tag_send(self_.tag, message);
-        Protocol::from_process(process, self_.tag)
+        let process: Process<A, S> = Process::new(self.node_id, self.id);
+        process.tag_send(self.tag, message);
+        self.cast()
     }
 }
 
@@ -92,12 +271,48 @@ where
 {
     /// Receives a value of type `A` from the session. Returns a tuple containing the resulting
     /// session and the received value.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the peer of a [resumable](Resumable) session failed, use
+    /// [`try_receive`](Self::try_receive) to handle the failure.
     #[must_use]
     pub fn receive(self) -> (Protocol<P, S>, A) {
-        // Temporarily cast to right mailbox type.
-        let mailbox: Mailbox<A, S> = unsafe { Mailbox::new() };
-        let received = mailbox.tag_receive(Some(&[self.tag]));
-        (self.cast(), received)
+        match self.try_receive() {
+            Ok(received) => received,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Same as `receive`, but returns [`SessionAborted`] if the peer of a [resumable](Resumable)
+    /// session failed.
+    ///
+    /// Sessions that aren't resumable are never aborted, their peers are linked to the process.
+    pub fn try_receive(self) -> Result<(Protocol<P, S>, A), SessionAborted> {
+        match self.receive_session(None).unwrap() {
+            Ok(received) => Ok((self.cast(), received)),
+            Err(_) => Err(self.abort()),
+        }
+    }
+
+    /// Same as `receive`, but only waits for the duration of timeout for the value.
+    ///
+    /// If the timeout is reached, the session is returned unchanged together with the error, so
+    /// that the receive can be retried. Dropping the returned session fails the process, same as
+    /// dropping any session before it reaches the `End` state.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the peer of a [resumable](Resumable) session failed.
+    pub fn receive_timeout(
+        self,
+        timeout: Duration,
+    ) -> Result<(Protocol<P, S>, A), (Self, ReceiveError)> {
+        match self.receive_session(Some(timeout)) {
+            Ok(Ok(received)) => Ok((self.cast(), received)),
+            Ok(Err(_)) => panic!("{}", self.abort()),
+            Err(err) => Err((self, err)),
+        }
     }
 }
 
@@ -125,6 +340,26 @@ where
         let _: Protocol<TaskEnd, S> = self.cast(); // Only `End` protocols can be dropped
         result
     }
+
+    pub(crate) fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Ends the task after its result was received outside of this session.
+    pub(crate) fn finish(self) {
+        let _: Protocol<TaskEnd, S> = self.cast(); // Only `End` protocols can be dropped
+    }
+
+    /// Cancels the task by killing the process running it.
+    ///
+    /// The task is unlinked before being killed, so the caller is not affected by its death.
+    pub fn cancel(self) {
+        unsafe {
+            host::api::process::unlink(self.id);
+            host::api::process::kill(self.id);
+        }
+        let _: Protocol<TaskEnd, S> = self.cast(); // Only `End` protocols can be dropped
+    }
 }
 
 impl<P, Q, S> Protocol<Choose<P, Q>, S>
@@ -134,23 +369,19 @@ where
     /// Perform an active choice, selecting protocol `P`.
     #[must_use]
     pub fn select_left(self) -> Protocol<P, S> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<bool, S> = Process::new(self_.node_id, self_.id);
-        process.tag_send(self_.tag, true);
-        Protocol::from_process(process, self_.tag)
+        let process: Process<bool, S> = Process::new(self.node_id, self.id);
+        process.tag_send(self.tag, true);
+        self.cast()
     }
 
     /// Perform an active choice, selecting protocol `Q`.
     #[must_use]
     pub fn select_right(self) -> Protocol<Q, S> {
-        // Don't drop the session yet.
-        let self_ = ManuallyDrop::new(self);
         // Temporarily cast to right process type.
-        let process: Process<bool, S> = Process::new(self_.node_id, self_.id);
-        process.tag_send(self_.tag, false);
-        Protocol::from_process(process, self_.tag)
+        let process: Process<bool, S> = Process::new(self.node_id, self.id);
+        process.tag_send(self.tag, false);
+        self.cast()
     }
 }
 
@@ -160,18 +391,116 @@ where
 {
     /// Passive choice. This allows the other end of the session to select one
     /// of two options for continuing the protocol: either `P` or `Q`.
+    ///
+    /// Offers with more than two options can be handled with the [`branch!`](crate::branch)
+    /// macro.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the peer of a [resumable](Resumable) session failed, use
+    /// [`try_offer`](Self::try_offer) to handle the failure.
     #[must_use]
     pub fn offer(self) -> Branch<Protocol<P, S>, Protocol<Q, S>> {
-        unsafe {
-            // Temporarily cast to right mailbox type.
-            let mailbox: Mailbox<bool, S> = Mailbox::new();
-            if mailbox.receive() {
-                Branch::Left(self.cast())
-            } else {
-                Branch::Right(self.cast())
-            }
+        match self.try_offer() {
+            Ok(branch) => branch,
+            Err(err) => panic!("{}", err),
         }
     }
+
+    /// Same as `offer`, but returns [`SessionAborted`] if the peer of a [resumable](Resumable)
+    /// session failed.
+    pub fn try_offer(self) -> Result<Offered<P, Q, S>, SessionAborted> {
+        match self.receive_session(None).unwrap() {
+            Ok(true) => Ok(Branch::Left(self.cast())),
+            Ok(false) => Ok(Branch::Right(self.cast())),
+            Err(_) => Err(self.abort()),
+        }
+    }
+}
+
+impl<P, S> Protocol<Rec<P>, S>
+where
+    P: Subst<Rec<P>>,
+{
+    /// Enter a recursive protocol. Returns a session with protocol `P`, where every [`Var`] is
+    /// replaced with `Rec<P>`, so that reaching a `Var` starts the protocol from the beginning.
+    #[must_use]
+    pub fn enter(self) -> Protocol<<P as Subst<Rec<P>>>::Out, S> {
+        self.cast()
+    }
+}
+
+impl<P, Q, R, S> Protocol<Par<P, Q, R>, S>
+where
+    P: 'static,
+    Q: 'static,
+{
+    /// Splits the session into the independent sub-sessions `P` and `Q`, which are handled by
+    /// `left` and `right` in two new processes. Returns a session with protocol `R` once both
+    /// sub-sessions finished.
+    ///
+    /// The other side needs to fork at the same point, its helpers are connected to the ones of
+    /// this side. Because the sub-sessions run in parallel, one of them can send while the other
+    /// one is receiving. The helpers are linked to the caller, so a failing sub-session fails the
+    /// whole session.
+    #[must_use]
+    pub fn fork(self, left: fn(Protocol<P, S>), right: fn(Protocol<Q, S>)) -> Protocol<R, S> {
+        let done = Tag::new();
+        let this = Process::<(), Bincode>::this();
+        let left: Process<(Process<()>, Tag)> =
+            Process::spawn_link((this.clone(), done, left as usize), fork_helper::<P, S>);
+        let right: Process<(Process<()>, Tag)> =
+            Process::spawn_link((this, done, right as usize), fork_helper::<Q, S>);
+
+        // Exchange the helpers with the other side.
+        let tags = (Tag::new(), Tag::new());
+        let peer: Process<ForkInfo, Bincode> = Process::new(self.node_id, self.id);
+        peer.tag_send(
+            self.tag,
+            (
+                Process::new(left.node_id(), left.id()),
+                tags.0,
+                Process::new(right.node_id(), right.id()),
+                tags.1,
+            ),
+        );
+        let mailbox: Mailbox<ForkInfo, Bincode> = unsafe { Mailbox::new() };
+        let (peer_left, left_tag, peer_right, right_tag) = mailbox.tag_receive(Some(&[self.tag]));
+        // Both sides need to agree on the tags, the ones of the process with the lower ID are used.
+        let (left_tag, right_tag) =
+            if (host::node_id(), host::process_id()) < (self.node_id, self.id) {
+                tags
+            } else {
+                (left_tag, right_tag)
+            };
+        left.send((peer_left, left_tag));
+        right.send((peer_right, right_tag));
+
+        // Rejoin after both sub-sessions finished.
+        let mailbox: Mailbox<(), Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[done]));
+        mailbox.tag_receive(Some(&[done]));
+        self.cast()
+    }
+}
+
+/// The session selected by the other side of an [`Offer`] between `P` and `Q`.
+type Offered<P, Q, S> = Branch<Protocol<P, S>, Protocol<Q, S>>;
+
+/// The helper processes of one side of a [`Par`] protocol and the tags of their sub-sessions.
+type ForkInfo = (Process<()>, Tag, Process<()>, Tag);
+
+/// Entry point of the helper processes running the sub-sessions of a [`Par`] protocol.
+fn fork_helper<P, S>(
+    (parent, done, function): (Process<(), Bincode>, Tag, usize),
+    mailbox: Mailbox<(Process<()>, Tag)>,
+) where
+    P: 'static,
+{
+    let (peer, tag) = mailbox.receive();
+    let function: fn(Protocol<P, S>) = unsafe { std::mem::transmute(function) };
+    function(Protocol::from_process(peer, tag));
+    parent.tag_send(done, ());
 }
 
 /// A special case of the protocol with a `result()` function.
@@ -187,11 +516,87 @@ pub struct Recv<A, P>(PhantomData<(A, P)>);
 pub struct Send<A, P>(PhantomData<(A, P)>);
 
 /// Active choice between `P` and `Q`
+///
+/// Choices between more than two protocols can be built with the [`choose!`](crate::choose) macro.
 pub struct Choose<P, Q>(PhantomData<(P, Q)>);
 
 /// Passive choice (offer) between `P` and `Q`
+///
+/// Offers of more than two protocols can be built with the [`offer!`](crate::offer) macro.
 pub struct Offer<P, Q>(PhantomData<(P, Q)>);
 
+/// Run `P` and `Q` in parallel, then `R`
+///
+/// Both sides split the session with [`fork`](Protocol::fork), which handles `P` and `Q` in
+/// separate processes. This allows full-duplex protocols, e.g. a stream of values sent in each
+/// direction at the same time:
+///
+/// ```
+/// type Duplex = Par<Rec<Offer<Recv<u64, Var>, End>>, Rec<Choose<Send<u64, Var>, End>>, End>;
+/// ```
+pub struct Par<P, Q, R>(PhantomData<(P, Q, R)>);
+
+/// Recursive protocol `P`
+///
+/// Each occurrence of [`Var`] inside of `P` continues the protocol from the start of `P`. This
+/// allows repeating exchanges to be expressed without unbounded nesting of types, e.g. a protocol
+/// that receives numbers until the other side decides to stop and ask for the sum:
+///
+/// ```
+/// type Sum = Rec<Offer<Recv<i32, Var>, Send<i32, End>>>;
+/// ```
+///
+/// A `Var` always refers to the innermost `Rec` it's contained in.
+pub struct Rec<P>(PhantomData<P>);
+
+/// Jump back to the start of the enclosing [`Rec`]
+pub struct Var;
+
+/// Replaces each [`Var`] referring to the outermost [`Rec`] in a protocol with `R`.
+///
+/// This trait is used by [`Protocol::enter`] to unroll recursive protocols one step and is
+/// sealed, like [`HasDual`].
+pub trait Subst<R>: private::Sealed {
+    type Out;
+}
+
+impl<R> Subst<R> for TaskEnd {
+    type Out = TaskEnd;
+}
+
+impl<R> Subst<R> for End {
+    type Out = End;
+}
+
+impl<R> Subst<R> for Var {
+    type Out = R;
+}
+
+impl<A, P: Subst<R>, R> Subst<R> for Send<A, P> {
+    type Out = Send<A, P::Out>;
+}
+
+impl<A, P: Subst<R>, R> Subst<R> for Recv<A, P> {
+    type Out = Recv<A, P::Out>;
+}
+
+impl<P: Subst<R>, Q: Subst<R>, R> Subst<R> for Choose<P, Q> {
+    type Out = Choose<P::Out, Q::Out>;
+}
+
+impl<P: Subst<R>, Q: Subst<R>, R> Subst<R> for Offer<P, Q> {
+    type Out = Offer<P::Out, Q::Out>;
+}
+
+impl<P: Subst<X>, Q: Subst<X>, R: Subst<X>, X> Subst<X> for Par<P, Q, R> {
+    type Out = Par<P::Out, Q::Out, R::Out>;
+}
+
+// `Var`s inside of a nested `Rec` refer to the nested one.
+impl<P, R> Subst<R> for Rec<P> {
+    type Out = Rec<P>;
+}
+
 /// The HasDual trait defines the dual relationship between protocols.
 ///
 /// Any valid protocol has a corresponding dual.
@@ -225,8 +630,23 @@ impl<P: HasDual, Q: HasDual> HasDual for Offer<P, Q> {
     type Dual = Choose<P::Dual, Q::Dual>;
 }
 
+impl<P: HasDual, Q: HasDual, R: HasDual> HasDual for Par<P, Q, R> {
+    type Dual = Par<P::Dual, Q::Dual, R::Dual>;
+}
+
+impl<P: HasDual> HasDual for Rec<P> {
+    type Dual = Rec<P::Dual>;
+}
+
+impl HasDual for Var {
+    type Dual = Var;
+}
+
+/// The option selected by the other side of an [`Offer`].
 pub enum Branch<L, R> {
+    /// The first option was selected.
     Left(L),
+    /// The second option was selected.
     Right(R),
 }
 
@@ -241,6 +661,9 @@ mod private {
     impl<A, P> Sealed for Recv<A, P> {}
     impl<P, Q> Sealed for Choose<P, Q> {}
     impl<P, Q> Sealed for Offer<P, Q> {}
+    impl<P, Q, R> Sealed for Par<P, Q, R> {}
+    impl<P> Sealed for Rec<P> {}
+    impl Sealed for Var {}
 }
 
 impl<P, S> IntoProcess<P, S> for Protocol<P, S>
@@ -259,40 +682,105 @@ where
     where
         S: Serializer<ProtocolCapture<C>>,
     {
-        let entry = entry as usize as i32;
-        let node_id = node.unwrap_or_else(host::node_id);
-
-        // The `type_helper_wrapper` function is used here to create a pointer to a function with
-        // generic types C, P & S. We can only send pointer data across processes and this is the
-        // only way the Rust compiler will let us transfer this information into the new process.
-        match host::spawn(node, config, link, type_helper_wrapper::<C, P, S>, entry) {
-            Ok(id) => {
-                // Use unique tag so that protocol messages are separated from regular messages.
-                let tag = Tag::new();
-                // Create reference to self
-                let this = Process::<()>::new(host::node_id(), host::process_id());
-                let capture = ProtocolCapture {
-                    process: this,
-                    tag,
-                    capture,
-                };
-                let child = Process::<ProtocolCapture<C>, S>::new(node_id, id);
-
-                child.send(capture);
-                Protocol::from_process(child, tag)
-            }
+        match spawn(capture, entry, link, config, node) {
+            Ok(protocol) => protocol,
             Err(err) => panic!("Failed to spawn a process: {}", err),
         }
     }
 }
 
+/// Spawns a process running `entry` with the protocol `P`.
+///
+/// Processes on the same node run the same module, so the handshake is only confirmed by
+/// processes on other nodes.
+fn spawn<C, P, S>(
+    capture: C,
+    entry: fn(C, Protocol<P, S>),
+    link: Option<Tag>,
+    config: Option<&ProcessConfig>,
+    node: Option<u64>,
+) -> Result<Protocol<<P as HasDual>::Dual, S>, ProtocolMismatch>
+where
+    P: HasDual + 'static,
+    S: Serializer<ProtocolCapture<C>>,
+{
+    let entry = entry as usize as i32;
+    let node_id = node.unwrap_or_else(host::node_id);
+
+    // The `type_helper_wrapper` function is used here to create a pointer to a function with
+    // generic types C, P & S. We can only send pointer data across processes and this is the
+    // only way the Rust compiler will let us transfer this information into the new process.
+    match host::spawn(node, config, link, type_helper_wrapper::<C, P, S>, entry) {
+        Ok(id) => {
+            // Use unique tag so that protocol messages are separated from regular messages.
+            let tag = Tag::new();
+            // Create reference to self
+            let this = Process::<()>::new(host::node_id(), host::process_id());
+            let handshake = Handshake {
+                schema: schema::<P, S>(),
+                reply: node.map(|_| (this.clone(), tag)),
+            };
+            let capture = ProtocolCapture {
+                process: this,
+                tag,
+                capture,
+            };
+            let child = Process::<ProtocolCapture<C>, S>::new(node_id, id);
+
+            // The handshake is always encoded with `Bincode`, so that it can be decoded no matter
+            // what serializer the other end uses.
+            host::create_data(Tag::none().id(), 0);
+            Bincode::encode(&handshake).unwrap();
+            S::encode(&capture).unwrap();
+            host::send(node_id, id);
+
+            if handshake.reply.is_some() {
+                // Temporarily cast to right mailbox type.
+                let mailbox: Mailbox<Result<(), ProtocolMismatch>, Bincode> =
+                    unsafe { Mailbox::new() };
+                mailbox.tag_receive(Some(&[tag]))?;
+            }
+            Ok(Protocol::from_process(child, tag))
+        }
+        Err(err) => panic!("Failed to spawn a process: {}", err),
+    }
+}
+
 /// Wrapper function to help transfer the generic types C, P & S into the new process.
 fn type_helper_wrapper<C, P, S>(function: i32)
 where
     S: Serializer<ProtocolCapture<C>>,
     P: HasDual + 'static,
 {
-    let p_capture = unsafe { Mailbox::<ProtocolCapture<C>, S>::new() }.receive();
+    host::receive(&[Tag::none().id()], u64::MAX);
+    let handshake: Handshake = Bincode::decode().unwrap();
+    let found = schema::<P, S>();
+    let result = if handshake.schema == found {
+        Ok(())
+    } else {
+        Err(ProtocolMismatch {
+            expected: handshake.schema,
+            found,
+        })
+    };
+    match handshake.reply {
+        Some((parent, tag)) => {
+            let parent = Process::<Result<(), ProtocolMismatch>, Bincode>::new(
+                parent.node_id(),
+                parent.id(),
+            );
+            parent.tag_send(tag, result);
+            if result.is_err() {
+                return;
+            }
+        }
+        None => {
+            if let Err(err) = result {
+                panic!("{}", err);
+            }
+        }
+    }
+    let p_capture: ProtocolCapture<C> = S::decode().unwrap();
     let capture = p_capture.capture;
     let protocol = Protocol::from_process(p_capture.process, p_capture.tag);
     let function: fn(C, Protocol<P, S>) = unsafe { std::mem::transmute(function) };
@@ -321,4 +809,22 @@ mod tests {
         let (_, result) = child.receive();
         assert_eq!(result, 5);
     }
+
+    #[test]
+    fn schema_depends_on_protocol() {
+        type OtherProtocol = Recv<i64, Recv<i64, Send<i64, End>>>;
+
+        assert_eq!(
+            schema::<AddProtocol, Bincode>(),
+            schema::<AddProtocol, Bincode>()
+        );
+        assert_ne!(
+            schema::<AddProtocol, Bincode>(),
+            schema::<OtherProtocol, Bincode>()
+        );
+        assert_ne!(
+            schema::<AddProtocol, Bincode>(),
+            schema::<<AddProtocol as HasDual>::Dual, Bincode>()
+        );
+    }
 }
@@ -0,0 +1,83 @@
+//! Random numbers and UUIDs from the host.
+//!
+//! The randomness comes directly from the runtime, that seeds it from the operating system.
+//! Crates building on `getrandom` need to be configured for the `wasm32-wasi` target, this module
+//! works without any setup. Each call crosses into the host, so programs that need many random
+//! numbers should seed a userspace generator with [`random_u64`] instead.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::rand;
+//!
+//! let id = rand::uuid_v4();
+//! println!("Request {} rolled a {}", id, rand::random_u64() % 6 + 1);
+//! ```
+
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::host::api::random;
+
+/// Fills `buf` with random bytes.
+///
+/// # Panics
+///
+/// This function will panic if the host fails to provide randomness.
+pub fn fill_bytes(buf: &mut [u8]) {
+    let result = unsafe { random::random_get(buf.as_mut_ptr(), buf.len()) };
+    assert_eq!(result, 0, "the host failed to provide random bytes");
+}
+
+/// Returns `len` random bytes.
+pub fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    fill_bytes(&mut bytes);
+    bytes
+}
+
+/// Returns a random `u64`.
+pub fn random_u64() -> u64 {
+    let mut bytes = [0; 8];
+    fill_bytes(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+/// Returns a random version 4 [`Uuid`].
+pub fn uuid_v4() -> Uuid {
+    let mut bytes = [0; 16];
+    fill_bytes(&mut bytes);
+    // Set the version (4) and the variant (RFC 4122).
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Uuid(bytes)
+}
+
+/// A universally unique identifier, formatted in the usual hyphenated form.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+    /// Creates a UUID from its bytes.
+    pub fn from_bytes(bytes: [u8; 16]) -> Self {
+        Uuid(bytes)
+    }
+
+    /// Returns the bytes of the UUID.
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl Display for Uuid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if matches!(i, 4 | 6 | 8 | 10) {
+                write!(f, "-")?;
+            }
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+//! A token bucket limiting how often something can be done.
+//!
+//! A [`RateLimiter`] holds up to a fixed number of tokens and adds a new one each time the refill
+//! interval passes. Every [`acquire`](ProcessRef::acquire) takes one token, waiting for the next
+//! refill if the bucket is empty. Because the limiter is a process, all processes that share it
+//! share the same limit, e.g. for outbound calls to an HTTP API or for expensive workers.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use lunatic::{process::StartProcess, rate_limiter::RateLimiter};
+//!
+//! // Allow bursts of 10 calls and 2 calls per second on average.
+//! let limiter = RateLimiter::start_link((10, Duration::from_millis(500)), None);
+//! for _ in 0..20 {
+//!     limiter.acquire();
+//!     // Call the API ...
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::process::{
+    AbstractProcess, DeferredRequest, DeferredRequestHandler, Message, MessageHandler, ProcessRef,
+    ReplyHandle, Request, RequestHandler,
+};
+
+/// A process handing out tokens with token bucket semantics.
+///
+/// It's started with a tuple of the bucket capacity & the interval after which a token is added.
+/// The bucket starts out full. Waiting callers are served in the order they called
+/// [`acquire`](ProcessRef::acquire).
+pub struct RateLimiter {
+    this: ProcessRef<RateLimiter>,
+    capacity: u32,
+    interval: Duration,
+    tokens: u32,
+    last_refill: Instant,
+    waiting: VecDeque<(ReplyHandle<bool>, Option<Instant>)>,
+    // A `Refill` message is already scheduled.
+    refill_scheduled: bool,
+}
+
+impl RateLimiter {
+    /// Adds the tokens for the intervals that passed since the last refill.
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let intervals = (now - self.last_refill).as_nanos() / self.interval.as_nanos().max(1);
+        let intervals = intervals.min(self.capacity as u128) as u32;
+        self.tokens = (self.tokens + intervals).min(self.capacity);
+        if self.tokens == self.capacity {
+            self.last_refill = now;
+        } else {
+            self.last_refill += self.interval * intervals;
+        }
+    }
+
+    /// Hands out tokens to waiting callers and drops the ones that timed out.
+    fn serve(&mut self) {
+        self.refill();
+        let now = Instant::now();
+        let mut waiting = VecDeque::with_capacity(self.waiting.len());
+        for (reply, deadline) in self.waiting.drain(..) {
+            if self.tokens > 0 {
+                self.tokens -= 1;
+                reply.reply(true);
+            } else if matches!(deadline, Some(deadline) if deadline <= now) {
+                reply.reply(false);
+            } else {
+                waiting.push_back((reply, deadline));
+            }
+        }
+        self.waiting = waiting;
+        if !self.waiting.is_empty() && !self.refill_scheduled {
+            let next = self.interval.saturating_sub(now - self.last_refill);
+            self.this.send_after(Refill, next);
+            self.refill_scheduled = true;
+        }
+    }
+}
+
+impl AbstractProcess for RateLimiter {
+    type Arg = (u32, Duration);
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, (capacity, interval): (u32, Duration)) -> Self::State {
+        RateLimiter {
+            this,
+            capacity,
+            interval,
+            tokens: capacity,
+            last_refill: Instant::now(),
+            waiting: VecDeque::new(),
+            refill_scheduled: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct TryAcquire;
+impl RequestHandler<TryAcquire> for RateLimiter {
+    type Response = bool;
+
+    fn handle(state: &mut Self::State, _: TryAcquire) -> bool {
+        state.refill();
+        // Don't overtake callers that are already waiting.
+        if state.tokens > 0 && state.waiting.is_empty() {
+            state.tokens -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Acquire(Option<Duration>);
+impl DeferredRequestHandler<Acquire> for RateLimiter {
+    type Response = bool;
+
+    fn handle(state: &mut Self::State, Acquire(timeout): Acquire, reply: ReplyHandle<bool>) {
+        let deadline = timeout.map(|timeout| {
+            state.this.send_after(Expire, timeout);
+            Instant::now() + timeout
+        });
+        state.waiting.push_back((reply, deadline));
+        state.serve();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Refill;
+impl MessageHandler<Refill> for RateLimiter {
+    fn handle(state: &mut Self::State, _: Refill) {
+        state.refill_scheduled = false;
+        state.serve();
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Expire;
+impl MessageHandler<Expire> for RateLimiter {
+    fn handle(state: &mut Self::State, _: Expire) {
+        state.serve();
+    }
+}
+
+impl ProcessRef<RateLimiter> {
+    /// Takes a token, waiting until one is available.
+    pub fn acquire(&self) {
+        self.deferred_request(Acquire(None));
+    }
+
+    /// Takes a token if one is available right now.
+    pub fn try_acquire(&self) -> bool {
+        self.request(TryAcquire)
+    }
+
+    /// Takes a token, waiting up to `timeout` for one to become available.
+    ///
+    /// Returns `false` if no token was acquired in time.
+    pub fn acquire_timeout(&self, timeout: Duration) -> bool {
+        // The limiter replies once the timeout passes, so that no token is lost to a caller that
+        // stopped waiting.
+        self.deferred_request(Acquire(Some(timeout)))
+    }
+}
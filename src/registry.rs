@@ -0,0 +1,458 @@
+//! Named process registration and lookup.
+//!
+//! The registry allows processes to be addressed by stable names, instead of passing process
+//! handles around. Names are local to the node.
+//!
+//! The type of the process is encoded into the registered name. This means that the same name
+//! can be used by processes of different types, and a lookup only succeeds if the type of the
+//! registered process matches the requested one.
+//!
+//! [`lookup`] and [`unregister`] work with processes using the default [`Bincode`] serializer.
+//! Processes using other serializers can be found with [`Process::lookup`].
+//!
+//! Names that should be visible on all nodes of a cluster can be registered with the functions
+//! in the [`global`] module.
+//!
+//! The registered names of the node can be listed with [`iter`], e.g. by management tools.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{registry, Mailbox, Process};
+//!
+//! let process = Process::spawn((), |_, mailbox: Mailbox<i32>| {
+//!     assert_eq!(mailbox.receive(), 1337);
+//! });
+//! registry::register("hello", &process);
+//!
+//! let hello = registry::lookup::<i32>("hello").unwrap();
+//! hello.send(1337);
+//! ```
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    distributed, host,
+    process::{
+        AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler, StartProcess,
+    },
+    serializer::Bincode,
+    LinkMailbox, Mailbox, Process,
+};
+
+/// Name under which the registrar of a node is registered.
+const REGISTRAR_NAME: &str = "lunatic::registry::registrar";
+
+/// Encode type information in name.
+fn process_name<M, S>(name: &str) -> String {
+    format!(
+        "{} + Process + {}/{}",
+        name,
+        std::any::type_name::<M>(),
+        std::any::type_name::<S>()
+    )
+}
+
+/// Registers `process` under `name`.
+///
+/// If a process of the same type is already registered under this name, it will be replaced.
+pub fn register<M, S>(name: &str, process: &Process<M, S>) {
+    let name = process_name::<M, S>(name);
+    unsafe { host::api::registry::put(name.as_ptr(), name.len(), process.node_id(), process.id()) };
+    track(name, process.node_id(), process.id());
+}
+
+/// Returns the names registered on this node that start with `prefix`, together with their
+/// processes.
+///
+/// The host registry can't be enumerated, so the registrar process of the node keeps an index of
+/// the names registered with [`register`], [`Process::register`] and the `start` functions of
+/// [`StartProcess`]. Names are removed from the index when they are unregistered, and when their
+/// process fails or, for an [`AbstractProcess`], shuts down. Names are listed in lexicographic order, a name that is registered for
+/// processes of different types is listed once for each of them. Names of the [`global`] module
+/// are not included.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::registry;
+///
+/// // Broadcast to all connections.
+/// for (name, process) in registry::iter("conn/") {
+///     println!("Notifying {}", name);
+///     process.send(());
+/// }
+/// ```
+pub fn iter(prefix: &str) -> Vec<(String, Process<()>)> {
+    registrar().request(List(prefix.to_owned()))
+}
+
+/// Adds the already encoded `name` of the process to the index of the registrar, which removes
+/// the name once the process fails.
+pub(crate) fn track(name: String, node_id: u64, id: u64) {
+    registrar().send(Track(name, node_id, id));
+}
+
+/// Removes the already encoded `name` from the index of the registrar, if it's running.
+pub(crate) fn untrack(name: String) {
+    if let Some(registrar) = ProcessRef::<Registrar>::lookup(REGISTRAR_NAME) {
+        registrar.send(Untrack(name));
+    }
+}
+
+/// Returns the name without the encoded type information.
+fn plain_name(name: &str) -> &str {
+    match name.find(" + Process") {
+        Some(end) => &name[..end],
+        None => name,
+    }
+}
+
+/// Removes the process of type `Process<M>` registered under `name`.
+pub fn unregister<M>(name: &str) {
+    unregister_process::<M, Bincode>(name);
+}
+
+/// Looks up the process of type `Process<M>` registered under `name`.
+pub fn lookup<M>(name: &str) -> Option<Process<M>> {
+    lookup_process(name)
+}
+
+pub(crate) fn unregister_process<M, S>(name: &str) {
+    let name = process_name::<M, S>(name);
+    unsafe { host::api::registry::remove(name.as_ptr(), name.len()) };
+    untrack(name);
+}
+
+pub(crate) fn lookup_process<M, S>(name: &str) -> Option<Process<M, S>> {
+    let name = process_name::<M, S>(name);
+    let mut id = 0;
+    let mut node_id = 0;
+    let result =
+        unsafe { host::api::registry::get(name.as_ptr(), name.len(), &mut node_id, &mut id) };
+    if result == 0 {
+        Some(Process::new(node_id, id))
+    } else {
+        None
+    }
+}
+
+/// The name is already registered by another running process.
+///
+/// Returned when starting a process under a name that should not be replaced, e.g. with
+/// [`StartProcess::start_named`].
+#[derive(Error, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[error("The name is already taken")]
+pub struct NameTaken;
+
+/// Registers the process under the already encoded `name`, unless another running process is
+/// registered under it.
+///
+/// The host registry can't check and update a name in one call, so all claims go through the
+/// registrar process of the node, one at a time. Names registered directly with
+/// [`register`] are not protected from being overwritten.
+///
+/// The registrar removes the names of registered processes on this node once they fail, so the
+/// name can be claimed again. Names of processes on other nodes, and of processes that finished
+/// normally without removing their name, stay taken until they are replaced or removed.
+pub(crate) fn claim(name: String, node_id: u64, id: u64) -> Result<(), NameTaken> {
+    registrar().request(Claim(name, node_id, id))
+}
+
+/// Returns the registrar of this node, starting it if it's not running yet.
+fn registrar() -> ProcessRef<Registrar> {
+    get_or_start(REGISTRAR_NAME, ())
+}
+
+/// Returns the process of type `T` registered under `name`, starting it with `arg` if it's not
+/// running yet.
+///
+/// Looking up the name with `get_or_put_later` locks it for other processes until the started
+/// process is registered, so only one of them is ever started on a node.
+pub(crate) fn get_or_start<T>(name: &str, arg: T::Arg) -> ProcessRef<T>
+where
+    T: AbstractProcess,
+{
+    let name = format!("{} + ProcessRef + {}", name, std::any::type_name::<T>());
+    let mut id = 0;
+    let mut node_id = 0;
+    let result = unsafe {
+        host::api::registry::get_or_put_later(name.as_ptr(), name.len(), &mut node_id, &mut id)
+    };
+    if result == 0 {
+        return unsafe { ProcessRef::new(node_id, id) };
+    }
+    let process = T::start(arg, None);
+    unsafe { host::api::registry::put(name.as_ptr(), name.len(), process.node_id(), process.id()) };
+    process
+}
+
+/// Process serializing the claims of names on the node and keeping an index of them.
+///
+/// Registered processes on this node are watched by helper processes, so that their names can
+/// be taken over once they fail. Processes that finish normally remove their names themselves.
+struct Registrar {
+    this: ProcessRef<Registrar>,
+    // Encoded names that were registered, some of them may be removed from the host registry.
+    names: BTreeSet<String>,
+    // ID of the process registered under a name and the helper process watching it.
+    watchers: HashMap<String, (u64, Process<()>)>,
+}
+
+impl Registrar {
+    /// Watches the process registered under `name`, replacing the watcher of the previous one.
+    fn watch(&mut self, name: String, node_id: u64, id: u64) {
+        if let Some((_, watcher)) = self.watchers.remove(&name) {
+            watcher.send(());
+        }
+        // Links are local to the node.
+        if node_id == distributed::node_id() {
+            let watcher = Process::spawn((self.this.clone(), name.clone(), id), watcher);
+            self.watchers.insert(name.clone(), (id, watcher));
+        }
+        self.names.insert(name);
+    }
+}
+
+impl AbstractProcess for Registrar {
+    type Arg = ();
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, _: ()) -> Self::State {
+        Registrar {
+            this,
+            names: BTreeSet::new(),
+            watchers: HashMap::new(),
+        }
+    }
+}
+
+/// Entry point of the helper process that reports the failure of a registered process.
+///
+/// The registrar isn't linked to the registered processes itself, so that a failure of the
+/// registrar doesn't take them down.
+fn watcher((registrar, name, id): (ProcessRef<Registrar>, String, u64), _: Mailbox<()>) {
+    // The watcher should survive the death of the registered process.
+    unsafe { host::api::process::die_when_link_dies(0) };
+    unsafe { host::api::process::link(0, id) };
+
+    let mailbox: LinkMailbox<(), Bincode> = unsafe { LinkMailbox::new() };
+    match mailbox.receive() {
+        // Stopped, remove the link so that the registered process doesn't get notified.
+        Ok(()) => unsafe { host::api::process::unlink(id) },
+        Err(_) => registrar.send(Failed(name, id)),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Claim(String, u64, u64);
+impl RequestHandler<Claim> for Registrar {
+    type Response = Result<(), NameTaken>;
+
+    fn handle(state: &mut Self::State, Claim(name, node_id, id): Claim) -> Self::Response {
+        let mut current_id = 0;
+        let mut current_node_id = 0;
+        let result = unsafe {
+            host::api::registry::get(
+                name.as_ptr(),
+                name.len(),
+                &mut current_node_id,
+                &mut current_id,
+            )
+        };
+        // Names of failed processes are removed once their watcher reports them.
+        if result == 0 && (current_node_id, current_id) != (node_id, id) {
+            return Err(NameTaken);
+        }
+        unsafe { host::api::registry::put(name.as_ptr(), name.len(), node_id, id) };
+        state.watch(name, node_id, id);
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Track(String, u64, u64);
+impl MessageHandler<Track> for Registrar {
+    fn handle(state: &mut Self::State, Track(name, node_id, id): Track) {
+        state.watch(name, node_id, id);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Untrack(String);
+impl MessageHandler<Untrack> for Registrar {
+    fn handle(state: &mut Self::State, Untrack(name): Untrack) {
+        state.names.remove(&name);
+        if let Some((_, watcher)) = state.watchers.remove(&name) {
+            watcher.send(());
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Failed(String, u64);
+impl MessageHandler<Failed> for Registrar {
+    fn handle(state: &mut Self::State, Failed(name, id): Failed) {
+        // The name may have been registered again in the meantime.
+        if !matches!(state.watchers.get(&name), Some((watched, _)) if *watched == id) {
+            return;
+        }
+        state.watchers.remove(&name);
+        state.names.remove(&name);
+        let mut current_id = 0;
+        let mut current_node_id = 0;
+        let result = unsafe {
+            host::api::registry::get(
+                name.as_ptr(),
+                name.len(),
+                &mut current_node_id,
+                &mut current_id,
+            )
+        };
+        if result == 0 && (current_node_id, current_id) == (distributed::node_id(), id) {
+            unsafe { host::api::registry::remove(name.as_ptr(), name.len()) };
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct List(String);
+impl RequestHandler<List> for Registrar {
+    type Response = Vec<(String, Process<()>)>;
+
+    fn handle(state: &mut Self::State, List(prefix): List) -> Self::Response {
+        let mut entries = Vec::new();
+        let mut removed = Vec::new();
+        let names = state
+            .names
+            .range(prefix.clone()..)
+            .take_while(|name| name.starts_with(&prefix));
+        for name in names {
+            let mut id = 0;
+            let mut node_id = 0;
+            let result = unsafe {
+                host::api::registry::get(name.as_ptr(), name.len(), &mut node_id, &mut id)
+            };
+            if result == 0 {
+                entries.push((plain_name(name).to_owned(), Process::new(node_id, id)));
+            } else {
+                removed.push(name.clone());
+            }
+        }
+        // Names are removed from the host registry directly, drop them from the index lazily.
+        for name in removed {
+            state.names.remove(&name);
+        }
+        entries
+    }
+}
+
+/// Names registered on all nodes of the cluster, similar to Erlang's `global` module.
+///
+/// A global registration is replicated by spawning a helper process on each node that is
+/// [known](crate::distributed::nodes) at the time of the call, which registers the name in the
+/// local registry of its node. Lookups are then resolved locally on every node without any
+/// network round trips.
+///
+/// The runtime doesn't provide any coordination between nodes, so conflicts are resolved with a
+/// "last write wins" strategy: registering a name that is already taken replaces it on all
+/// reachable nodes. Nodes that join the cluster later, or were unreachable during the
+/// registration (e.g. during a netsplit), don't see the name until it's registered again.
+/// Global names live in a separate namespace from the names of [`register`](super::register).
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{registry::global, Mailbox, Process};
+///
+/// let process = Process::spawn((), |_, mailbox: Mailbox<i32>| {
+///     assert_eq!(mailbox.receive(), 1337);
+/// });
+/// global::register("hello", &process);
+///
+/// // Works from any node in the cluster.
+/// let hello = global::lookup::<i32>("hello").unwrap();
+/// hello.send(1337);
+/// ```
+pub mod global {
+    use super::process_name;
+    use crate::{distributed, host, serializer::Bincode, Mailbox, Process, Tag};
+
+    /// Encode type information and the global namespace in name.
+    fn global_name<M, S>(name: &str) -> String {
+        process_name::<M, S>(&format!("{} + Global", name))
+    }
+
+    /// Registers `process` under `name` on all nodes of the cluster.
+    ///
+    /// Blocks until the name is registered on all nodes.
+    pub fn register<M, S>(name: &str, process: &Process<M, S>) {
+        replicate(
+            global_name::<M, S>(name),
+            Some((process.node_id(), process.id())),
+        );
+    }
+
+    /// Removes the process of type `Process<M>` registered under `name` from all nodes of the
+    /// cluster.
+    ///
+    /// Blocks until the name is removed from all nodes.
+    pub fn unregister<M>(name: &str) {
+        replicate(global_name::<M, Bincode>(name), None);
+    }
+
+    /// Looks up the process of type `Process<M>` registered globally under `name`.
+    pub fn lookup<M>(name: &str) -> Option<Process<M>> {
+        let name = global_name::<M, Bincode>(name);
+        let mut id = 0;
+        let mut node_id = 0;
+        let result =
+            unsafe { host::api::registry::get(name.as_ptr(), name.len(), &mut node_id, &mut id) };
+        if result == 0 {
+            Some(Process::new(node_id, id))
+        } else {
+            None
+        }
+    }
+
+    /// Puts (or removes, if `process` is `None`) the name on every known node.
+    fn replicate(name: String, process: Option<(u64, u64)>) {
+        let this_node = distributed::node_id();
+        let tag = Tag::new();
+        let this = Process::<(), Bincode>::this();
+        let remote: Vec<u64> = distributed::nodes()
+            .into_iter()
+            .filter(|node| *node != this_node)
+            .collect();
+        for node in remote.iter() {
+            Process::spawn_node(*node, (this.clone(), tag, name.clone(), process), update);
+        }
+        update_local(&name, process);
+        // Wait for the confirmation of each node.
+        let mailbox: Mailbox<(), Bincode> = unsafe { Mailbox::new() };
+        for _ in remote {
+            mailbox.tag_receive(Some(&[tag]));
+        }
+    }
+
+    fn update_local(name: &str, process: Option<(u64, u64)>) {
+        match process {
+            Some((node_id, id)) => unsafe {
+                host::api::registry::put(name.as_ptr(), name.len(), node_id, id)
+            },
+            None => unsafe { host::api::registry::remove(name.as_ptr(), name.len()) },
+        }
+    }
+
+    /// Parent to confirm to, tag of the confirmation, name and the new process of the name.
+    type Update = (Process<(), Bincode>, Tag, String, Option<(u64, u64)>);
+
+    /// Entry point of the helper process that updates the registry of a remote node.
+    fn update((parent, tag, name, process): Update, _: Mailbox<()>) {
+        update_local(&name, process);
+        parent.tag_send(tag, ());
+    }
+}
@@ -0,0 +1,250 @@
+//! Routing messages to workers by key.
+//!
+//! A [`Router`] owns a fixed number of worker processes and forwards each message to one of them,
+//! picked by the hash of a key that is extracted from the message. All messages with the same key
+//! end up at the same worker, in the order they were routed. This is the common pattern for
+//! systems with one actor per entity (e.g. per user or per device), where the workers keep the
+//! state of the entities they are responsible for.
+//!
+//! Workers are linked to the router and are restarted if they fail. A restarted worker takes over
+//! the keys of the failed one, but messages that were still in the mailbox of the failed worker
+//! are lost.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{
+//!     process::StartProcess,
+//!     router::{Router, RouterArg},
+//!     Mailbox,
+//! };
+//!
+//! fn worker(mailbox: Mailbox<(u64, String)>) {
+//!     loop {
+//!         let (user, event) = mailbox.receive();
+//!         println!("User {}: {}", user, event);
+//!     }
+//! }
+//!
+//! let router = Router::start_link(RouterArg::new(4, |(user, _)| *user, worker), None);
+//! router.route((1, "login".to_owned()));
+//! router.route((1, "logout".to_owned()));
+//! ```
+
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    process::{AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler},
+    serializer::Bincode,
+    storage::Fnv,
+    Mailbox, Process, Tag,
+};
+
+/// The argument a [`Router`] is started with.
+///
+/// It contains the number of workers, the function extracting the key from a message and the
+/// entry function of the workers.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct RouterArg<K, M> {
+    workers: usize,
+    // Function pointers can't be serialized and are passed as integers.
+    key: usize,
+    entry: usize,
+    phantom: PhantomData<(K, M)>,
+}
+
+impl<K, M> RouterArg<K, M>
+where
+    M: Serialize + DeserializeOwned,
+{
+    /// Creates the argument for a router with `workers` workers running `entry`, which routes
+    /// messages by the key returned from `key`.
+    pub fn new(workers: usize, key: fn(&M) -> K, entry: fn(Mailbox<M>)) -> Self {
+        RouterArg {
+            workers,
+            key: key as usize,
+            entry: entry as usize,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// A process routing messages of type `M` by a key of type `K` to its workers.
+///
+/// It's started with a [`RouterArg`]. Keys are assigned to the workers with a consistent hash, so
+/// the assignment only depends on the key and the number of workers.
+///
+/// Shutting down the router kills the workers.
+pub struct Router<K, M>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    key: fn(&M) -> K,
+    entry: usize,
+    workers: Vec<(Process<M, Bincode>, Tag)>,
+}
+
+impl<K, M> Router<K, M>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    fn start_worker(&self) -> (Process<M, Bincode>, Tag) {
+        let tag = Tag::new();
+        (Process::spawn_link_tag(self.entry, tag, worker::<M>), tag)
+    }
+
+    /// Returns the worker responsible for `key`.
+    fn worker(&self, key: &K) -> &Process<M, Bincode> {
+        let mut hasher = Fnv::new();
+        key.hash(&mut hasher);
+        &self.workers[jump_hash(hasher.finish(), self.workers.len())].0
+    }
+}
+
+impl<K, M> AbstractProcess for Router<K, M>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    type Arg = RouterArg<K, M>;
+    type State = Self;
+
+    fn init(_: ProcessRef<Self>, arg: RouterArg<K, M>) -> Self::State {
+        if arg.workers == 0 {
+            panic!(
+                "Router<{}> needs at least one worker",
+                std::any::type_name::<M>()
+            );
+        }
+
+        let mut router = Router {
+            key: unsafe { std::mem::transmute::<usize, fn(&M) -> K>(arg.key) },
+            entry: arg.entry,
+            workers: Vec::with_capacity(arg.workers),
+        };
+        for _ in 0..arg.workers {
+            let worker = router.start_worker();
+            router.workers.push(worker);
+        }
+        router
+    }
+
+    fn terminate(state: Self::State) {
+        state.workers.iter().for_each(|(worker, _)| worker.kill());
+    }
+
+    // Router shouldn't die if the workers die
+    fn trap_links() -> bool {
+        true
+    }
+
+    fn handle_link_trapped(state: &mut Self::State, tag: Tag) {
+        let index = match state
+            .workers
+            .iter()
+            .position(|(_, worker_tag)| *worker_tag == tag)
+        {
+            Some(index) => index,
+            None => panic!(
+                "Router<{}> received kill signal from a died link",
+                std::any::type_name::<M>()
+            ),
+        };
+        state.workers[index] = state.start_worker();
+    }
+}
+
+/// Entry point of the workers, calling the entry function of the router.
+fn worker<M>(entry: usize, mailbox: Mailbox<M>)
+where
+    M: Serialize + DeserializeOwned,
+{
+    let entry = unsafe { std::mem::transmute::<usize, fn(Mailbox<M>)>(entry) };
+    entry(mailbox);
+}
+
+/// Maps `key` to one of `buckets` buckets with the jump consistent hash of Lamping and Veach.
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let mut bucket = 0;
+    let mut next = 0;
+    while next < buckets as i64 {
+        bucket = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    bucket as usize
+}
+
+#[derive(Serialize, Deserialize)]
+struct Route<M>(M);
+impl<K, M> MessageHandler<Route<M>> for Router<K, M>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    fn handle(state: &mut Self::State, Route(message): Route<M>) {
+        let key = (state.key)(&message);
+        state.worker(&key).send(message);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct GetWorkers;
+impl<K, M> RequestHandler<GetWorkers> for Router<K, M>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    type Response = Vec<Process<M, Bincode>>;
+
+    fn handle(state: &mut Self::State, _: GetWorkers) -> Self::Response {
+        state
+            .workers
+            .iter()
+            .map(|(worker, _)| worker.clone())
+            .collect()
+    }
+}
+
+impl<K, M> ProcessRef<Router<K, M>>
+where
+    K: Hash,
+    M: Serialize + DeserializeOwned,
+{
+    /// Sends `message` to the worker responsible for its key.
+    ///
+    /// Messages going through the same router keep their order per key.
+    pub fn route(&self, message: M) {
+        self.send(Route(message));
+    }
+
+    /// Returns all currently running workers.
+    pub fn workers(&self) -> Vec<Process<M, Bincode>> {
+        self.request(GetWorkers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::jump_hash;
+    use lunatic_test::test;
+
+    #[test]
+    fn jump_hash_is_consistent() {
+        for key in 0..1000u64 {
+            let key = key.wrapping_mul(0x9e3779b97f4a7c15);
+            assert_eq!(jump_hash(key, 1), 0);
+            let before = jump_hash(key, 10);
+            let after = jump_hash(key, 11);
+            // Growing the number of buckets only moves keys to the new bucket.
+            assert!(before < 10);
+            assert!(after == before || after == 10);
+        }
+    }
+}
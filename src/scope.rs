@@ -0,0 +1,91 @@
+use std::{cell::RefCell, collections::HashSet};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{serializer::Bincode, Mailbox, Process, Tag};
+
+/// Creates a scope for spawning processes.
+///
+/// All processes spawned with [`Scope::spawn`] are linked to the calling process, and the
+/// function doesn't return until all of them have finished. If one of the processes fails, the
+/// caller fails too. If the caller fails inside of the scope, all processes spawned in it are
+/// killed through the link. This guarantees that no process outlives the scope.
+///
+/// # Example
+///
+/// ```
+/// lunatic::scope(|scope| {
+///     for i in 0..4 {
+///         scope.spawn(i, |i, _: Mailbox<()>| {
+///             println!("Hello from worker {}", i);
+///         });
+///     }
+/// });
+/// // All workers finished at this point.
+/// ```
+pub fn scope<F, R>(f: F) -> R
+where
+    F: FnOnce(&Scope) -> R,
+{
+    let scope = Scope {
+        tag: Tag::new(),
+        running: RefCell::new(HashSet::new()),
+    };
+    let result = f(&scope);
+    // Wait for all processes to finish.
+    let mailbox: Mailbox<u64, Bincode> = unsafe { Mailbox::new() };
+    while !scope.running.borrow().is_empty() {
+        let id = mailbox.tag_receive(Some(&[scope.tag]));
+        scope.running.borrow_mut().remove(&id);
+    }
+    result
+}
+
+/// A scope for spawning processes, created with [`scope`].
+#[derive(Debug)]
+pub struct Scope {
+    // Tag of the messages sent by processes when they finish.
+    tag: Tag,
+    // IDs of the processes that didn't finish yet.
+    running: RefCell<HashSet<u64>>,
+}
+
+impl Scope {
+    /// Spawns a linked process that needs to finish before the scope ends.
+    ///
+    /// Processes that need to be stopped before the end of the scope can be
+    /// [cancelled](Self::cancel).
+    pub fn spawn<C, M>(&self, capture: C, entry: fn(C, Mailbox<M>)) -> Process<M>
+    where
+        C: Serialize + DeserializeOwned,
+        M: Serialize + DeserializeOwned,
+    {
+        let parent = Process::<u64, Bincode>::this();
+        let process = Process::spawn_link((parent, self.tag, capture, entry as usize), scoped);
+        self.running.borrow_mut().insert(process.id());
+        process
+    }
+
+    /// Kills a process spawned in this scope, so that the scope doesn't wait for it.
+    ///
+    /// The process is unlinked first, so its death doesn't take the caller down. Killing it with
+    /// [`Process::kill`] instead would fail the caller through the link.
+    pub fn cancel<M>(&self, process: &Process<M>) {
+        if self.running.borrow_mut().remove(&process.id()) {
+            process.unlink();
+            process.kill();
+        }
+    }
+}
+
+/// Entry point of processes spawned in a [`Scope`].
+fn scoped<C, M>(
+    (parent, tag, capture, entry): (Process<u64, Bincode>, Tag, C, usize),
+    mailbox: Mailbox<M>,
+) where
+    M: Serialize + DeserializeOwned,
+{
+    let entry: fn(C, Mailbox<M>) = unsafe { std::mem::transmute(entry) };
+    entry(capture, mailbox);
+    parent.tag_send(tag, crate::host::process_id());
+}
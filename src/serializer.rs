@@ -1,5 +1,7 @@
 //! Serializer implementations for messages.
 
+use std::marker::PhantomData;
+
 use crate::host::api::message;
 
 use thiserror::Error;
@@ -70,6 +72,13 @@ pub enum DecodeError {
 /// unnecessary copies. Serializer that require raw access to chunks of mutable memories (e.g.
 /// Prost) require additional copies between guest and host memories.
 pub trait Serializer<M> {
+    /// `false` if messages encoded with this serializer don't carry the
+    /// [trace context](crate::trace) of the sender.
+    ///
+    /// The context is written in front of the encoded message. Serializers that define the exact
+    /// layout of a message, so that other guests can read it byte for byte, turn it off.
+    const TRACE_CONTEXT: bool = true;
+
     fn encode(message: &M) -> Result<(), EncodeError>;
     fn decode() -> Result<M, DecodeError>;
 }
@@ -180,6 +189,277 @@ where
     }
 }
 
+/// A serializer for raw binary messages of type `Vec<u8>`.
+///
+/// The bytes are copied directly into and out of the message buffer, without going through
+/// `serde`, and are the whole message. This avoids an intermediate copy of large payloads (e.g. files or images) that other
+/// serializers make. Borrowed data can be sent with
+/// [`Process::send_bytes`](crate::Process::send_bytes).
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{serializer::Bytes, Mailbox, Process};
+///
+/// let child = Process::spawn((), |_, mailbox: Mailbox<Vec<u8>, Bytes>| {
+///     assert_eq!(mailbox.receive().len(), 4 * 1024 * 1024);
+/// });
+/// child.send_bytes(&vec![0; 4 * 1024 * 1024]);
+/// ```
+#[derive(Debug, Hash)]
+pub struct Bytes {}
+
+impl Serializer<Vec<u8>> for Bytes {
+    const TRACE_CONTEXT: bool = false;
+
+    fn encode(message: &Vec<u8>) -> Result<(), EncodeError> {
+        unsafe { message::write_data(message.as_ptr(), message.len()) };
+        Ok(())
+    }
+
+    fn decode() -> Result<Vec<u8>, DecodeError> {
+        let size = unsafe { message::data_size() } as usize;
+        let mut data = vec![0; size];
+        let read = unsafe { message::read_data(data.as_mut_ptr(), size) };
+        data.truncate(read);
+        Ok(data)
+    }
+}
+
+/// Identifies a serializer on the wire, so that guests written in other languages know how to
+/// decode a message.
+///
+/// The IDs of the formats shipped with lunatic are fixed, custom serializers should pick IDs
+/// above 127.
+pub trait SerializerId {
+    const SERIALIZER_ID: u8;
+}
+
+impl SerializerId for Bytes {
+    const SERIALIZER_ID: u8 = 0;
+}
+
+impl SerializerId for Bincode {
+    const SERIALIZER_ID: u8 = 1;
+}
+
+#[cfg(feature = "msgpack_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "msgpack_serializer")))]
+impl SerializerId for MessagePack {
+    const SERIALIZER_ID: u8 = 2;
+}
+
+#[cfg(feature = "json_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "json_serializer")))]
+impl SerializerId for Json {
+    const SERIALIZER_ID: u8 = 3;
+}
+
+#[cfg(feature = "protobuf_serializer")]
+#[cfg_attr(docsrs, doc(cfg(feature = "protobuf_serializer")))]
+impl SerializerId for ProtocolBuffers {
+    const SERIALIZER_ID: u8 = 4;
+}
+
+/// A serializer that prefixes each message with the [`SerializerId`] of `S`.
+///
+/// Other serializers don't mark their messages in any way, so both sides need to agree on the
+/// format up front. This is no problem between processes using this crate, but guests written in
+/// other languages (e.g. AssemblyScript) need to know how to decode the payload. With `Tagged`
+/// the first byte of the message is the ID of the serializer, followed by the message encoded
+/// with `S`. Received messages with a different ID fail to decode. Tagged messages don't carry a
+/// [trace context](crate::trace), which would be written in front of the ID.
+///
+/// Messages from foreign guests that don't match a Rust type can be received as [`RawMessage`]s.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{serializer::{Bincode, Tagged}, Mailbox, Process};
+///
+/// let child = Process::spawn((), |_, mailbox: Mailbox<u64, Tagged<Bincode>>| {
+///     assert_eq!(mailbox.receive(), 42);
+/// });
+/// child.send(42);
+/// ```
+#[derive(Debug, Hash)]
+pub struct Tagged<S> {
+    serializer_type: PhantomData<S>,
+}
+
+impl<M, S> Serializer<M> for Tagged<S>
+where
+    S: Serializer<M> + SerializerId,
+{
+    const TRACE_CONTEXT: bool = false;
+
+    fn encode(message: &M) -> Result<(), EncodeError> {
+        unsafe { message::write_data(&S::SERIALIZER_ID, 1) };
+        S::encode(message)
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        let mut id = 0;
+        if unsafe { message::read_data(&mut id, 1) } != 1 {
+            return Err(DecodeError::Custom(
+                "message is missing a serializer ID".to_owned(),
+            ));
+        }
+        if id != S::SERIALIZER_ID {
+            return Err(DecodeError::Custom(format!(
+                "expected serializer ID {}, got {}",
+                S::SERIALIZER_ID,
+                id
+            )));
+        }
+        S::decode()
+    }
+}
+
+impl<S: SerializerId> SerializerId for Tagged<S> {
+    const SERIALIZER_ID: u8 = S::SERIALIZER_ID;
+}
+
+/// A message prefixed with a serializer ID, whose payload is not decoded.
+///
+/// This is the receiving end for messages in any format sent by a [`Tagged`] serializer, or by a
+/// guest written in another language that follows the same layout. Raw messages can be sent
+/// too, the payload needs to be encoded already.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{
+///     serializer::{Bincode, Raw, RawMessage, SerializerId, Tagged},
+///     Mailbox, Process,
+/// };
+///
+/// let child = Process::spawn((), |_, mailbox: Mailbox<RawMessage, Raw>| {
+///     let message = mailbox.receive();
+///     assert_eq!(message.serializer_id, Bincode::SERIALIZER_ID);
+/// });
+/// let child = Process::<u64, Tagged<Bincode>>::new(child.node_id(), child.id());
+/// child.send(42);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RawMessage {
+    /// The [`SerializerId`] of the payload.
+    pub serializer_id: u8,
+    /// The encoded message.
+    pub payload: Vec<u8>,
+}
+
+/// A serializer for [`RawMessage`]s.
+///
+/// Same as [`Bytes`], the message can't contain resources.
+#[derive(Debug, Hash)]
+pub struct Raw {}
+
+impl Serializer<RawMessage> for Raw {
+    const TRACE_CONTEXT: bool = false;
+
+    fn encode(message: &RawMessage) -> Result<(), EncodeError> {
+        unsafe { message::write_data(&message.serializer_id, 1) };
+        Bytes::encode(&message.payload)
+    }
+
+    fn decode() -> Result<RawMessage, DecodeError> {
+        let mut serializer_id = 0;
+        if unsafe { message::read_data(&mut serializer_id, 1) } != 1 {
+            return Err(DecodeError::Custom(
+                "message is missing a serializer ID".to_owned(),
+            ));
+        }
+        // The rest of the message is the payload.
+        let payload = Bytes::decode()?;
+        Ok(RawMessage {
+            serializer_id,
+            payload,
+        })
+    }
+}
+
+/// A [`Bincode`] serializer that compresses messages larger than `THRESHOLD` bytes with LZ4.
+///
+/// Compression mostly pays off for big messages sent to other nodes, e.g. large state transfers,
+/// where the bandwidth matters more than the time spent compressing. Smaller messages are sent
+/// uncompressed, same as messages that don't get smaller. Unlike other serializers, the message
+/// is first encoded into a buffer in the guest, before it's compressed and copied to the host.
+///
+/// The first byte of each message tells if it's compressed, so both sides need to use
+/// `Compressed`, but the threshold can differ between them.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::{serializer::Compressed, Mailbox, Process};
+///
+/// let child = Process::spawn((), |_, mailbox: Mailbox<String, Compressed>| {
+///     assert_eq!(mailbox.receive().len(), 1024 * 1024);
+/// });
+/// child.send("a".repeat(1024 * 1024));
+/// ```
+#[derive(Debug, Hash)]
+pub struct Compressed<const THRESHOLD: usize = 4096> {}
+
+const UNCOMPRESSED: u8 = 0;
+const LZ4: u8 = 1;
+
+impl<M, const THRESHOLD: usize> Serializer<M> for Compressed<THRESHOLD>
+where
+    M: serde::Serialize + serde::de::DeserializeOwned,
+{
+    fn encode(message: &M) -> Result<(), EncodeError> {
+        let data = bincode::serialize(message)?;
+        if data.len() > THRESHOLD {
+            let compressed = crate::lz4::compress(&data);
+            if compressed.len() + 8 < data.len() {
+                let size = (data.len() as u64).to_le_bytes();
+                unsafe {
+                    message::write_data(&LZ4, 1);
+                    message::write_data(size.as_ptr(), size.len());
+                    message::write_data(compressed.as_ptr(), compressed.len());
+                };
+                return Ok(());
+            }
+        }
+        unsafe {
+            message::write_data(&UNCOMPRESSED, 1);
+            message::write_data(data.as_ptr(), data.len());
+        };
+        Ok(())
+    }
+
+    fn decode() -> Result<M, DecodeError> {
+        let mut format = 0;
+        if unsafe { message::read_data(&mut format, 1) } != 1 {
+            return Err(DecodeError::Custom(
+                "message is missing the compression format".to_owned(),
+            ));
+        }
+        match format {
+            UNCOMPRESSED => bincode::deserialize_from(MessageRw {}).map_err(|err| err.into()),
+            LZ4 => {
+                let mut size = [0; 8];
+                if unsafe { message::read_data(size.as_mut_ptr(), size.len()) } != size.len() {
+                    return Err(DecodeError::Custom(
+                        "compressed message is missing its size".to_owned(),
+                    ));
+                }
+                let size = u64::from_le_bytes(size) as usize;
+                let data = crate::lz4::decompress(&Bytes::decode()?, size).ok_or_else(|| {
+                    DecodeError::Custom("compressed message is malformed".to_owned())
+                })?;
+                bincode::deserialize(&data).map_err(|err| err.into())
+            }
+            format => Err(DecodeError::Custom(format!(
+                "unknown compression format {}",
+                format
+            ))),
+        }
+    }
+}
+
 /// A helper struct to read from and write to the message scratch buffer.
 ///
 /// It simplifies streaming serialization/deserialization directly from the host and avoids copies.
@@ -0,0 +1,136 @@
+//! Named services that can be reached from any node of the cluster.
+//!
+//! A [`Service`] is an [`AbstractProcess`] registered under a name on all nodes, so that clients
+//! don't need to know on which node the server runs. Connecting to a service returns a
+//! [`ProcessRef`], a typed client that can only send the messages and requests the server
+//! handles.
+//!
+//! Services are built on top of the [`global`](crate::registry::global) registry and share its
+//! limitations: the name is only visible on nodes that were reachable at the time of the
+//! registration. Requests to a server on another node only work if both nodes run the same
+//! module, because the handlers are identified by their function table index.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{
+//!     process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartProcess},
+//!     service::Service,
+//! };
+//!
+//! struct Billing;
+//!
+//! impl AbstractProcess for Billing {
+//!     type Arg = ();
+//!     type State = Self;
+//!
+//!     fn init(_: ProcessRef<Self>, _: ()) -> Self {
+//!         Self
+//!     }
+//! }
+//!
+//! impl RequestHandler<u64> for Billing {
+//!     type Response = u64;
+//!
+//!     fn handle(_: &mut Self::State, amount: u64) -> u64 {
+//!         amount * 2
+//!     }
+//! }
+//!
+//! let server = Billing::start((), None);
+//! Service::register("billing", &server);
+//!
+//! // On any node in the cluster.
+//! let billing = Service::<Billing>::connect("billing").unwrap();
+//! assert_eq!(billing.request(21), 42);
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    process::{AbstractProcess, ProcessRef},
+    registry::global,
+    Process,
+};
+
+/// A handle to the service `name`, served by a process of type `T`.
+///
+/// The handle only stores the name. Each [`resolve`](Self::resolve) looks up the current server,
+/// so a handle keeps working if the server is restarted and registered again.
+#[derive(Serialize, Deserialize)]
+pub struct Service<T> {
+    name: String,
+    #[serde(skip_serializing, default)]
+    phantom: PhantomData<T>,
+}
+
+impl<T> Service<T>
+where
+    T: AbstractProcess,
+{
+    /// Returns a handle to the service `name`.
+    ///
+    /// The service doesn't need to be registered yet.
+    pub fn new(name: &str) -> Self {
+        Service {
+            name: name.to_owned(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Registers `server` as the service `name` on all nodes of the cluster.
+    ///
+    /// If the service is already registered, the new server replaces the old one. Blocks until
+    /// the name is registered on all nodes.
+    pub fn register(name: &str, server: &ProcessRef<T>) -> Self {
+        let service = Self::new(name);
+        let server: Process<T> = Process::new(server.node_id(), server.id());
+        global::register(&service.global_name(), &server);
+        service
+    }
+
+    /// Returns a client of the service `name`, or `None` if it's not registered.
+    pub fn connect(name: &str) -> Option<ProcessRef<T>> {
+        Self::new(name).resolve()
+    }
+
+    /// Returns the name of the service.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns a client of the server currently registered for the service.
+    pub fn resolve(&self) -> Option<ProcessRef<T>> {
+        let server = global::lookup::<T>(&self.global_name())?;
+        // The type of the server is encoded into the registered name.
+        Some(unsafe { ProcessRef::new(server.node_id(), server.id()) })
+    }
+
+    /// Removes the service from all nodes of the cluster.
+    ///
+    /// The server itself keeps running.
+    pub fn unregister(self) {
+        global::unregister::<T>(&self.global_name());
+    }
+
+    fn global_name(&self) -> String {
+        format!("{} + Service", self.name)
+    }
+}
+
+impl<T> Clone for Service<T> {
+    fn clone(&self) -> Self {
+        Service {
+            name: self.name.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Service<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Service").field("name", &self.name).finish()
+    }
+}
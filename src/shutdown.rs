@@ -0,0 +1,197 @@
+//! Graceful shutdown of the processes on a node.
+//!
+//! Services often need to flush buffers or close connections before they stop. A process that
+//! [`subscribe`]s receives a [`ShutdownSignal`] once [`shutdown`] is called, and has until the end
+//! of the grace period to [`acknowledge`](ShutdownSignal::acknowledge) it. Subscribers that don't
+//! acknowledge the signal in time are killed.
+//!
+//! The lunatic runtime doesn't notify processes when the node is terminating, so the shutdown
+//! needs to be started by the application itself, e.g. by the main process before it returns.
+//! Subscribers are tracked with a [`ProcessGroup`], which is local to the node.
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use lunatic::{shutdown, Mailbox, Process};
+//!
+//! Process::spawn((), |_, _: Mailbox<()>| {
+//!     let subscription = shutdown::subscribe();
+//!     let signal = subscription.receive();
+//!     // Flush buffers, close sockets, ...
+//!     signal.acknowledge();
+//! });
+//!
+//! // Later, before the node stops.
+//! let killed = shutdown::shutdown(Duration::from_secs(5));
+//! ```
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    group::ProcessGroup, serializer::Bincode, time::Deadline, Mailbox, Process, ReceiveError, Tag,
+};
+
+/// Time the initiator of a shutdown waits for the reports after the grace period ended.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Messages handled by the helper process of a subscription.
+#[derive(Serialize, Deserialize)]
+enum Command {
+    /// Starts the shutdown, the helper reports to the process with the tag if it had to kill.
+    Shutdown(Process<bool>, Tag, Duration),
+    Acknowledge,
+    Unsubscribe,
+}
+
+/// Signal that the node is shutting down, delivered to subscribed processes.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ShutdownSignal {
+    helper: Process<Command>,
+    grace: Duration,
+}
+
+impl ShutdownSignal {
+    /// Returns the grace period, after which the process is killed if it didn't acknowledge the
+    /// signal.
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Tells the initiator of the shutdown that the process is ready to stop.
+    ///
+    /// The process is not killed after acknowledging, but it should finish soon.
+    pub fn acknowledge(self) {
+        self.helper.send(Command::Acknowledge);
+    }
+}
+
+/// Subscribes the current process to the shutdown of the node.
+///
+/// The process should [`unsubscribe`](ShutdownSubscription::unsubscribe) if it finishes before
+/// the shutdown, failed processes are removed automatically.
+pub fn subscribe() -> ShutdownSubscription {
+    let tag = Tag::new();
+    let this = Process::<ShutdownSignal, Bincode>::this();
+    let helper = Process::spawn((this, tag), helper);
+    group().join(&helper);
+    ShutdownSubscription { helper, tag }
+}
+
+/// Sends a [`ShutdownSignal`] to all subscribed processes and waits for them to acknowledge it.
+///
+/// Subscribers that didn't acknowledge the signal after the `grace` period are killed. Returns
+/// the number of killed processes. The caller itself shouldn't be subscribed, otherwise it may
+/// be killed too.
+pub fn shutdown(grace: Duration) -> usize {
+    let helpers = group().members();
+    let tag = Tag::new();
+    let this = Process::<bool, Bincode>::this();
+    for helper in helpers.iter() {
+        helper.send(Command::Shutdown(this.clone(), tag, grace));
+    }
+
+    // Temporarily cast to right mailbox type.
+    let mailbox: Mailbox<bool, Bincode> = unsafe { Mailbox::new() };
+    let deadline = Deadline::after(grace + REPORT_TIMEOUT);
+    let mut killed = 0;
+    for _ in helpers.iter() {
+        match mailbox.tag_receive_timeout(Some(&[tag]), deadline.remaining()) {
+            Ok(true) => killed += 1,
+            Ok(false) => {}
+            Err(_) => break,
+        }
+    }
+    killed
+}
+
+/// A subscription to the shutdown of the node, created with [`subscribe`].
+///
+/// Each subscription is watched by a helper process that delivers the [`ShutdownSignal`] to the
+/// subscribed process, tagged with the [`tag`](Self::tag) of the subscription, and kills it once
+/// the grace period ends.
+#[derive(Debug)]
+pub struct ShutdownSubscription {
+    helper: Process<Command>,
+    tag: Tag,
+}
+
+impl ShutdownSubscription {
+    /// Returns the tag used for the [`ShutdownSignal`] message.
+    pub fn tag(&self) -> Tag {
+        self.tag
+    }
+
+    /// Blocks until the shutdown starts.
+    pub fn receive(&self) -> ShutdownSignal {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ShutdownSignal, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive(Some(&[self.tag]))
+    }
+
+    /// Same as [`receive`](Self::receive), but only waits for the duration of timeout.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<ShutdownSignal, ReceiveError> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<ShutdownSignal, Bincode> = unsafe { Mailbox::new() };
+        mailbox.tag_receive_timeout(Some(&[self.tag]), timeout)
+    }
+
+    /// Stops the subscription.
+    pub fn unsubscribe(self) {
+        group().leave(&self.helper);
+        self.helper.send(Command::Unsubscribe);
+    }
+}
+
+fn group() -> ProcessGroup<Command> {
+    ProcessGroup::new("lunatic::shutdown")
+}
+
+/// Entry point of the helper process that delivers the signal and enforces the grace period.
+fn helper((subscriber, tag): (Process<ShutdownSignal>, Tag), mailbox: Mailbox<Command>) {
+    // The helper should survive the death of the subscriber.
+    let mailbox = mailbox.catch_link_failure();
+    subscriber.link();
+    let this = mailbox.this();
+    loop {
+        match mailbox.receive() {
+            Ok(Command::Shutdown(initiator, reply, grace)) => {
+                subscriber.tag_send(
+                    tag,
+                    ShutdownSignal {
+                        helper: this.clone(),
+                        grace,
+                    },
+                );
+                let deadline = Deadline::after(grace);
+                let finished = loop {
+                    match mailbox.receive_(None, Some(deadline.remaining())) {
+                        // The subscriber acknowledged, unsubscribed or failed.
+                        Ok(Ok(Command::Acknowledge | Command::Unsubscribe)) | Ok(Err(_)) => {
+                            break true
+                        }
+                        Ok(Ok(Command::Shutdown(..))) => {}
+                        Err(_) => break false,
+                    }
+                };
+                if !finished {
+                    subscriber.kill();
+                }
+                initiator.tag_send(reply, !finished);
+                break;
+            }
+            // Left over from an earlier shutdown.
+            Ok(Command::Acknowledge) => {}
+            Ok(Command::Unsubscribe) => {
+                subscriber.unlink();
+                return;
+            }
+            // The subscriber failed.
+            Err(_) => break,
+        }
+    }
+    group().leave(&this);
+}
@@ -0,0 +1,370 @@
+//! In-memory key-value tables shared between processes.
+//!
+//! A [`Table`] is an [`AbstractProcess`] owning a map. All other processes access the map through
+//! requests, so that the state can be shared without hand-rolling a process for it each time.
+//! Like any other `AbstractProcess`, a table can be registered under a name and be a child of a
+//! [`Supervisor`](crate::supervisor::Supervisor). A restarted table starts out empty.
+//!
+//! Because a table handles one request at a time, a busy table can become a bottleneck. A
+//! [`ShardedTable`] spreads the keys over multiple tables.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{process::StartProcess, storage::Table};
+//!
+//! let table = Table::<String, u64>::start_link((), Some("visits"));
+//! table.insert("/".to_owned(), 1);
+//! table.update("/".to_owned(), |visits| visits.map(|visits| visits + 1));
+//! assert_eq!(table.get("/".to_owned()), Some(2));
+//! ```
+
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::process::{
+    AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler, StartProcess,
+};
+
+/// A process owning a map from `K` to `V`.
+///
+/// Keys can be inserted with a time to live (TTL), after which they are removed from the table.
+pub struct Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    this: ProcessRef<Table<K, V>>,
+    entries: HashMap<K, Entry<V>>,
+}
+
+struct Entry<V> {
+    value: V,
+    expires: Option<Instant>,
+}
+
+impl<V> Entry<V> {
+    fn expired(&self, now: Instant) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+}
+
+impl<K, V> Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    /// Returns the entry of `key`, removing it first if it expired.
+    fn entry(&mut self, key: &K) -> Option<&mut Entry<V>> {
+        if self.entries.get(key)?.expired(Instant::now()) {
+            self.entries.remove(key);
+            return None;
+        }
+        self.entries.get_mut(key)
+    }
+}
+
+impl<K, V> AbstractProcess for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Arg = ();
+    type State = Self;
+
+    fn init(this: ProcessRef<Self>, _: ()) -> Self::State {
+        Table {
+            this,
+            entries: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Insert<K, V>(K, V, Option<Duration>);
+impl<K, V> RequestHandler<Insert<K, V>> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Response = Option<V>;
+
+    fn handle(state: &mut Self::State, Insert(key, value, ttl): Insert<K, V>) -> Option<V> {
+        let previous = state.entry(&key).map(|entry| entry.value.clone());
+        let expires = ttl.map(|ttl| {
+            // The timer only removes the key if it wasn't re-inserted with a later deadline.
+            state.this.send_after(Expire(key.clone()), ttl);
+            Instant::now() + ttl
+        });
+        state.entries.insert(key, Entry { value, expires });
+        previous
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Expire<K>(K);
+impl<K, V> MessageHandler<Expire<K>> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    fn handle(state: &mut Self::State, Expire(key): Expire<K>) {
+        // Looking up the entry removes it if it expired.
+        state.entry(&key);
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Get<K>(K);
+impl<K, V> RequestHandler<Get<K>> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Response = Option<V>;
+
+    fn handle(state: &mut Self::State, Get(key): Get<K>) -> Option<V> {
+        state.entry(&key).map(|entry| entry.value.clone())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Remove<K>(K);
+impl<K, V> RequestHandler<Remove<K>> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Response = Option<V>;
+
+    fn handle(state: &mut Self::State, Remove(key): Remove<K>) -> Option<V> {
+        state.entry(&key)?;
+        state.entries.remove(&key).map(|entry| entry.value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Update<K>(K, usize);
+impl<K, V> RequestHandler<Update<K>> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Response = Option<V>;
+
+    fn handle(state: &mut Self::State, Update(key, function): Update<K>) -> Option<V> {
+        // The function pointer stays valid, because the table runs the same module as the caller.
+        let function: fn(Option<V>) -> Option<V> = unsafe { std::mem::transmute(function) };
+        let (value, expires) = match state.entry(&key) {
+            Some(_) => {
+                let entry = state.entries.remove(&key).unwrap();
+                (Some(entry.value), entry.expires)
+            }
+            None => (None, None),
+        };
+        let value = function(value)?;
+        state.entries.insert(
+            key,
+            Entry {
+                value: value.clone(),
+                expires,
+            },
+        );
+        Some(value)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entries;
+impl<K, V> RequestHandler<Entries> for Table<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    type Response = Vec<(K, V)>;
+
+    fn handle(state: &mut Self::State, _: Entries) -> Self::Response {
+        let now = Instant::now();
+        state.entries.retain(|_, entry| !entry.expired(now));
+        state
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+}
+
+impl<K, V> ProcessRef<Table<K, V>>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    /// Inserts `value` under `key`, returning the previous value.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.request(Insert(key, value, None))
+    }
+
+    /// Inserts `value` under `key` for the duration of `ttl`, returning the previous value.
+    ///
+    /// Inserting the key again replaces the TTL.
+    pub fn insert_ttl(&self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.request(Insert(key, value, Some(ttl)))
+    }
+
+    /// Returns the value of `key`.
+    pub fn get(&self, key: K) -> Option<V> {
+        self.request(Get(key))
+    }
+
+    /// Removes `key` from the table, returning its value.
+    pub fn remove(&self, key: K) -> Option<V> {
+        self.request(Remove(key))
+    }
+
+    /// Replaces the value of `key` with the result of `function`, and returns the new value.
+    ///
+    /// `function` is called inside of the table process with the current value, or `None` if the
+    /// key doesn't exist. If it returns `None`, the key is removed. Because no other request is
+    /// handled in the meantime, this can be used for atomic updates, like incrementing a counter.
+    /// The TTL of the key is kept.
+    pub fn update(&self, key: K, function: fn(Option<V>) -> Option<V>) -> Option<V> {
+        self.request(Update(key, function as usize))
+    }
+
+    /// Returns all entries of the table, in arbitrary order.
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.request(Entries)
+    }
+}
+
+/// A table partitioned into multiple [`Table`] processes.
+///
+/// Each key is assigned to one of the shards by its hash, so that requests for different keys can
+/// be handled in parallel instead of queueing up in a single process. The shards are linked to
+/// the process that created the table. A `ShardedTable` can be cloned or sent to other processes,
+/// all copies access the same shards.
+///
+/// # Example
+///
+/// ```
+/// use lunatic::storage::ShardedTable;
+///
+/// let table = ShardedTable::<u64, String>::new(4);
+/// table.insert(1, "one".to_owned());
+/// assert_eq!(table.get(1), Some("one".to_owned()));
+/// ```
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct ShardedTable<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    shards: Vec<ProcessRef<Table<K, V>>>,
+}
+
+impl<K, V> ShardedTable<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash + Clone,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    /// Starts a table with `num_shards` shards, linked to the caller.
+    ///
+    /// # Panics
+    ///
+    /// This function will panic if `num_shards` is 0.
+    pub fn new(num_shards: usize) -> Self {
+        assert!(num_shards > 0, "ShardedTable needs at least one shard");
+        ShardedTable {
+            shards: (0..num_shards)
+                .map(|_| Table::start_link((), None))
+                .collect(),
+        }
+    }
+
+    /// Returns the shards of the table.
+    pub fn shards(&self) -> &[ProcessRef<Table<K, V>>] {
+        &self.shards
+    }
+
+    /// Returns the shard that `key` belongs to.
+    pub fn shard(&self, key: &K) -> &ProcessRef<Table<K, V>> {
+        let mut hasher = Fnv::new();
+        key.hash(&mut hasher);
+        &self.shards[(hasher.finish() % self.shards.len() as u64) as usize]
+    }
+
+    /// Inserts `value` under `key`, returning the previous value.
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        self.shard(&key).insert(key, value)
+    }
+
+    /// Inserts `value` under `key` for the duration of `ttl`, returning the previous value.
+    pub fn insert_ttl(&self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.shard(&key).insert_ttl(key, value, ttl)
+    }
+
+    /// Returns the value of `key`.
+    pub fn get(&self, key: K) -> Option<V> {
+        self.shard(&key).get(key)
+    }
+
+    /// Removes `key` from the table, returning its value.
+    pub fn remove(&self, key: K) -> Option<V> {
+        self.shard(&key).remove(key)
+    }
+
+    /// Replaces the value of `key` with the result of `function`, and returns the new value.
+    ///
+    /// See [`ProcessRef::update`] for details.
+    pub fn update(&self, key: K, function: fn(Option<V>) -> Option<V>) -> Option<V> {
+        self.shard(&key).update(key, function)
+    }
+
+    /// Returns all entries of the table, in arbitrary order.
+    ///
+    /// The shards are queried one after another, so the result is not a consistent snapshot of
+    /// the whole table.
+    pub fn entries(&self) -> Vec<(K, V)> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.entries())
+            .collect()
+    }
+}
+
+impl<K, V> Clone for ShardedTable<K, V>
+where
+    K: Serialize + DeserializeOwned + Eq + Hash,
+    V: Serialize + DeserializeOwned + Clone,
+{
+    fn clone(&self) -> Self {
+        ShardedTable {
+            shards: self.shards.clone(),
+        }
+    }
+}
+
+/// 64-bit FNV-1a hasher, stable across processes.
+pub(crate) struct Fnv(u64);
+
+impl Fnv {
+    pub(crate) fn new() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fnv {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
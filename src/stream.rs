@@ -0,0 +1,201 @@
+//! Typed streams of values between two processes, with flow control.
+//!
+//! Mailboxes are unbounded, so a producer sending faster than the consumer can process the
+//! messages grows the consumer's memory without limits. A [`Stream`] and its [`Sink`] use
+//! credit-based flow control instead: the producer can only send as many values as the
+//! consumer's window allows, and blocks until the consumer has received some of them.
+//!
+//! The pair is created with [`channel`] in the consuming process. The [`Sink`] is then sent to
+//! the producing process.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{stream, Mailbox, Process};
+//!
+//! let (sink, stream) = stream::channel::<u64>(16);
+//! Process::spawn(sink, |mut sink, _: Mailbox<()>| {
+//!     for i in 0..1000 {
+//!         // Blocks if the consumer falls behind by 16 values.
+//!         sink.send(i);
+//!     }
+//!     sink.close();
+//! });
+//!
+//! let sum: u64 = stream.sum();
+//! assert_eq!(sum, 499500);
+//! ```
+
+use std::marker::PhantomData;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{serializer::Bincode, Mailbox, Process, Tag};
+
+/// Creates a new stream in the current process, together with the sink that values are sent to.
+///
+/// `window` is the maximal number of values that can be sent to the stream without being
+/// received yet.
+///
+/// # Panics
+///
+/// This function will panic if the `window` is 0.
+pub fn channel<T>(window: usize) -> (Sink<T>, Stream<T>)
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    if window == 0 {
+        panic!("stream window needs to be at least 1");
+    }
+    let tag = Tag::new();
+    let sink = Sink {
+        consumer: Process::this(),
+        tag,
+        credit: window,
+        producer: None,
+    };
+    let stream = Stream {
+        tag,
+        window,
+        producer: None,
+        received: 0,
+        closed: false,
+        phantom: PhantomData,
+    };
+    (sink, stream)
+}
+
+/// Messages sent from the sink to the stream.
+#[derive(Serialize, Deserialize)]
+enum StreamMessage<T> {
+    /// The first message, contains the producer and the tag used for credits.
+    Open(Process<usize, Bincode>, Tag),
+    Item(T),
+    Close,
+}
+
+/// The sending half of a [`Stream`].
+///
+/// The sink can be sent to another process, but must only be used by one process.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Sink<T> {
+    consumer: Process<StreamMessage<T>, Bincode>,
+    // Tag of the values sent to the consumer.
+    tag: Tag,
+    // Number of values that can be sent without waiting.
+    credit: usize,
+    // Tag used for credits, set by the producer on the first send.
+    producer: Option<Tag>,
+}
+
+impl<T> Sink<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    /// Sends a value to the stream.
+    ///
+    /// If the window of the stream is full, blocks until the consumer receives some of the
+    /// values.
+    pub fn send(&mut self, value: T) {
+        let credit_tag = self.open();
+        if self.credit == 0 {
+            // Temporarily cast to right mailbox type.
+            let mailbox: Mailbox<usize, Bincode> = unsafe { Mailbox::new() };
+            self.credit += mailbox.tag_receive(Some(&[credit_tag]));
+        }
+        self.credit -= 1;
+        self.consumer.tag_send(self.tag, StreamMessage::Item(value));
+    }
+
+    /// Closes the stream.
+    ///
+    /// The consumer receives all values sent before the stream ends.
+    pub fn close(mut self) {
+        self.open();
+        self.consumer.tag_send(self.tag, StreamMessage::Close);
+    }
+
+    /// Lets the consumer know where credits should be sent to, returns the tag of the credits.
+    fn open(&mut self) -> Tag {
+        match self.producer {
+            Some(credit_tag) => credit_tag,
+            None => {
+                // The tag is created in the producer, so that it's unique in its mailbox.
+                let credit_tag = Tag::new();
+                self.producer = Some(credit_tag);
+                self.consumer
+                    .tag_send(self.tag, StreamMessage::Open(Process::this(), credit_tag));
+                credit_tag
+            }
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Sink<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sink")
+            .field("consumer", &self.consumer)
+            .field("credit", &self.credit)
+            .finish()
+    }
+}
+
+/// The receiving half created by [`channel`].
+///
+/// The stream is an [`Iterator`] over the received values, that ends when the [`Sink`] is
+/// closed. It must only be used by the process that created it.
+#[derive(Debug)]
+pub struct Stream<T> {
+    tag: Tag,
+    window: usize,
+    producer: Option<(Process<usize, Bincode>, Tag)>,
+    // Values received since the last credit was sent.
+    received: usize,
+    closed: bool,
+    phantom: PhantomData<T>,
+}
+
+impl<T> Stream<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    /// Returns the next value, or `None` if the sink was closed.
+    ///
+    /// Blocks until the next value arrives.
+    pub fn receive(&mut self) -> Option<T> {
+        // Temporarily cast to right mailbox type.
+        let mailbox: Mailbox<StreamMessage<T>, Bincode> = unsafe { Mailbox::new() };
+        while !self.closed {
+            match mailbox.tag_receive(Some(&[self.tag])) {
+                StreamMessage::Open(producer, credit_tag) => {
+                    self.producer = Some((producer, credit_tag));
+                }
+                StreamMessage::Item(value) => {
+                    self.received += 1;
+                    // Return credits in batches, to not send a message for every value.
+                    if self.received >= (self.window / 2).max(1) {
+                        if let Some((producer, credit_tag)) = self.producer.as_ref() {
+                            producer.tag_send(*credit_tag, self.received);
+                        }
+                        self.received = 0;
+                    }
+                    return Some(value);
+                }
+                StreamMessage::Close => self.closed = true,
+            }
+        }
+        None
+    }
+}
+
+impl<T> Iterator for Stream<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.receive()
+    }
+}
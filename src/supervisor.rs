@@ -1,11 +1,56 @@
+use std::collections::VecDeque;
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-use crate::process::{AbstractProcess, ProcessRef, StartFailableProcess, Subscriber};
-use crate::{host, Tag};
+use crate::process::{
+    supervise, AbstractProcess, ProcessRef, Request, RequestHandler, StartFailableProcess,
+    Subscriber,
+};
+use crate::serializer::{Bincode, Serializer};
+use crate::{Process, Tag};
 
 /// A `Supervisor` can detect failures (panics) inside [`AbstractProcesses`](AbstractProcess) and
 /// restart them.
 ///
+/// Supervisors are also `AbstractProcesses` and can be children of other supervisors, forming a
+/// supervision tree. If a child supervisor fails (e.g. because it reached its
+/// [maximum restart intensity](SupervisorConfig::set_max_restarts)), the parent restarts it with
+/// the original arguments, which in turn restarts the whole subtree.
+///
+/// Children can keep their state across restarts by implementing
+/// [`AbstractProcess::snapshot`]. The supervisor stores the latest snapshot of each child and
+/// restarts it with the snapshot instead of the original argument.
+///
+/// Children that shouldn't be restarted, like cleanup tasks that are allowed to finish and die,
+/// can be marked with [`Restart::Temporary`] using
+/// [`SupervisorConfig::set_restart`].
+///
+/// Panicking children send a [`CrashReport`] to their supervisor. The last report of each child
+/// can be inspected with [`which_children`](ProcessRef::which_children), and all reports can be
+/// forwarded to a crash log process with [`SupervisorConfig::set_crash_log`].
+///
+/// When the supervisor shuts down, it shuts down its children in reverse start order, so children
+/// can rely on the ones started before them (e.g. a database connection used by workers) until
+/// they are shut down themselves. A child that doesn't finish shutting down inside its
+/// [shutdown timeout](SupervisorConfig::set_shutdown_timeout) is killed, and the supervisor
+/// continues with the next one.
+///
+/// Other processes can observe the lifecycle of the children without changing their code, by
+/// subscribing to the [`SupervisorEvent`]s of the supervisor with
+/// [`subscribe_events`](ProcessRef::subscribe_events), e.g. for alerting or dashboards.
+///
+/// # Upgrading children
+///
+/// Children can't be upgraded to a new version of the code, e.g. a different [`WasmModule`].
+/// Messages and requests sent to an `AbstractProcess` carry the function table index of their
+/// handler in the sender's module, which only matches the receiver if both run the same module.
+/// A [`WasmModule`] resource also belongs to the process that compiled it, so it can't be handed
+/// over to a supervisor. Processes spawned from a new module version should use messages that
+/// don't depend on the code layout, like a [`Process`](crate::Process) and plain data, and can be
+/// swapped in the [`registry`](crate::registry) once the state was migrated.
+///
+/// [`WasmModule`]: crate::WasmModule
+///
 /// # Example
 ///
 /// ```
@@ -30,6 +75,9 @@ use crate::{host, Tag};
 /// let hello = ProcessRef::<Counter>::lookup("hello").unwrap();
 /// let count2 = hello.request(Count);
 /// assert_eq!(count1, count2);
+/// // Or ask the supervisor for the current process of the child.
+/// let hello = sup.get_child::<Counter>("hello").unwrap();
+/// assert_eq!(hello.request(Count), count1);
 /// ```
 pub trait Supervisor
 where
@@ -60,9 +108,6 @@ where
     type State = SupervisorConfig<T>;
 
     fn init(_: ProcessRef<Self>, arg: T::Arg) -> Self::State {
-        // Supervisor shouldn't die if the children die
-        unsafe { host::api::process::die_when_link_dies(0) };
-
         let mut config = SupervisorConfig::default();
         <T as Supervisor>::init(&mut config, arg);
 
@@ -81,25 +126,76 @@ where
         config.terminate();
     }
 
+    // Supervisor shouldn't die if the children die
+    fn trap_links() -> bool {
+        true
+    }
+
     fn handle_link_trapped(config: &mut SupervisorConfig<T>, tag: Tag) {
+        // Children killed after their shutdown timeout didn't fail.
+        if config.take_killed(tag) {
+            return;
+        }
+        config.child_crashed(tag);
+        config.register_restart();
         T::Children::handle_failure(config, tag);
     }
 }
 
+/// Determines which children are restarted when one of them fails.
+///
+/// The strategies follow the semantics of Erlang/OTP supervisors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SupervisorStrategy {
+    /// Only the failed child is restarted.
     OneForOne,
+    /// All other children are shut down (in reverse start order) and then all children are
+    /// restarted.
     OneForAll,
+    /// The children started after the failed one are shut down (in reverse start order), then the
+    /// failed child and the ones after it are restarted.
     RestForOne,
 }
 
+/// Determines if a child is restarted after it fails.
+///
+/// The policies follow the semantics of Erlang/OTP child specifications. The runtime only
+/// notifies supervisors about failed children, so [`Permanent`](Restart::Permanent) and
+/// [`Transient`](Restart::Transient) children currently behave the same.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Restart {
+    /// The child is always restarted.
+    Permanent,
+    /// The child is only restarted if it terminates abnormally.
+    Transient,
+    /// The child is never restarted, not even if it's shut down because of a sibling's failure.
+    ///
+    /// After it terminates, the reference returned by [`children`](ProcessRef::children) points
+    /// to a process that doesn't exist anymore.
+    Temporary,
+}
+
 pub struct SupervisorConfig<T>
 where
     T: Supervisor,
 {
     strategy: SupervisorStrategy,
+    max_restarts: Option<usize>,
+    max_seconds: u64,
+    restarts: VecDeque<Instant>,
     children: Option<<<T as Supervisor>::Children as Supervisable<T>>::Processes>,
     children_args: Option<<<T as Supervisor>::Children as Supervisable<T>>::Args>,
     children_tags: Option<<<T as Supervisor>::Children as Supervisable<T>>::Tags>,
+    restart_policies: Vec<Restart>,
+    shutdown_timeouts: Vec<Option<Duration>>,
+    // Tags of children that were killed after their shutdown timeout, their link failures are
+    // expected.
+    killed: Vec<Tag>,
+    // Indexes of temporary children that terminated and were not restarted.
+    terminated: Vec<usize>,
+    children_stats: Vec<ChildStats>,
+    crash_log: Option<Process<CrashReport>>,
+    observers: Vec<Process<SupervisorEvent>>,
     terminate_subscribers: Vec<Subscriber>,
     phantom: PhantomData<T>,
 }
@@ -108,10 +204,72 @@ impl<T> SupervisorConfig<T>
 where
     T: Supervisor,
 {
+    /// Sets the restart strategy of the supervisor. Defaults to [`SupervisorStrategy::OneForOne`].
     pub fn set_strategy(&mut self, strategy: SupervisorStrategy) {
         self.strategy = strategy;
     }
 
+    /// Returns the restart strategy of the supervisor.
+    pub fn strategy(&self) -> SupervisorStrategy {
+        self.strategy
+    }
+
+    /// Sets the maximum number of restarts allowed inside the time window set with
+    /// [`set_max_seconds`](Self::set_max_seconds).
+    ///
+    /// If there are more restarts inside the window, the supervisor gives up and fails itself,
+    /// taking down all children and propagating the failure to the processes it's linked to. By
+    /// default the number of restarts is unlimited.
+    pub fn set_max_restarts(&mut self, max_restarts: usize) {
+        self.max_restarts = Some(max_restarts);
+    }
+
+    /// Sets the length of the time window, in seconds, used by
+    /// [`set_max_restarts`](Self::set_max_restarts). Defaults to 5 seconds.
+    pub fn set_max_seconds(&mut self, max_seconds: u64) {
+        self.max_seconds = max_seconds;
+    }
+
+    /// Sets the restart policy of the child at position `index` in
+    /// [`Children`](Supervisor::Children). Children default to [`Restart::Permanent`].
+    pub fn set_restart(&mut self, index: usize, restart: Restart) {
+        if self.restart_policies.len() <= index {
+            self.restart_policies.resize(index + 1, Restart::Permanent);
+        }
+        self.restart_policies[index] = restart;
+    }
+
+    /// Returns the restart policy of the child at position `index`.
+    pub fn restart(&self, index: usize) -> Restart {
+        self.restart_policies
+            .get(index)
+            .copied()
+            .unwrap_or(Restart::Permanent)
+    }
+
+    /// Sets how long the child at position `index` in [`Children`](Supervisor::Children) can take
+    /// to shut down before it's killed.
+    ///
+    /// The timeout applies both when the supervisor shuts down and when the child is shut down to
+    /// be restarted together with a failed sibling. By default the supervisor waits until the
+    /// child is shut down.
+    pub fn set_shutdown_timeout(&mut self, index: usize, timeout: Duration) {
+        if self.shutdown_timeouts.len() <= index {
+            self.shutdown_timeouts.resize(index + 1, None);
+        }
+        self.shutdown_timeouts[index] = Some(timeout);
+    }
+
+    /// Returns the shutdown timeout of the child at position `index`, if it has one.
+    pub fn shutdown_timeout(&self, index: usize) -> Option<Duration> {
+        self.shutdown_timeouts.get(index).copied().flatten()
+    }
+
+    /// Forwards the [`CrashReport`]s of all children to `crash_log`.
+    pub fn set_crash_log(&mut self, crash_log: Process<CrashReport>) {
+        self.crash_log = Some(crash_log);
+    }
+
     pub fn children_args(&mut self, args: <<T as Supervisor>::Children as Supervisable<T>>::Args) {
         T::Children::start_links(self, args)
     }
@@ -122,6 +280,142 @@ where
         self.children.as_ref().unwrap().clone()
     }
 
+    /// Shuts down the child at position `index`, killing it if it takes longer than its shutdown
+    /// timeout.
+    fn shutdown_child<C>(&mut self, index: usize, child: &ProcessRef<C>, tag: Tag)
+    where
+        C: AbstractProcess,
+    {
+        match self.shutdown_timeout(index) {
+            Some(timeout) => {
+                if !child.shutdown_or_kill(timeout) {
+                    self.killed.push(tag);
+                }
+            }
+            None => child.shutdown(),
+        }
+    }
+
+    /// Returns `true` if the link failure with `tag` comes from a child killed during a shutdown.
+    fn take_killed(&mut self, tag: Tag) -> bool {
+        match self.killed.iter().position(|killed| *killed == tag) {
+            Some(position) => {
+                self.killed.swap_remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_terminated(&self, index: usize) -> bool {
+        self.terminated.contains(&index)
+    }
+
+    fn set_terminated(&mut self, index: usize) {
+        if !self.is_terminated(index) {
+            self.terminated.push(index);
+        }
+    }
+
+    /// Records that the child at position `index` was started as `process`.
+    fn child_started(&mut self, index: usize, process: Process<()>) {
+        let event = match self.children_stats.get_mut(index) {
+            Some(stats) => {
+                stats.restarts += 1;
+                stats.started = Instant::now();
+                stats.reported = false;
+                SupervisorEvent::ChildRestarted {
+                    index,
+                    process,
+                    restarts: stats.restarts,
+                }
+            }
+            None => {
+                self.children_stats.push(ChildStats {
+                    restarts: 0,
+                    started: Instant::now(),
+                    last_crash: None,
+                    reported: false,
+                });
+                SupervisorEvent::ChildStarted { index, process }
+            }
+        };
+        self.notify(event);
+    }
+
+    /// Notifies the observers that the child linked with `tag` failed.
+    fn child_crashed(&mut self, tag: Tag) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let index = match T::Children::child_index(self, tag) {
+            Some(index) => index,
+            None => return,
+        };
+        let process = T::Children::which_children(self).swap_remove(index).process;
+        let stats = &self.children_stats[index];
+        // The report is sent right before the child dies, so it's already received.
+        let report = if stats.reported {
+            stats.last_crash.clone()
+        } else {
+            None
+        };
+        self.notify(SupervisorEvent::ChildCrashed {
+            index,
+            process,
+            report,
+        });
+    }
+
+    fn notify(&self, event: SupervisorEvent) {
+        for observer in self.observers.iter() {
+            observer.send(event.clone());
+        }
+    }
+
+    fn child_info<C>(&self, index: usize, child: &ProcessRef<C>, name: Option<&str>) -> ChildInfo {
+        let stats = &self.children_stats[index];
+        ChildInfo {
+            name: name.map(|name| name.to_owned()),
+            process: untyped(child),
+            restart: self.restart(index),
+            restarts: stats.restarts,
+            uptime: stats.started.elapsed(),
+            running: !self.is_terminated(index),
+            last_crash: stats.last_crash.clone(),
+        }
+    }
+
+    /// Records a restart and fails the supervisor if the restart intensity was exceeded.
+    fn register_restart(&mut self) {
+        let max_restarts = match self.max_restarts {
+            Some(max_restarts) => max_restarts,
+            None => return,
+        };
+        let now = Instant::now();
+        let window = Duration::from_secs(self.max_seconds);
+        while let Some(restart) = self.restarts.front() {
+            if now.duration_since(*restart) > window {
+                self.restarts.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.restarts.push_back(now);
+        if self.restarts.len() > max_restarts {
+            self.notify(SupervisorEvent::LimitReached {
+                max_restarts,
+                max_seconds: self.max_seconds,
+            });
+            panic!(
+                "Supervisor {} reached maximum restart intensity ({} restarts in {}s)",
+                std::any::type_name::<T>(),
+                max_restarts,
+                self.max_seconds
+            );
+        }
+    }
+
     fn terminate(self) {
         self.terminate_subscribers
             .iter()
@@ -144,8 +438,18 @@ where
             children: None,
             children_args: None,
             children_tags: None,
+            restart_policies: Vec::new(),
+            shutdown_timeouts: Vec::new(),
+            killed: Vec::new(),
+            terminated: Vec::new(),
+            children_stats: Vec::new(),
+            crash_log: None,
+            observers: Vec::new(),
             terminate_subscribers: vec![],
             strategy: SupervisorStrategy::OneForOne,
+            max_restarts: None,
+            max_seconds: 5,
+            restarts: VecDeque::new(),
         }
     }
 }
@@ -161,6 +465,227 @@ where
     fn start_links(config: &mut SupervisorConfig<T>, args: Self::Args);
     fn terminate(config: SupervisorConfig<T>);
     fn handle_failure(config: &mut SupervisorConfig<T>, tag: Tag);
+    fn restore_snapshot(config: &mut SupervisorConfig<T>, tag: Tag);
+    fn which_children(config: &SupervisorConfig<T>) -> Vec<ChildInfo>;
+    fn child_index(config: &SupervisorConfig<T>, tag: Tag) -> Option<usize>;
+    fn child_types() -> Vec<&'static str>;
+}
+
+/// Information about a child of a [`Supervisor`], returned by
+/// [`which_children`](ProcessRef::which_children).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ChildInfo {
+    /// The name the child is registered under.
+    pub name: Option<String>,
+    /// The currently running process of the child.
+    pub process: Process<()>,
+    /// The restart policy of the child.
+    pub restart: Restart,
+    /// How often the child was restarted, including restarts caused by the failure of siblings.
+    pub restarts: usize,
+    /// The time since the child was last (re)started.
+    pub uptime: Duration,
+    /// `false` if the child is [`Temporary`](Restart::Temporary) and terminated.
+    pub running: bool,
+    /// The report of the last panic of the child.
+    pub last_crash: Option<CrashReport>,
+}
+
+/// Report of a panic inside of a supervised child.
+///
+/// Supervised children send the report to their supervisor right before they die, in addition
+/// to printing the panic to stderr. The last report of each child is part of its [`ChildInfo`],
+/// and all reports can be forwarded to a process set with
+/// [`set_crash_log`](SupervisorConfig::set_crash_log).
+///
+/// Failures that are not panics (e.g. a trap or a kill) don't produce a report.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CrashReport {
+    /// The process that panicked.
+    pub process: Process<()>,
+    /// The [label](crate::ProcessConfig::set_label) of the process that panicked.
+    pub label: Option<String>,
+    /// The panic message, including the location of the panic.
+    pub message: String,
+    /// The backtrace of the panic.
+    ///
+    /// WebAssembly guests currently can't capture backtraces, so this is usually only a note
+    /// that backtraces are unsupported.
+    pub backtrace: String,
+}
+
+struct ChildStats {
+    restarts: usize,
+    started: Instant,
+    last_crash: Option<CrashReport>,
+    // The current process of the child sent a crash report.
+    reported: bool,
+}
+
+/// Lifecycle event of a [`Supervisor`], delivered to the processes subscribed with
+/// [`subscribe_events`](ProcessRef::subscribe_events).
+///
+/// Children are identified by their position in [`Children`](Supervisor::Children).
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum SupervisorEvent {
+    /// The child was started for the first time.
+    ChildStarted { index: usize, process: Process<()> },
+    /// The child failed. The report is only included if the child panicked.
+    ChildCrashed {
+        index: usize,
+        process: Process<()>,
+        report: Option<CrashReport>,
+    },
+    /// The child was restarted, because it or one of its siblings failed.
+    ChildRestarted {
+        index: usize,
+        process: Process<()>,
+        restarts: usize,
+    },
+    /// The supervisor reached its maximum restart intensity and is failing.
+    LimitReached {
+        max_restarts: usize,
+        max_seconds: u64,
+    },
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ChildrenInfo;
+impl<T> RequestHandler<ChildrenInfo> for T
+where
+    T: Supervisor,
+{
+    type Response = Vec<ChildInfo>;
+
+    fn handle(state: &mut Self::State, _: ChildrenInfo) -> Self::Response {
+        T::Children::which_children(state)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SubscribeEvents(Process<SupervisorEvent>);
+impl<T> RequestHandler<SubscribeEvents> for T
+where
+    T: Supervisor,
+{
+    type Response = ();
+
+    fn handle(state: &mut Self::State, SubscribeEvents(observer): SubscribeEvents) {
+        if !state.observers.contains(&observer) {
+            state.observers.push(observer);
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct UnsubscribeEvents(Process<SupervisorEvent>);
+impl<T> RequestHandler<UnsubscribeEvents> for T
+where
+    T: Supervisor,
+{
+    type Response = ();
+
+    fn handle(state: &mut Self::State, UnsubscribeEvents(observer): UnsubscribeEvents) {
+        state.observers.retain(|other| *other != observer);
+    }
+}
+
+impl<T> ProcessRef<T>
+where
+    T: Supervisor,
+{
+    /// Sends all future [`SupervisorEvent`]s of the supervisor to `observer`.
+    ///
+    /// Subscribing the same process multiple times has no effect.
+    pub fn subscribe_events(&self, observer: &Process<SupervisorEvent>) {
+        self.request(SubscribeEvents(observer.clone()));
+    }
+
+    /// Stops sending [`SupervisorEvent`]s to `observer`.
+    pub fn unsubscribe_events(&self, observer: &Process<SupervisorEvent>) {
+        self.request(UnsubscribeEvents(observer.clone()));
+    }
+
+    /// Returns the number of running children.
+    pub fn count_children(&self) -> usize {
+        self.which_children()
+            .iter()
+            .filter(|child| child.running)
+            .count()
+    }
+
+    /// Returns information about all children, in start order.
+    pub fn which_children(&self) -> Vec<ChildInfo> {
+        self.request(ChildrenInfo)
+    }
+
+    /// Returns the current process of the child named `name`.
+    ///
+    /// Children are named by the arguments passed to
+    /// [`children_args`](SupervisorConfig::children_args). A restarted child keeps its name, so
+    /// siblings can look up a dependency each time they use it, instead of holding a reference to
+    /// a process that was replaced. Returns `None` if no running child of type `C` has the name.
+    pub fn get_child<C>(&self, name: &str) -> Option<ProcessRef<C>>
+    where
+        C: AbstractProcess,
+    {
+        let types = T::Children::child_types();
+        self.which_children()
+            .into_iter()
+            .enumerate()
+            .find(|(index, child)| {
+                child.running
+                    && child.name.as_deref() == Some(name)
+                    && types[*index] == std::any::type_name::<C>()
+            })
+            .map(|(_, child)| unsafe {
+                ProcessRef::new(child.process.node_id(), child.process.id())
+            })
+    }
+}
+
+/// Handles snapshots sent by the children with [`AbstractProcess::snapshot`].
+///
+/// The snapshot replaces the argument that the child is restarted with.
+fn restore_snapshot<K>(config: &mut SupervisorConfig<K>)
+where
+    K: Supervisor,
+{
+    let tag: Tag = Bincode::decode().unwrap();
+    K::Children::restore_snapshot(config, tag);
+}
+
+/// Handles crash reports sent by panicking children.
+fn receive_crash_report<K>(config: &mut SupervisorConfig<K>)
+where
+    K: Supervisor,
+{
+    let tag: Tag = Bincode::decode().unwrap();
+    let report: CrashReport = Bincode::decode().unwrap();
+    if let Some(crash_log) = config.crash_log.as_ref() {
+        crash_log.send(report.clone());
+    }
+    // Reports of already restarted children are ignored.
+    if let Some(index) = K::Children::child_index(config, tag) {
+        config.children_stats[index].last_crash = Some(report);
+        config.children_stats[index].reported = true;
+    }
+}
+
+fn untyped<C>(child: &ProcessRef<C>) -> Process<()> {
+    Process::new(child.node_id(), child.id())
+}
+
+/// Marks `child` as supervised by the current process, a supervisor of type `K`.
+fn supervise_child<K, C>(child: &ProcessRef<C>)
+where
+    K: Supervisor,
+{
+    supervise(
+        child,
+        restore_snapshot::<K> as *const () as usize as i32,
+        receive_crash_report::<K> as *const () as usize as i32,
+    );
 }
 
 impl<T1, K> Supervisable<K> for T1
@@ -176,34 +701,50 @@ where
     fn start_links(config: &mut SupervisorConfig<K>, args: Self::Args) {
         config.children_args = Some(args.clone());
         let (proc, tag) = match T1::start_link_or_fail(args.0, args.1.as_deref()) {
-            Ok(result) => result,
+            Ok((proc, tag)) => {
+                supervise_child::<K, _>(&proc);
+                (proc, tag)
+            }
             Err(_) => panic!(
                 "Supervisor failed to start child `{}`",
                 std::any::type_name::<T1>()
             ),
         };
+        config.child_started(0, untyped(&proc));
         config.children = Some(proc);
         config.children_tags = Some(tag);
     }
 
-    fn terminate(config: SupervisorConfig<K>) {
-        config.children.unwrap().shutdown();
+    fn terminate(mut config: SupervisorConfig<K>) {
+        if !config.is_terminated(0) {
+            let child = config.children.clone().unwrap();
+            let tag = config.children_tags.unwrap();
+            config.shutdown_child(0, &child, tag);
+        }
     }
 
     fn handle_failure(config: &mut SupervisorConfig<K>, tag: Tag) {
         // Since there is only one children process, the behavior is the same for all
         // strategies -- after a failure, restart the child process
         if tag == config.children_tags.unwrap() {
+            if config.restart(0) == Restart::Temporary {
+                config.set_terminated(0);
+                return;
+            }
             let (proc, tag) = match T1::start_link_or_fail(
                 config.children_args.as_ref().unwrap().0.clone(),
                 config.children_args.as_ref().unwrap().1.as_deref(),
             ) {
-                Ok(result) => result,
+                Ok((proc, tag)) => {
+                    supervise_child::<K, _>(&proc);
+                    (proc, tag)
+                }
                 Err(_) => panic!(
                     "Supervisor failed to start child `{}`",
                     std::any::type_name::<T1>()
                 ),
             };
+            config.child_started(0, untyped(&proc));
             *config.children.as_mut().unwrap() = proc;
             *config.children_tags.as_mut().unwrap() = tag;
         } else {
@@ -213,6 +754,25 @@ where
             );
         }
     }
+    fn child_index(config: &SupervisorConfig<K>, tag: Tag) -> Option<usize> {
+        (tag == config.children_tags.unwrap()).then_some(0)
+    }
+
+    fn which_children(config: &SupervisorConfig<K>) -> Vec<ChildInfo> {
+        let args = config.children_args.as_ref().unwrap();
+        vec![config.child_info(0, config.children.as_ref().unwrap(), args.1.as_deref())]
+    }
+
+    fn child_types() -> Vec<&'static str> {
+        vec![std::any::type_name::<T1>()]
+    }
+
+    fn restore_snapshot(config: &mut SupervisorConfig<K>, tag: Tag) {
+        // Snapshots of already restarted children are ignored.
+        if tag == config.children_tags.unwrap() {
+            config.children_args.as_mut().unwrap().0 = Bincode::decode().unwrap();
+        }
+    }
 }
 
 // Auto-implement Supervisable for up to 12 children.
@@ -237,19 +797,32 @@ mod macros {
         };
     }
 
+    // Shuts down the child at position `i`, killing it after its shutdown timeout
+    macro_rules! shutdown_child {
+        ($config:ident, $i:tt) => {
+            let child = $config.children.as_ref().unwrap().$i.clone();
+            let tag = $config.children_tags.unwrap().$i;
+            $config.shutdown_child($i, &child, tag);
+        };
+    }
+
     macro_rules! reverse_shutdown {
         // reverse_shutdown!(config, [...]) shuts down all children in reverse order
         ($config:ident, []) => {}; // base case
         ($config:ident, [$head_i:tt $($rest_i:tt)*]) => { // recursive case
             macros::reverse_shutdown!($config, [$($rest_i)*]);
-            $config.children.as_ref().unwrap().$head_i.shutdown();
+            if !$config.is_terminated($head_i) {
+                macros::shutdown_child!($config, $head_i);
+            }
         };
         // reverse_shutdown!(config, skip tag, [...]) shuts down all children with unmatched tags
         ($config:ident, skip $tag:ident, []) => {}; // base case
         ($config:ident, skip $tag:ident, [$head_i:tt $($rest_i:tt)*]) => { // recursive case
             macros::reverse_shutdown!($config, skip $tag, [$($rest_i)*]);
-            if $tag != $config.children_tags.as_ref().unwrap().$head_i {
-                $config.children.as_ref().unwrap().$head_i.shutdown();
+            if $tag != $config.children_tags.as_ref().unwrap().$head_i
+                && !$config.is_terminated($head_i)
+            {
+                macros::shutdown_child!($config, $head_i);
             }
         };
         // reverse_shutdown!(config, after tag, [...]) shuts down the children after the tag
@@ -283,7 +856,10 @@ mod macros {
                     $(
                         let (paste::paste!([<proc$i>]),paste::paste!([<tag$i>]))
                                 = match $args ::start_link_or_fail(args.$i.0, args.$i.1.as_deref()) {
-                            Ok(result) => result,
+                            Ok((proc, tag)) => {
+                                supervise_child::<K, _>(&proc);
+                                (proc, tag)
+                            }
                             Err(_) => panic!(
                                 "Supervisor failed to start child `{}`",
                                 std::any::type_name::<$args>()
@@ -291,14 +867,45 @@ mod macros {
                         };
                     )*
 
+                    $(
+                        config.child_started($i, untyped(&paste::paste!([<proc$i>])));
+                    )*
                     config.children = Some(($(paste::paste!([<proc$i>])),*));
                     config.children_tags = Some(($(paste::paste!([<tag$i>])),*));
                 }
 
-                fn terminate(config: SupervisorConfig<K>) {
+                fn terminate(mut config: SupervisorConfig<K>) {
                     macros::reverse_shutdown!(config, [ $($i)* ]);
                 }
 
+                fn child_index(config: &SupervisorConfig<K>, tag: Tag) -> Option<usize> {
+                    $(
+                        if tag == config.children_tags.unwrap().$i {
+                            return Some($i);
+                        }
+                    )*
+                    None
+                }
+
+                fn which_children(config: &SupervisorConfig<K>) -> Vec<ChildInfo> {
+                    let children = config.children.as_ref().unwrap();
+                    let args = config.children_args.as_ref().unwrap();
+                    vec![$(config.child_info($i, &children.$i, args.$i.1.as_deref())),*]
+                }
+
+                fn child_types() -> Vec<&'static str> {
+                    vec![$(std::any::type_name::<$args>()),*]
+                }
+
+                fn restore_snapshot(config: &mut SupervisorConfig<K>, tag: Tag) {
+                    // Snapshots of already restarted children are ignored.
+                    $(
+                        if tag == config.children_tags.unwrap().$i {
+                            config.children_args.as_mut().unwrap().$i.0 = Bincode::decode().unwrap();
+                        }
+                    )*
+                }
+
                 fn handle_failure(config: &mut SupervisorConfig<K>, tag: Tag) {
                     match config.strategy {
                         // After a failure, just restart the same process.
@@ -306,17 +913,25 @@ mod macros {
 
                             $(
 
-                                if tag == config.children_tags.unwrap().$i {
+                                if tag == config.children_tags.unwrap().$i
+                                    && config.restart($i) == Restart::Temporary
+                                {
+                                    config.set_terminated($i);
+                                } else if tag == config.children_tags.unwrap().$i {
                                     let (proc, tag) = match $args::start_link_or_fail(
                                         config.children_args.as_ref().unwrap().$i.0.clone(),
                                         config.children_args.as_ref().unwrap().$i.1.as_deref(),
                                     ) {
-                                        Ok(result) => result,
+                                        Ok((proc, tag)) => {
+                                            supervise_child::<K, _>(&proc);
+                                            (proc, tag)
+                                        }
                                         Err(_) => panic!(
                                             "Supervisor failed to start child `{}`",
                                             std::any::type_name::<$args>()
                                         ),
                                     };
+                                    config.child_started($i, untyped(&proc));
                                     (*config.children.as_mut().unwrap()).$i = proc;
                                     (*config.children_tags.as_mut().unwrap()).$i = tag;
                                 } else
@@ -349,18 +964,26 @@ mod macros {
                             // restart all
                             $(
 
-                                let (proc, tag) = match $args::start_link_or_fail(
-                                    config.children_args.as_ref().unwrap().$i.0.clone(),
-                                    config.children_args.as_ref().unwrap().$i.1.as_deref(),
-                                ) {
-                                    Ok(result) => result,
-                                    Err(_) => panic!(
-                                        "Supervisor failed to start child `{}`",
-                                        std::any::type_name::<$args>()
-                                    ),
-                                };
-                                (*config.children.as_mut().unwrap()).$i = proc;
-                                (*config.children_tags.as_mut().unwrap()).$i = tag;
+                                if config.restart($i) == Restart::Temporary {
+                                    config.set_terminated($i);
+                                } else {
+                                    let (proc, tag) = match $args::start_link_or_fail(
+                                        config.children_args.as_ref().unwrap().$i.0.clone(),
+                                        config.children_args.as_ref().unwrap().$i.1.as_deref(),
+                                    ) {
+                                        Ok((proc, tag)) => {
+                                            supervise_child::<K, _>(&proc);
+                                            (proc, tag)
+                                        }
+                                        Err(_) => panic!(
+                                            "Supervisor failed to start child `{}`",
+                                            std::any::type_name::<$args>()
+                                        ),
+                                    };
+                                    config.child_started($i, untyped(&proc));
+                                    (*config.children.as_mut().unwrap()).$i = proc;
+                                    (*config.children_tags.as_mut().unwrap()).$i = tag;
+                                }
 
                             )*
                         }
@@ -393,18 +1016,26 @@ mod macros {
                                     if seen_tag == true || tag == config.children_tags.unwrap().$i {
                                         seen_tag = true;
 
-                                        let (proc, tag) = match $args::start_link_or_fail(
-                                            config.children_args.as_ref().unwrap().$i.0.clone(),
-                                            config.children_args.as_ref().unwrap().$i.1.as_deref(),
-                                        ) {
-                                            Ok(result) => result,
-                                            Err(_) => panic!(
-                                                "Supervisor failed to start child `{}`",
-                                                std::any::type_name::<$args>()
-                                            ),
-                                        };
-                                        (*config.children.as_mut().unwrap()).$i = proc;
-                                        (*config.children_tags.as_mut().unwrap()).$i = tag;
+                                        if config.restart($i) == Restart::Temporary {
+                                            config.set_terminated($i);
+                                        } else {
+                                            let (proc, tag) = match $args::start_link_or_fail(
+                                                config.children_args.as_ref().unwrap().$i.0.clone(),
+                                                config.children_args.as_ref().unwrap().$i.1.as_deref(),
+                                            ) {
+                                                Ok((proc, tag)) => {
+                                                    supervise_child::<K, _>(&proc);
+                                                    (proc, tag)
+                                                }
+                                                Err(_) => panic!(
+                                                    "Supervisor failed to start child `{}`",
+                                                    std::any::type_name::<$args>()
+                                                ),
+                                            };
+                                            config.child_started($i, untyped(&proc));
+                                            (*config.children.as_mut().unwrap()).$i = proc;
+                                            (*config.children_tags.as_mut().unwrap()).$i = tag;
+                                        }
                                     }
 
                                 )*
@@ -418,9 +1049,183 @@ mod macros {
 
     pub(crate) use impl_supervisable;
     pub(crate) use reverse_shutdown;
+    pub(crate) use shutdown_child;
     pub(crate) use tag;
 }
 
+/// A supervisor of a dynamic set of identical children.
+///
+/// Contrary to a [`Supervisor`], a `DynamicSupervisor` starts without any children. They are
+/// started and stopped at runtime with [`start_child`](ProcessRef::start_child) and
+/// [`terminate_child`](ProcessRef::terminate_child), which fits patterns like one worker per
+/// connection or per user. This mirrors the `simple_one_for_one` strategy of Erlang/OTP
+/// supervisors.
+///
+/// Each failed child is restarted with the argument it was originally started with. Shutting
+/// down the supervisor shuts down all children in reverse start order.
+///
+/// # Example
+///
+/// ```
+/// let sup = DynamicSupervisor::<Counter>::start_link((), None);
+/// let counter = sup.start_child(0);
+/// counter.send(Inc);
+/// assert_eq!(sup.which_children(), vec![counter.clone()]);
+/// assert!(sup.terminate_child(&counter));
+/// ```
+pub struct DynamicSupervisor<T>
+where
+    T: AbstractProcess,
+{
+    children: Vec<(ProcessRef<T>, Tag, T::Arg)>,
+}
+
+impl<T> AbstractProcess for DynamicSupervisor<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Arg = ();
+    type State = Self;
+
+    fn init(_: ProcessRef<Self>, _: ()) -> Self::State {
+        DynamicSupervisor {
+            children: Vec::new(),
+        }
+    }
+
+    // Supervisor shouldn't die if the children die
+    fn trap_links() -> bool {
+        true
+    }
+
+    fn terminate(state: Self::State) {
+        state
+            .children
+            .iter()
+            .rev()
+            .for_each(|(child, _, _)| child.shutdown());
+    }
+
+    fn handle_link_trapped(state: &mut Self::State, tag: Tag) {
+        let index = match state
+            .children
+            .iter()
+            .position(|(_, child_tag, _)| *child_tag == tag)
+        {
+            Some(index) => index,
+            None => panic!(
+                "DynamicSupervisor<{}> received kill signal from a died link",
+                std::any::type_name::<T>()
+            ),
+        };
+        let arg = state.children[index].2.clone();
+        let (child, tag) = start_dynamic_child::<T>(arg.clone());
+        state.children[index] = (child, tag, arg);
+    }
+}
+
+fn start_dynamic_child<T>(arg: T::Arg) -> (ProcessRef<T>, Tag)
+where
+    T: AbstractProcess,
+{
+    match T::start_link_or_fail(arg, None) {
+        Ok(result) => result,
+        Err(_) => panic!(
+            "DynamicSupervisor failed to start child `{}`",
+            std::any::type_name::<T>()
+        ),
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StartChild<A>(A);
+impl<T> RequestHandler<StartChild<T::Arg>> for DynamicSupervisor<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Response = ProcessRef<T>;
+
+    fn handle(state: &mut Self::State, StartChild(arg): StartChild<T::Arg>) -> Self::Response {
+        let (child, tag) = start_dynamic_child::<T>(arg.clone());
+        state.children.push((child.clone(), tag, arg));
+        child
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(bound = "")]
+struct TerminateChild<T>(ProcessRef<T>);
+impl<T> RequestHandler<TerminateChild<T>> for DynamicSupervisor<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Response = bool;
+
+    fn handle(state: &mut Self::State, TerminateChild(child): TerminateChild<T>) -> bool {
+        match state
+            .children
+            .iter()
+            .position(|(other, _, _)| *other == child)
+        {
+            Some(index) => {
+                let (child, _, _) = state.children.remove(index);
+                child.shutdown();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct WhichChildren;
+impl<T> RequestHandler<WhichChildren> for DynamicSupervisor<T>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    type Response = Vec<ProcessRef<T>>;
+
+    fn handle(state: &mut Self::State, _: WhichChildren) -> Self::Response {
+        state
+            .children
+            .iter()
+            .map(|(child, _, _)| child.clone())
+            .collect()
+    }
+}
+
+impl<T> ProcessRef<DynamicSupervisor<T>>
+where
+    T: AbstractProcess,
+    T::Arg: Clone,
+{
+    /// Starts a new child with `arg` and returns a reference to it.
+    pub fn start_child(&self, arg: T::Arg) -> ProcessRef<T> {
+        self.request(StartChild(arg))
+    }
+
+    /// Shuts down the `child` and stops supervising it.
+    ///
+    /// Returns `false` if `child` is not supervised by this supervisor.
+    pub fn terminate_child(&self, child: &ProcessRef<T>) -> bool {
+        self.request(TerminateChild(child.clone()))
+    }
+
+    /// Returns all currently running children, in start order.
+    pub fn which_children(&self) -> Vec<ProcessRef<T>> {
+        self.request(WhichChildren)
+    }
+
+    /// Returns the number of running children.
+    pub fn count_children(&self) -> usize {
+        self.which_children().len()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+
 /// A `i64` value used as a message tag.
 ///
 /// Processes can selectively receive messages based on the message's tag. This mechanism can be
@@ -5,7 +7,8 @@
 ///
 /// Creating a new tag will return a process-unique value. Some tag values are reserved for
 /// internal use only, but the range from 64 to 128 can be used by the developer to assign
-/// application specific meaning.
+/// application specific meaning. Libraries that need stable tags, that are the same in all
+/// processes, should use [`Tag::namespaced`] instead to avoid collisions with the application.
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone, Copy, Hash)]
 pub struct Tag(i64);
 
@@ -20,10 +23,7 @@ impl Tag {
     /// Two calls to `Tag::new()` are guaranteed to return a unique tag only if they occurred
     /// inside the same process.
     pub fn new() -> Tag {
-        unsafe {
-            COUNTER += 1;
-            Tag(COUNTER)
-        }
+        Tag(COUNTER.fetch_add(1, Ordering::Relaxed) + 1)
     }
 
     /// Returns a special tag that is used by [`Process::send`](crate::Process) and awaited on by
@@ -34,6 +34,12 @@ impl Tag {
         Tag(1)
     }
 
+    /// Returns the tag used for high priority messages, sent with
+    /// [`Process::send_priority`](crate::Process::send_priority).
+    pub(crate) fn high_priority() -> Tag {
+        Tag(2)
+    }
+
     /// Create a special purpose tag.
     ///
     /// The `id` must be in the range between 64 and 128 or the function will return `None`.
@@ -45,15 +51,27 @@ impl Tag {
         }
     }
 
+    /// Create a stable tag inside of a `namespace`, e.g. the name of a library.
+    ///
+    /// The same `namespace` and `id` always result in the same tag, in all processes. Namespaced
+    /// tags never collide with tags returned by [`Tag::new`] or [`Tag::special`], and tags of
+    /// different namespaces are only equal if the hashes of their namespaces collide.
+    pub fn namespaced(namespace: &str, id: u32) -> Tag {
+        // 32-bit FNV-1a, stable across processes and compilations.
+        let hash = namespace.bytes().fold(0x811c9dc5_u32, |hash, byte| {
+            (hash ^ byte as u32).wrapping_mul(0x01000193)
+        });
+        // Other tags are positive, so the sign bit is always set.
+        Tag(i64::MIN | ((hash as i64 & 0x7fff_ffff) << 32) | id as i64)
+    }
+
     pub fn id(&self) -> i64 {
         self.0
     }
 }
 
 // Reserve first 128 tags for special purposes.
-static mut COUNTER: i64 = 128;
-
-impl Tag {}
+static COUNTER: AtomicI64 = AtomicI64::new(128);
 
 impl Default for Tag {
     fn default() -> Self {
@@ -84,4 +102,15 @@ mod tests {
 
         assert_eq!(Tag::none(), Tag::none());
     }
+
+    #[test]
+    fn namespaced_tag() {
+        let tag = Tag::namespaced("my_library", 1);
+        assert_eq!(tag, Tag::namespaced("my_library", 1));
+        assert!(tag.id() < 0);
+
+        assert_ne!(tag, Tag::namespaced("my_library", 2));
+        assert_ne!(tag, Tag::namespaced("other_library", 1));
+        assert_ne!(Tag::namespaced("", 0), Tag::none());
+    }
 }
@@ -0,0 +1,72 @@
+//! Combinators for tasks spawned with the `spawn_link!(@task ...)` macro.
+//!
+//! All tasks run concurrently from the moment they are spawned, the functions in this module only
+//! decide how the caller waits for their results.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{spawn_link, task};
+//!
+//! let tasks = (1..=3).map(|n| spawn_link!(@task |n| n * n)).collect();
+//! assert_eq!(task::join_all(tasks), vec![1, 4, 9]);
+//!
+//! let fast = spawn_link!(@task || "fast");
+//! let slow = spawn_link!(@task || {
+//!     lunatic::sleep(std::time::Duration::from_secs(1));
+//!     "slow"
+//! });
+//! assert_eq!(task::race(vec![fast, slow]), "fast");
+//! ```
+
+use crate::{
+    host,
+    protocol::{Protocol, Recv, TaskEnd},
+    serializer::{Bincode, Serializer},
+    Mailbox, Tag,
+};
+
+/// A task returning a value of type `T`, spawned with the `spawn_link!(@task ...)` macro.
+pub type Task<T, S = Bincode> = Protocol<Recv<T, TaskEnd>, S>;
+
+/// Waits for all `tasks` to finish and returns their results in the same order.
+///
+/// # Panics
+///
+/// This function will panic if one of the results can't be deserialized into `T` with serializer
+/// `S`.
+pub fn join_all<T, S>(tasks: Vec<Task<T, S>>) -> Vec<T>
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    tasks.into_iter().map(|task| task.result()).collect()
+}
+
+/// Waits for the first of `tasks` to finish and returns its result.
+///
+/// All other tasks are [canceled](Protocol::cancel).
+///
+/// # Panics
+///
+/// This function will panic if `tasks` is empty or the result can't be deserialized into `T`
+/// with serializer `S`.
+pub fn race<T, S>(tasks: Vec<Task<T, S>>) -> T
+where
+    T: 'static,
+    S: Serializer<T>,
+{
+    assert!(!tasks.is_empty(), "`task::race` needs at least one task");
+    let tags: Vec<i64> = tasks.iter().map(|task| task.tag().id()).collect();
+    let mailbox: Mailbox<T, S> = unsafe { Mailbox::new() };
+    let result = mailbox.receive_host(Some(&tags), None).unwrap();
+    let winner = Tag::from(unsafe { host::api::message::get_tag() });
+    for task in tasks {
+        if task.tag() == winner {
+            task.finish();
+        } else {
+            task.cancel();
+        }
+    }
+    result
+}
@@ -0,0 +1,151 @@
+//! Clocks of the host and deadlines for timeouts.
+//!
+//! [`now`] reads the wall clock and [`instant`] the monotonic clock of the runtime. Both go
+//! directly to the host, so they behave the same on every runtime version, no matter how the
+//! standard library maps `std::time` to WASI. Monotonic [`Instant`]s are only comparable on the
+//! same node.
+//!
+//! Operations that wait for multiple messages with a single timeout can track it with a
+//! [`Deadline`].
+//!
+//! # Example
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use lunatic::{time::Deadline, Mailbox};
+//!
+//! let mailbox: Mailbox<u64> = unsafe { Mailbox::new() };
+//! let deadline = Deadline::after(Duration::from_secs(1));
+//! let mut messages = Vec::new();
+//! while let Ok(message) = mailbox.receive_timeout(deadline.remaining()) {
+//!     messages.push(message);
+//! }
+//! ```
+
+use std::ops::{Add, AddAssign, Sub};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::host::api::clock;
+
+fn clock_time_get(clock_id: u32) -> u64 {
+    let mut time = 0;
+    let result = unsafe { clock::clock_time_get(clock_id, 1, &mut time) };
+    assert_eq!(result, 0, "the host failed to read the clock");
+    time
+}
+
+/// Returns the current time of the wall clock.
+///
+/// The wall clock can jump, e.g. when the time of the machine is adjusted. Use [`instant`] to
+/// measure durations.
+pub fn now() -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_nanos(clock_time_get(clock::REALTIME))
+}
+
+/// Returns the current time of the monotonic clock.
+pub fn instant() -> Instant {
+    Instant::now()
+}
+
+/// A point in time of the monotonic clock, that never goes backwards.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    nanos: u64,
+}
+
+impl Instant {
+    /// Returns the current time of the monotonic clock.
+    pub fn now() -> Self {
+        Instant {
+            nanos: clock_time_get(clock::MONOTONIC),
+        }
+    }
+
+    /// Returns the time that passed since `earlier`, or zero if `earlier` is later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_nanos(self.nanos.saturating_sub(earlier.nanos))
+    }
+
+    /// Returns the time that passed since this instant.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Returns the instant `duration` after this one, or `None` if it can't be represented.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        let nanos = u64::try_from(duration.as_nanos()).ok()?;
+        Some(Instant {
+            nanos: self.nanos.checked_add(nanos)?,
+        })
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    /// # Panics
+    ///
+    /// This function will panic if the resulting instant can't be represented.
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+/// A point in time until which an operation can wait.
+///
+/// Timeout APIs take a [`Duration`], so operations that consist of multiple waits need to pass
+/// on what's left of the original timeout. A `Deadline` tracks this, its
+/// [`remaining`](Self::remaining) time can be passed directly to the timeout APIs.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    /// Creates a deadline `timeout` from now.
+    ///
+    /// Timeouts that are too big to be represented never expire.
+    pub fn after(timeout: Duration) -> Self {
+        let now = Instant::now();
+        Deadline(
+            now.checked_add(timeout)
+                .unwrap_or(Instant { nanos: u64::MAX }),
+        )
+    }
+
+    /// Creates a deadline at `instant`.
+    pub fn at(instant: Instant) -> Self {
+        Deadline(instant)
+    }
+
+    /// Returns the instant of the deadline.
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    /// Returns the time left until the deadline, or zero if it passed.
+    pub fn remaining(&self) -> Duration {
+        self.0.duration_since(Instant::now())
+    }
+
+    /// Returns `true` if the deadline passed.
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.0
+    }
+}
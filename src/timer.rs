@@ -1,4 +1,6 @@
-use crate::host;
+use std::time::Duration;
+
+use crate::{host, serializer::Bincode, Mailbox, Process, ReceiveError, Tag};
 
 /// A reference to a timer created from send_after.
 #[derive(Clone, Copy)]
@@ -14,3 +16,77 @@ impl TimerRef {
         unsafe { host::api::timer::cancel_timer(self.0) == 1 }
     }
 }
+
+/// A reference to a periodic timer created from send_interval.
+///
+/// The message is sent from a helper process that keeps running until the interval is canceled,
+/// the receiving process fails or the process that started the interval fails. Dropping the
+/// reference doesn't stop the interval, and processes that finish normally aren't noticed, so
+/// intervals should be canceled once they aren't needed anymore.
+#[derive(Debug)]
+#[must_use = "the interval can only be canceled through the reference"]
+pub struct IntervalRef {
+    ticker: Process<(), Bincode>,
+}
+
+impl IntervalRef {
+    /// Starts sending the message written by `encode` to the process every `period`.
+    ///
+    /// The message is copied byte for byte and can't contain resources (e.g. a TCP stream). It
+    /// only starts with the trace context of the caller if `traced` is set.
+    pub(crate) fn start<F>(
+        node_id: u64,
+        process_id: u64,
+        period: Duration,
+        traced: bool,
+        encode: F,
+    ) -> Self
+    where
+        F: FnOnce(),
+    {
+        let tag = Tag::new();
+        // The ticker is linked, so that it's killed if the caller fails.
+        let ticker = Process::spawn_link((node_id, process_id, tag, period), ticker);
+        // Hand the message over to the ticker.
+        unsafe { host::api::message::create_data(tag.id(), 0) };
+        if traced {
+            crate::trace::write_header();
+        }
+        encode();
+        host::send(ticker.node_id(), ticker.id());
+        IntervalRef { ticker }
+    }
+
+    /// Cancel the interval, no more messages are going to be sent.
+    ///
+    /// A message that is already on the way can still arrive after this call.
+    pub fn cancel(self) {
+        self.ticker.send(());
+    }
+}
+
+/// Entry point of the helper process that periodically sends the message.
+fn ticker((node_id, process_id, tag, period): (u64, u64, Tag, Duration), mailbox: Mailbox<()>) {
+    // Take the message out of the mailbox without decoding it, including the trace context.
+    let tags = [tag.id()];
+    host::receive(&tags, u64::MAX);
+    let size = unsafe { host::api::message::data_size() } as usize;
+    let mut data = vec![0; size];
+    unsafe { host::api::message::seek_data(0) };
+    let read = unsafe { host::api::message::read_data(data.as_mut_ptr(), size) };
+    data.truncate(read);
+
+    // Stop once the target fails.
+    let target = Process::<()>::new(node_id, process_id).monitor();
+    // Any untagged message cancels the interval.
+    while let Err(ReceiveError::Timeout) = mailbox.receive_timeout(period) {
+        if target.wait_timeout(Duration::ZERO).is_ok() {
+            return;
+        }
+        unsafe {
+            host::api::message::create_data(Tag::none().id(), data.len() as u64);
+            host::api::message::write_data(data.as_ptr(), data.len());
+        }
+        host::send(node_id, process_id);
+    }
+}
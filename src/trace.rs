@@ -0,0 +1,232 @@
+//! Tracing of requests across processes.
+//!
+//! A trace follows a request through all the processes that take part in handling it. The
+//! [`TraceContext`] of a process identifies the trace and the current span, a named unit of work
+//! inside of the trace. Tracing is opt-in: a process doesn't have a context until it opens a
+//! [`span`] or receives a message from a process that has one.
+//!
+//! While a process has a context, it's attached to every message the process sends and restored
+//! in the receiving process. Each received message that carries a context replaces the context of
+//! the receiver. Spans opened by the receiver become children of the sender's span. Messages
+//! without a context, e.g. from untraced processes, keep the context of the receiver. Messages
+//! that were put aside by a selective receive, like
+//! [`receive_where`](crate::Mailbox::receive_where), keep their context until they are taken.
+//!
+//! An [`AbstractProcess`](crate::process::AbstractProcess) instead handles each message in the
+//! context it carries, and messages without one without a context. Its own context is restored
+//! once the message is handled, so a traced request doesn't leak into the handling of later ones.
+//!
+//! Messages encoded with a serializer that defines the exact layout of the message, like
+//! [`Bytes`](crate::serializer::Bytes), [`Tagged`](crate::serializer::Tagged) and
+//! [`Raw`](crate::serializer::Raw), don't carry a context, so that other guests can read them.
+//! See [`Serializer::TRACE_CONTEXT`](crate::serializer::Serializer::TRACE_CONTEXT).
+//!
+//! Finished spans are sent as [`SpanRecord`]s to the exporter process of the node, which can
+//! forward them to a tracing backend. Without an [exporter](set_exporter), spans are only used to
+//! propagate the context.
+//!
+//! # Example
+//!
+//! ```
+//! use lunatic::{trace, Mailbox, Process};
+//!
+//! let exporter = Process::spawn((), |_, mailbox: Mailbox<trace::SpanRecord>| loop {
+//!     let span = mailbox.receive();
+//!     println!("{} took {:?}", span.name, span.duration);
+//! });
+//! trace::set_exporter(&exporter);
+//!
+//! let worker = Process::spawn((), |_, mailbox: Mailbox<u64>| {
+//!     let value = mailbox.receive();
+//!     // Child of the "request" span of the sender.
+//!     let _span = trace::span("work");
+//!     println!("working on {}", value);
+//! });
+//!
+//! let span = trace::span("request");
+//! worker.send(42);
+//! drop(span);
+//! ```
+
+use std::cell::Cell;
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{host::api::message, rand, registry, Process};
+
+/// Marks messages that start with a trace context.
+const MAGIC: [u8; 8] = *b"\xfflnt-tc\x01";
+/// Length of the magic, trace id and span id at the start of traced messages.
+const HEADER_LEN: usize = 24;
+/// Name of the exporter in the registry.
+const EXPORTER: &str = "lunatic::trace::exporter";
+
+thread_local! {
+    static CURRENT: Cell<Option<TraceContext>> = const { Cell::new(None) };
+    // Position of the payload in the last received message.
+    static PAYLOAD_START: Cell<u64> = const { Cell::new(0) };
+    // Context carried by the last received message.
+    static RECEIVED: Cell<Option<TraceContext>> = const { Cell::new(None) };
+    // Context of the process before the last received message replaced it.
+    static PREVIOUS: Cell<Option<TraceContext>> = const { Cell::new(None) };
+}
+
+/// Identifies a trace and a span inside of it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    /// Id shared by all spans of a trace.
+    pub trace_id: u64,
+    /// Id of the span.
+    pub span_id: u64,
+}
+
+/// A finished span, sent to the [exporter](set_exporter).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SpanRecord {
+    pub trace_id: u64,
+    pub span_id: u64,
+    /// Id of the enclosing span, possibly in another process. `None` for the root of the trace.
+    pub parent_span_id: Option<u64>,
+    pub name: String,
+    /// The process the span was opened in.
+    pub process: Process<()>,
+    pub start: SystemTime,
+    pub duration: Duration,
+}
+
+/// Returns the trace context of the current process.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with(|current| current.get())
+}
+
+/// Replaces the trace context of the current process.
+///
+/// This can be used to continue a trace that was started outside of lunatic, e.g. from the
+/// headers of an HTTP request. Setting `None` stops attaching a context to sent messages.
+pub fn set_current(context: Option<TraceContext>) {
+    CURRENT.with(|current| current.set(context));
+}
+
+/// Opens a span named `name`, which lasts until the returned guard is dropped.
+///
+/// The span is a child of the current span, or the root of a new trace if the process doesn't
+/// have a context.
+pub fn span(name: &str) -> Span {
+    let parent = current();
+    let context = TraceContext {
+        trace_id: parent.map_or_else(rand::random_u64, |parent| parent.trace_id),
+        span_id: rand::random_u64(),
+    };
+    set_current(Some(context));
+    Span {
+        name: name.to_owned(),
+        context,
+        parent,
+        start: SystemTime::now(),
+        started: Instant::now(),
+    }
+}
+
+/// Registers `exporter` as the process that receives the finished spans of all processes on
+/// this node.
+pub fn set_exporter(exporter: &Process<SpanRecord>) {
+    registry::register(EXPORTER, exporter);
+}
+
+/// An open span, created with [`span`].
+///
+/// Dropping the span restores the context the process had when it was opened and sends the span
+/// to the [exporter](set_exporter).
+#[derive(Debug)]
+#[must_use = "the span is closed when it's dropped"]
+pub struct Span {
+    name: String,
+    context: TraceContext,
+    parent: Option<TraceContext>,
+    start: SystemTime,
+    started: Instant,
+}
+
+impl Span {
+    /// Returns the context of the span.
+    pub fn context(&self) -> TraceContext {
+        self.context
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        set_current(self.parent);
+        if let Some(exporter) = registry::lookup::<SpanRecord>(EXPORTER) {
+            exporter.send(SpanRecord {
+                trace_id: self.context.trace_id,
+                span_id: self.context.span_id,
+                parent_span_id: self.parent.map(|parent| parent.span_id),
+                name: std::mem::take(&mut self.name),
+                process: Process::this(),
+                start: self.start,
+                duration: self.started.elapsed(),
+            });
+        }
+    }
+}
+
+/// Writes the context of the current process to the start of a new message.
+pub(crate) fn write_header() {
+    if let Some(context) = current() {
+        let mut header = [0; HEADER_LEN];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..16].copy_from_slice(&context.trace_id.to_le_bytes());
+        header[16..].copy_from_slice(&context.span_id.to_le_bytes());
+        unsafe { message::write_data(header.as_ptr(), header.len()) };
+    }
+}
+
+/// Restores the context from the start of a received message, leaving the message positioned at
+/// the payload.
+///
+/// The context of the process is only replaced if the message carries one.
+pub(crate) fn read_header() {
+    let mut header = [0; HEADER_LEN];
+    let context = if unsafe { message::data_size() } >= HEADER_LEN as u64
+        && unsafe { message::read_data(header.as_mut_ptr(), HEADER_LEN) } == HEADER_LEN
+        && header[..8] == MAGIC
+    {
+        Some(TraceContext {
+            trace_id: u64::from_le_bytes(header[8..16].try_into().unwrap()),
+            span_id: u64::from_le_bytes(header[16..].try_into().unwrap()),
+        })
+    } else {
+        unsafe { message::seek_data(0) };
+        None
+    };
+    let start = if context.is_some() { HEADER_LEN } else { 0 };
+    PAYLOAD_START.with(|payload_start| payload_start.set(start as u64));
+    RECEIVED.with(|received| received.set(context));
+    PREVIOUS.with(|previous| previous.set(current()));
+    if context.is_some() {
+        set_current(context);
+    }
+}
+
+/// Undoes [`read_header`] for a message whose serializer doesn't use trace contexts, so that a
+/// payload starting like a header is read in full.
+pub(crate) fn unread_header() {
+    if payload_start() != 0 {
+        unsafe { message::seek_data(0) };
+        PAYLOAD_START.with(|payload_start| payload_start.set(0));
+        RECEIVED.with(|received| received.set(None));
+        set_current(PREVIOUS.with(|previous| previous.get()));
+    }
+}
+
+/// Returns the context carried by the last received message.
+pub(crate) fn received() -> Option<TraceContext> {
+    RECEIVED.with(|received| received.get())
+}
+
+/// Returns the position of the payload in the last received message, after the context.
+pub(crate) fn payload_start() -> u64 {
+    PAYLOAD_START.with(|payload_start| payload_start.get())
+}
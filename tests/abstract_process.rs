@@ -3,10 +3,12 @@ use std::time::Duration;
 use lunatic::{
     host,
     process::{
-        AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler,
-        SelfReference, StartProcess,
+        AbstractProcess, DeferredRequest, DeferredRequestHandler, Message, MessageHandler,
+        OverloadPolicy, ProcessRef, ReplyHandle, Request, RequestHandler, SelfReference,
+        StartProcess,
     },
-    sleep, spawn_link, test, Tag,
+    registry::NameTaken,
+    sleep, spawn_link, test, Mailbox, Priority, Process, ReceiveError, Tag,
 };
 
 #[test]
@@ -69,6 +71,58 @@ fn handle_link_trapped() {
     assert!(a.request(IsLinkTrapped));
 }
 
+#[test]
+fn trap_links() {
+    struct A {
+        link_trapped: Option<Tag>,
+    }
+
+    impl AbstractProcess for A {
+        type Arg = ();
+        type State = A;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Self {
+            Self { link_trapped: None }
+        }
+
+        fn trap_links() -> bool {
+            true
+        }
+
+        fn handle_link_trapped(state: &mut Self::State, tag: Tag) {
+            state.link_trapped = Some(tag);
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct SpawnFailing;
+    impl RequestHandler<SpawnFailing> for A {
+        type Response = Tag;
+
+        fn handle(_: &mut Self::State, _: SpawnFailing) -> Tag {
+            let tag = Tag::new();
+            Process::spawn_link_tag((), tag, |_, _: Mailbox<()>| panic!());
+            tag
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct LinkTrapped;
+    impl RequestHandler<LinkTrapped> for A {
+        type Response = Option<Tag>;
+
+        fn handle(state: &mut Self::State, _: LinkTrapped) -> Option<Tag> {
+            state.link_trapped
+        }
+    }
+
+    let a = A::start((), None);
+    let tag = a.request(SpawnFailing);
+    sleep(Duration::from_millis(10));
+    // The server survived the failure and received the tag of the link.
+    assert_eq!(a.request(LinkTrapped), Some(tag));
+}
+
 #[test]
 fn handle_message() {
     struct A;
@@ -371,6 +425,80 @@ fn request_timeout() {
     assert!(response.is_err());
 }
 
+#[test]
+fn request_async() {
+    struct A;
+
+    impl AbstractProcess for A {
+        type Arg = ();
+        type State = A;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> A {
+            A
+        }
+    }
+
+    impl RequestHandler<u64> for A {
+        type Response = u64;
+
+        fn handle(_state: &mut Self::State, request: u64) -> u64 {
+            request * 2
+        }
+    }
+
+    let a = A::start_link((), None);
+    let pending: Vec<_> = (0..5).map(|i| a.request_async(i)).collect();
+    assert_ne!(pending[0].tag(), pending[1].tag());
+    // Collect the responses in reverse order.
+    let responses: Vec<u64> = pending
+        .into_iter()
+        .rev()
+        .map(|reply| reply.wait())
+        .collect();
+    assert_eq!(responses, vec![8, 6, 4, 2, 0]);
+
+    let pending = a.request_async(21);
+    assert_eq!(pending.wait_timeout(Duration::from_secs(1)).unwrap(), 42);
+}
+
+#[test]
+fn deferred_request() {
+    struct Barrier {
+        waiting: Vec<ReplyHandle<usize>>,
+    }
+
+    impl AbstractProcess for Barrier {
+        type Arg = ();
+        type State = Barrier;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Barrier {
+            Barrier { waiting: vec![] }
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Wait;
+    impl DeferredRequestHandler<Wait> for Barrier {
+        type Response = usize;
+
+        fn handle(state: &mut Self::State, _: Wait, reply: ReplyHandle<usize>) {
+            state.waiting.push(reply);
+            // Release everyone once the second process arrives.
+            if state.waiting.len() == 2 {
+                for reply in state.waiting.drain(..) {
+                    reply.reply(2);
+                }
+            }
+        }
+    }
+
+    let barrier = Barrier::start_link((), None);
+    let a = spawn_link!(@task |barrier = { barrier.clone() }| barrier.deferred_request(Wait));
+    let response = barrier.deferred_request_timeout(Wait, Duration::from_millis(100));
+    assert_eq!(response.unwrap(), 2);
+    assert_eq!(a.result(), 2);
+}
+
 #[test]
 fn shutdown_timeout() {
     struct A;
@@ -393,3 +521,279 @@ fn shutdown_timeout() {
 
     assert!(response.is_err());
 }
+
+#[test]
+fn shutdown_or_kill() {
+    struct A;
+
+    impl AbstractProcess for A {
+        type Arg = u64;
+        type State = u64;
+
+        fn init(_: ProcessRef<Self>, terminate_ms: u64) -> u64 {
+            terminate_ms
+        }
+
+        fn terminate(terminate_ms: Self::State) {
+            sleep(Duration::from_millis(terminate_ms));
+        }
+    }
+
+    let fast = A::start_link(0, None);
+    assert!(fast.shutdown_or_kill(Duration::from_millis(25)));
+
+    // Not linked, because killing the process would also kill the test.
+    let slow = A::start(50, None);
+    let monitor = slow.monitor();
+    assert!(!slow.shutdown_or_kill(Duration::from_millis(10)));
+    assert!(monitor.wait_timeout(Duration::from_millis(25)).is_ok());
+}
+
+#[test]
+fn hibernate() {
+    struct A {
+        buffer: Vec<u8>,
+        hibernations: u32,
+    }
+
+    impl AbstractProcess for A {
+        type Arg = ();
+        type State = A;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Self {
+            Self {
+                buffer: Vec::new(),
+                hibernations: 0,
+            }
+        }
+
+        fn hibernate_after() -> Option<Duration> {
+            Some(Duration::from_millis(10))
+        }
+
+        fn hibernate(state: &mut Self::State) {
+            state.buffer = Vec::new();
+            state.hibernations += 1;
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Fill;
+    impl RequestHandler<Fill> for A {
+        type Response = ();
+
+        fn handle(state: &mut Self::State, _: Fill) {
+            state.buffer.extend_from_slice(&[0; 1024]);
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Stats;
+    impl RequestHandler<Stats> for A {
+        type Response = (usize, u32);
+
+        fn handle(state: &mut Self::State, _: Stats) -> (usize, u32) {
+            (state.buffer.capacity(), state.hibernations)
+        }
+    }
+
+    let a = A::start((), None);
+    a.request(Fill);
+    sleep(Duration::from_millis(100));
+    // Idle processes hibernate only once.
+    assert_eq!(a.request(Stats), (0, 1));
+}
+
+#[test]
+fn start_named() {
+    struct A;
+
+    impl AbstractProcess for A {
+        type Arg = ();
+        type State = A;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> A {
+            A
+        }
+    }
+
+    let a = A::start_link_named((), "start_named").unwrap();
+    assert_eq!(A::start_named((), "start_named"), Err(NameTaken));
+    assert_eq!(ProcessRef::<A>::lookup("start_named"), Some(a.clone()));
+
+    // The name can be claimed again once the process is gone.
+    a.shutdown();
+    assert!(A::start_named((), "start_named").is_ok());
+}
+
+#[test]
+fn start_named_after_failure() {
+    struct A;
+
+    impl AbstractProcess for A {
+        type Arg = ();
+        type State = A;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> A {
+            A
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Panic;
+    impl MessageHandler<Panic> for A {
+        fn handle(_state: &mut Self::State, _: Panic) {
+            panic!();
+        }
+    }
+
+    let a = A::start_named((), "start_named_after_failure").unwrap();
+    a.send(Panic);
+    sleep(Duration::from_millis(100));
+    // The name of the failed process is released.
+    assert!(A::start_named((), "start_named_after_failure").is_ok());
+
+    // Also if it was registered without claiming it.
+    let b = A::start((), Some("start_named_after_failure/replaced"));
+    b.send(Panic);
+    sleep(Duration::from_millis(100));
+    assert!(A::start_named((), "start_named_after_failure/replaced").is_ok());
+}
+
+#[test]
+fn overload_policy() {
+    struct Slow;
+
+    impl AbstractProcess for Slow {
+        type Arg = ();
+        type State = Slow;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Slow {
+            Slow
+        }
+
+        fn overload_policy() -> Option<OverloadPolicy> {
+            Some(OverloadPolicy::Reject { max_queued: 1 })
+        }
+    }
+
+    impl RequestHandler<u64> for Slow {
+        type Response = u64;
+
+        fn handle(_state: &mut Self::State, request: u64) -> u64 {
+            sleep(Duration::from_millis(50));
+            request
+        }
+    }
+
+    impl RequestHandler<[u8; 16]> for Slow {
+        type Response = [u8; 16];
+
+        fn handle(_state: &mut Self::State, request: [u8; 16]) -> [u8; 16] {
+            request
+        }
+    }
+
+    let slow = Slow::start_link((), None);
+    let pending: Vec<_> = (0..5).map(|i| slow.request_async(i)).collect();
+    let responses: Vec<_> = pending
+        .iter()
+        .map(|reply| reply.wait_timeout(Duration::from_secs(5)))
+        .collect();
+    // At most the request that is being handled and one queued request succeed.
+    let overloaded = responses
+        .iter()
+        .filter(|response| matches!(response, Err(ReceiveError::Overloaded)))
+        .count();
+    assert!(overloaded >= 3);
+    assert_eq!(*responses[0].as_ref().unwrap(), 0);
+
+    // The process keeps working once the load is gone.
+    assert_eq!(slow.try_request(7).unwrap(), 7);
+    // Responses can't be mistaken for shed requests.
+    let response = *b"\xfflunatic-overld\x01";
+    assert_eq!(slow.try_request(response).unwrap(), response);
+}
+
+#[test]
+fn priority_messages() {
+    struct Ordered;
+    struct Limited;
+
+    impl AbstractProcess for Ordered {
+        type Arg = ();
+        type State = Vec<u64>;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Vec<u64> {
+            Vec::new()
+        }
+    }
+
+    impl AbstractProcess for Limited {
+        type Arg = ();
+        type State = Vec<u64>;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Vec<u64> {
+            Vec::new()
+        }
+
+        fn overload_policy() -> Option<OverloadPolicy> {
+            Some(OverloadPolicy::Reject { max_queued: 10 })
+        }
+    }
+
+    fn handle(state: &mut Vec<u64>, message: u64) {
+        // Give the other messages time to queue up.
+        if message == 0 {
+            sleep(Duration::from_millis(50));
+        }
+        state.push(message);
+    }
+
+    impl MessageHandler<u64> for Ordered {
+        fn handle(state: &mut Self::State, message: u64) {
+            handle(state, message);
+        }
+    }
+
+    impl MessageHandler<u64> for Limited {
+        fn handle(state: &mut Self::State, message: u64) {
+            handle(state, message);
+        }
+    }
+
+    impl RequestHandler<()> for Ordered {
+        type Response = Vec<u64>;
+
+        fn handle(state: &mut Self::State, _: ()) -> Vec<u64> {
+            state.clone()
+        }
+    }
+
+    impl RequestHandler<()> for Limited {
+        type Response = Vec<u64>;
+
+        fn handle(state: &mut Self::State, _: ()) -> Vec<u64> {
+            state.clone()
+        }
+    }
+
+    fn send_all<T>(process: &ProcessRef<T>)
+    where
+        T: MessageHandler<u64>,
+    {
+        process.send(0);
+        process.send_priority(1, Priority::Normal);
+        process.send_priority(2, Priority::High);
+        process.send_priority(3, Priority::Normal);
+        process.send_priority(4, Priority::High);
+    }
+
+    let ordered = Ordered::start_link((), None);
+    send_all(&ordered);
+    assert_eq!(ordered.request(()), vec![0, 2, 4, 1, 3]);
+
+    let limited = Limited::start_link((), None);
+    send_all(&limited);
+    assert_eq!(limited.request(()), vec![0, 2, 4, 1, 3]);
+}
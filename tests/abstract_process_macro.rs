@@ -1,6 +1,6 @@
 use lunatic::{
     abstract_process, host,
-    process::{ProcessRef, StartProcess},
+    process::{ProcessRef, ReplyHandle, StartProcess},
     sleep, spawn_link, test, Tag,
 };
 use std::time::Duration;
@@ -367,6 +367,39 @@ fn request_timeout() {
         .is_err());
 }
 
+#[test]
+fn deferred_request() {
+    struct Barrier {
+        waiting: Vec<ReplyHandle<u32>>,
+    }
+
+    #[abstract_process]
+    impl Barrier {
+        #[init]
+        fn init(_process: ProcessRef<Self>, _: ()) -> Self {
+            Self { waiting: vec![] }
+        }
+
+        #[handle_deferred_request]
+        fn wait(&mut self, count: u32, reply: ReplyHandle<u32>) {
+            self.waiting.push(reply);
+            if self.waiting.len() as u32 == count {
+                for reply in self.waiting.drain(..) {
+                    reply.reply(count);
+                }
+            }
+        }
+    }
+
+    let barrier = Barrier::start_link((), None);
+    assert!(barrier
+        .with_timeout(Duration::from_millis(10))
+        .wait(2)
+        .is_err());
+    // The first request is still waiting on the barrier.
+    assert_eq!(barrier.wait(2), 2);
+}
+
 #[test]
 fn visibility() {
     mod m {
@@ -478,3 +511,40 @@ fn generics() {
         .unwrap();
     assert_eq!(3.1415926 * 2f32, s);
 }
+
+#[test]
+fn handle_cast_and_call() {
+    struct Counter {
+        count: u32,
+    }
+
+    #[abstract_process]
+    impl Counter {
+        #[init]
+        fn init(_: ProcessRef<Self>, count: u32) -> Self {
+            Self { count }
+        }
+
+        #[handle_cast]
+        fn add(&mut self, amount: u32) {
+            self.count += amount;
+        }
+
+        #[handle_call]
+        fn count(&self) -> u32 {
+            self.count
+        }
+    }
+
+    let counter = Counter::start_link(2, None);
+    counter.cast_add(3);
+    assert_eq!(5, counter.call_count());
+
+    counter.after(Duration::from_millis(10)).cast_add(1);
+    sleep(Duration::from_millis(20));
+    let count = counter
+        .with_timeout(Duration::from_millis(10))
+        .call_count()
+        .unwrap();
+    assert_eq!(6, count);
+}
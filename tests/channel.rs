@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use lunatic::{channel, sleep, test, Mailbox, Process, ReceiveError};
+
+#[test]
+fn unbounded() {
+    let (sender, receiver) = channel::channel();
+    for i in 0..10 {
+        Process::spawn((sender.clone(), i), |(sender, i), _: Mailbox<()>| {
+            sender.send(i)
+        });
+    }
+    let mut values: Vec<u64> = (0..10).map(|_| receiver.recv()).collect();
+    values.sort_unstable();
+    assert_eq!(values, (0..10).collect::<Vec<_>>());
+    assert_eq!(receiver.try_recv(), None);
+    receiver.close();
+}
+
+#[test]
+fn recv_timeout() {
+    let (sender, receiver) = channel::channel::<u64>();
+    assert!(matches!(
+        receiver.recv_timeout(Duration::from_millis(10)),
+        Err(ReceiveError::Timeout)
+    ));
+    // A value sent after the timeout is kept for the next receive.
+    sender.send(1);
+    assert_eq!(receiver.recv_timeout(Duration::from_millis(10)).unwrap(), 1);
+}
+
+#[test]
+fn bounded(mailbox: Mailbox<u64>) {
+    let (sender, receiver) = channel::bounded(2);
+    assert_eq!(sender.try_send(1), Ok(()));
+    assert_eq!(sender.try_send(2), Ok(()));
+    assert_eq!(sender.try_send(3), Err(3));
+
+    // The sender blocks until there is space.
+    Process::spawn(
+        (sender, mailbox.this()),
+        |(sender, parent), _: Mailbox<()>| {
+            sender.send(3);
+            parent.send(3);
+        },
+    );
+    sleep(Duration::from_millis(50));
+    assert!(mailbox.try_receive().is_none());
+    assert_eq!(receiver.recv(), 1);
+    assert_eq!(mailbox.receive(), 3);
+    assert_eq!(receiver.recv(), 2);
+    assert_eq!(receiver.recv(), 3);
+}
+
+#[test]
+fn rendezvous() {
+    let (sender, receiver) = channel::bounded(0);
+    assert_eq!(sender.try_send(1), Err(1));
+    Process::spawn(sender, |sender, _: Mailbox<()>| sender.send(1));
+    assert_eq!(receiver.recv(), 1);
+}
@@ -1,4 +1,4 @@
-use lunatic::{spawn_link, ProcessConfig};
+use lunatic::{env, fs, metrics, spawn_link, LunaticErrorKind, ProcessConfig, WasmModule};
 use lunatic_test::test;
 
 #[test]
@@ -49,6 +49,18 @@ fn config_with_config_creation_permissions() {
     assert_eq!(config.can_create_configs(), true);
 }
 
+#[test]
+fn config_memory_and_fuel_limits() {
+    let mut config = ProcessConfig::new();
+    config.set_max_memory(5_000_000);
+    config.set_max_fuel(10);
+    assert_eq!(config.get_max_memory(), 5_000_000);
+    assert_eq!(config.get_max_fuel(), 10);
+
+    let task = spawn_link!(@task &config, || (1..=100u64).sum::<u64>());
+    assert_eq!(task.result(), 5050);
+}
+
 #[test]
 #[should_panic]
 fn config_with_memory_limit() {
@@ -101,3 +113,105 @@ fn config_cli_args() {
     });
     let _ = task.result();
 }
+
+#[test]
+fn env_module() {
+    let mut config = ProcessConfig::new();
+    config.add_environment_variable("hello", "world");
+    config.add_command_line_argument("test");
+
+    let task = spawn_link!(@task &config, || {
+        (env::var("hello"), env::var("missing"), env::vars(), env::args())
+    });
+    let (hello, missing, vars, args) = task.result();
+    assert_eq!(hello.as_deref(), Some("world"));
+    assert_eq!(missing, None);
+    assert_eq!(vars, vec![("hello".to_owned(), "world".to_owned())]);
+    assert_eq!(args, vec!["test".to_owned()]);
+}
+
+#[test]
+fn compile_module_permission_denied() {
+    let config = ProcessConfig::new();
+    let task = spawn_link!(@task &config, || {
+        match WasmModule::new(&[]) {
+            Err(err) => err.kind() == LunaticErrorKind::PermissionDenied,
+            Ok(_) => false,
+        }
+    });
+    assert!(task.result());
+}
+
+#[test]
+fn compile_invalid_module() {
+    let mut config = ProcessConfig::new();
+    config.set_can_compile_modules(true);
+    let task = spawn_link!(@task &config, || {
+        match WasmModule::new(b"not wasm") {
+            Err(err) => err.kind() == LunaticErrorKind::InvalidModule,
+            Ok(_) => false,
+        }
+    });
+    assert!(task.result());
+}
+
+#[test]
+fn memory_usage_grows() {
+    let before = metrics::memory_usage();
+    assert!(before > 0);
+    let data = std::hint::black_box(vec![1u8; 4 * before]);
+    assert!(metrics::memory_usage() > before);
+    drop(data);
+}
+
+#[test]
+fn default_config_cant_access_files() {
+    let config = ProcessConfig::new();
+    let task = spawn_link!(@task &config, || {
+        (fs::read_dir(".").is_err(), fs::File::open("Cargo.toml").is_err())
+    });
+    assert_eq!(task.result(), (true, true));
+}
+
+#[test]
+fn config_builder() {
+    let config = ProcessConfig::default()
+        .with_max_memory(5_000_000)
+        .with_max_fuel(10)
+        .with_can_spawn_processes(true)
+        .with_environment_variable("hello", "world");
+    assert_eq!(config.get_max_memory(), 5_000_000);
+    assert_eq!(config.get_max_fuel(), 10);
+    assert!(config.can_spawn_processes());
+    assert!(!config.can_create_configs());
+
+    let task = spawn_link!(@task &config, || {
+        // Children of the sandboxed process inherit its limits and permissions.
+        let inherited = ProcessConfig::inherit();
+        let sub_task = spawn_link!(@task &inherited, || std::env::var("hello").unwrap());
+        sub_task.result()
+    });
+    assert_eq!(task.result(), "world");
+}
+
+#[test]
+#[should_panic]
+fn inherited_config_cant_be_modified() {
+    ProcessConfig::inherit().with_max_fuel(10);
+}
+
+#[test]
+fn label_and_metadata() {
+    let config = ProcessConfig::default()
+        .with_label("ws-conn:1234")
+        .with_metadata("peer", "127.0.0.1");
+    let task = spawn_link!(@task &config, || (env::label(), env::metadata()));
+    let (label, metadata) = task.result();
+    assert_eq!(label.as_deref(), Some("ws-conn:1234"));
+    assert_eq!(metadata, vec![("peer".to_owned(), "127.0.0.1".to_owned())]);
+
+    // Processes are unlabeled by default.
+    let config = ProcessConfig::new();
+    let task = spawn_link!(@task &config, || env::label());
+    assert_eq!(task.result(), None);
+}
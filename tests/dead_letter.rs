@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use lunatic::{
+    dead_letter::{self, DeadLetter},
+    sleep, test, Mailbox, Process,
+};
+
+#[test]
+fn forward_undelivered(mailbox: Mailbox<DeadLetter<u64>>) {
+    dead_letter::set_handler(&mailbox.this());
+
+    let target = Process::spawn((), |_, mailbox: Mailbox<u64>| {
+        mailbox.receive();
+        panic!();
+    });
+    assert!(target.send_checked(1));
+
+    // The target fails after receiving the first message.
+    sleep(Duration::from_millis(100));
+    assert!(!target.send_checked(2));
+    let letter = mailbox.receive();
+    assert_eq!(letter.target.id(), target.id());
+    assert_eq!(letter.message, 2);
+
+    // Without a handler the message is dropped.
+    dead_letter::remove_handler::<u64>();
+    assert!(!target.send_checked(3));
+    assert!(mailbox.try_receive().is_none());
+}
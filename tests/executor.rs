@@ -0,0 +1,42 @@
+use std::future::{poll_fn, Future, IntoFuture};
+use std::pin::pin;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use lunatic::{executor, sleep, spawn_link, test, Mailbox};
+
+async fn sum(mailbox: &Mailbox<u64>) -> u64 {
+    let task = spawn_link!(@task || 1 + 2);
+    task.await + mailbox.recv().await
+}
+
+#[test]
+fn await_task_and_mailbox(mailbox: Mailbox<u64>) {
+    mailbox.this().send(3);
+    assert_eq!(executor::block_on(sum(&mailbox)), 6);
+}
+
+#[test]
+fn sleep_future() {
+    let start = Instant::now();
+    executor::block_on(executor::sleep(Duration::from_millis(50)));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
+
+#[test]
+fn concurrent_operations() {
+    let mut slow = pin!(spawn_link!(@task || {
+        sleep(Duration::from_secs(5));
+        1
+    })
+    .into_future());
+    let mut timeout = pin!(executor::sleep(Duration::from_millis(50)));
+    let timed_out = executor::block_on(poll_fn(|cx| {
+        if timeout.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(true);
+        }
+        slow.as_mut().poll(cx).map(|_| false)
+    }));
+    // The slow task is canceled when its future is dropped.
+    assert!(timed_out);
+}
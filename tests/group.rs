@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use lunatic::{group::ProcessGroup, sleep, test, Mailbox, Process};
+
+#[test]
+fn broadcast(mailbox: Mailbox<u64>) {
+    let group = ProcessGroup::<u64>::new("broadcast");
+    let this = Process::<u64>::this();
+    let members: Vec<_> = (0..3)
+        .map(|i| {
+            Process::spawn((this.clone(), i), |(parent, i), mailbox: Mailbox<u64>| {
+                let message = mailbox.receive();
+                parent.send(message + i);
+            })
+        })
+        .collect();
+    for member in members.iter() {
+        group.join(member);
+    }
+    // Joining twice doesn't duplicate the member.
+    group.join(&members[0]);
+    assert_eq!(group.members(), members);
+
+    group.broadcast(10);
+    let mut results: Vec<_> = (0..3).map(|_| mailbox.receive()).collect();
+    results.sort_unstable();
+    assert_eq!(results, vec![10, 11, 12]);
+}
+
+#[test]
+fn join_and_leave() {
+    let group = ProcessGroup::<()>::new("join_and_leave");
+    let other = ProcessGroup::<u64>::new("join_and_leave");
+    assert!(group.members().is_empty());
+
+    let this = Process::<()>::this();
+    group.join(&this);
+    assert_eq!(group.members(), vec![this.clone()]);
+    // Groups with the same name and a different message type are separate.
+    assert!(other.members().is_empty());
+
+    assert!(group.leave(&this));
+    assert!(!group.leave(&this));
+    assert!(group.members().is_empty());
+}
+
+#[test]
+fn failed_members_are_removed() {
+    let group = ProcessGroup::<()>::new("failed_members_are_removed");
+    let member = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        panic!();
+    });
+    group.join(&member);
+    assert_eq!(group.members(), vec![member]);
+
+    group.broadcast(());
+    sleep(Duration::from_millis(50));
+    assert!(group.members().is_empty());
+}
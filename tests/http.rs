@@ -0,0 +1,211 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use lunatic::{
+    http::{Client, Request, Response, Server},
+    net::{self, WebSocket, WebSocketMessage},
+    spawn_link, Mailbox, Process,
+};
+use lunatic_test::test;
+
+/// Reads the head of a request and returns all lines, without the final empty one.
+fn read_request_head(reader: &mut impl BufRead) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end().to_owned();
+        if line.is_empty() {
+            return lines;
+        }
+        lines.push(line);
+    }
+}
+
+#[test]
+fn client_get() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = spawn_link!(@task |addr| {
+        let response = Client::new()
+            .get(&format!("http://{}/hello?name=lunatic", addr))
+            .header("Accept", "text/plain")
+            .send()
+            .unwrap();
+        (
+            response.status(),
+            response.header("content-type").map(str::to_owned),
+            response.text().unwrap(),
+        )
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    let head = read_request_head(&mut reader);
+    assert_eq!(head[0], "GET /hello?name=lunatic HTTP/1.1");
+    assert!(head.contains(&format!("Host: {}", addr)));
+    assert!(head.contains(&"Accept: text/plain".to_owned()));
+
+    let mut writer = stream;
+    writer
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 5\r\n\r\nhello")
+        .unwrap();
+
+    let (status, content_type, body) = client.result();
+    assert_eq!(status, 200);
+    assert_eq!(content_type.as_deref(), Some("text/plain"));
+    assert_eq!(body, "hello");
+}
+
+#[test]
+fn client_post_chunked() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = spawn_link!(@task |addr| {
+        let mut client = Client::new();
+        client.set_timeout(Some(Duration::from_secs(1)));
+        let response = client
+            .post(&format!("http://{}/echo", addr))
+            .body_reader(&b"ping"[..])
+            .send()
+            .unwrap();
+        (response.status(), response.bytes().unwrap())
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.clone());
+    let head = read_request_head(&mut reader);
+    assert_eq!(head[0], "POST /echo HTTP/1.1");
+    assert!(head.contains(&"Transfer-Encoding: chunked".to_owned()));
+    let mut body = [0; 14];
+    reader.read_exact(&mut body).unwrap();
+    assert_eq!(&body, b"4\r\nping\r\n0\r\n\r\n");
+
+    let mut writer = stream;
+    writer
+        .write_all(b"HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n2\r\npo\r\n2\r\nng\r\n0\r\n\r\n")
+        .unwrap();
+
+    let (status, body) = client.result();
+    assert_eq!(status, 201);
+    assert_eq!(body, b"pong");
+}
+
+fn echo(request: Request) -> Response {
+    match request.path() {
+        "/panic" => panic!("handler failed"),
+        "/ws" => return Response::websocket(&request, echo_websocket),
+        _ => {}
+    }
+    let body = format!(
+        "{} {} {}",
+        request.method(),
+        request.target(),
+        String::from_utf8_lossy(request.body())
+    );
+    Response::new(200)
+        .header("Content-Type", "text/plain")
+        .body(body)
+}
+
+/// Starts an echo server in a new process and returns its address.
+fn start_server(mailbox: &Mailbox<SocketAddr>) -> SocketAddr {
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        parent.send(server.local_addr().unwrap());
+        server.serve(echo).unwrap();
+    });
+    mailbox.receive()
+}
+
+#[test]
+fn server(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox);
+    let client = Client::new();
+
+    let response = client
+        .post(&format!("http://{}/echo?name=lunatic", addr))
+        .body("hello")
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.header("Content-Type"), Some("text/plain"));
+    assert_eq!(response.text().unwrap(), "POST /echo?name=lunatic hello");
+
+    // A failing handler only closes its own connection.
+    assert!(client
+        .get(&format!("http://{}/panic", addr))
+        .send()
+        .is_err());
+    let response = client.get(&format!("http://{}/", addr)).send().unwrap();
+    assert_eq!(response.text().unwrap(), "GET / ");
+}
+
+#[test]
+fn server_keep_alive(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox);
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET /first HTTP/1.1\r\nHost: test\r\n\r\n")
+        .unwrap();
+    stream
+        .write_all(b"GET /second HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+    assert!(response.ends_with("GET /second "));
+}
+
+fn echo_websocket(mut websocket: WebSocket) {
+    loop {
+        match websocket.receive().unwrap() {
+            message @ (WebSocketMessage::Text(_) | WebSocketMessage::Binary(_)) => {
+                websocket.send(message).unwrap()
+            }
+            WebSocketMessage::Close(_) => return,
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn websocket(mailbox: Mailbox<SocketAddr>) {
+    let addr = start_server(&mailbox);
+
+    // Plain HTTP requests can't be upgraded.
+    let response = Client::new()
+        .get(&format!("http://{}/ws", addr))
+        .send()
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let mut websocket = WebSocket::connect(&format!("ws://{}/ws", addr)).unwrap();
+    websocket
+        .send(WebSocketMessage::Text("hello".to_owned()))
+        .unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        WebSocketMessage::Text("hello".to_owned())
+    );
+    let large = vec![7; 70_000];
+    websocket
+        .send(WebSocketMessage::Binary(large.clone()))
+        .unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        WebSocketMessage::Binary(large)
+    );
+
+    websocket.close(1000, "done").unwrap();
+    assert_eq!(
+        websocket.receive().unwrap(),
+        WebSocketMessage::Close(Some((1000, String::new())))
+    );
+}
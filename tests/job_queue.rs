@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use lunatic::{
+    job_queue::{Job, JobQueue, JobQueueConfig},
+    process::StartProcess,
+    sleep, test, Mailbox, Process,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+enum TestJob {
+    Report(Process<u64>, u64),
+    Fail,
+    Panic,
+}
+
+impl Job for TestJob {
+    fn run(self) -> Result<(), String> {
+        match self {
+            TestJob::Report(process, value) => {
+                process.send(value);
+                Ok(())
+            }
+            TestJob::Fail => Err("failed".to_owned()),
+            TestJob::Panic => panic!("job panicked"),
+        }
+    }
+}
+
+#[test]
+fn runs_jobs(mailbox: Mailbox<u64>) {
+    let queue = JobQueue::<TestJob>::start_link(JobQueueConfig::new(2), None);
+    for i in 0..5 {
+        queue.enqueue(TestJob::Report(mailbox.this(), i));
+    }
+    let mut values: Vec<u64> = (0..5).map(|_| mailbox.receive()).collect();
+    values.sort_unstable();
+    assert_eq!(values, vec![0, 1, 2, 3, 4]);
+    sleep(Duration::from_millis(10));
+    assert_eq!(queue.pending(), 0);
+}
+
+#[test]
+fn dead_jobs(mailbox: Mailbox<u64>) {
+    let config = JobQueueConfig::new(1)
+        .max_attempts(2)
+        .backoff(Duration::from_millis(10));
+    let queue = JobQueue::<TestJob>::start_link(config, None);
+    let failing = queue.enqueue(TestJob::Fail);
+    let panicking = queue.enqueue(TestJob::Panic);
+    sleep(Duration::from_millis(200));
+
+    let mut dead = queue.dead_jobs();
+    dead.sort_by_key(|dead| dead.id);
+    assert_eq!(dead.len(), 2);
+    assert_eq!(dead[0].id, failing);
+    assert_eq!(dead[0].attempts, 2);
+    assert_eq!(dead[0].error, "failed");
+    assert_eq!(dead[1].id, panicking);
+    assert_eq!(queue.pending(), 0);
+
+    // The queue keeps working after a worker failed.
+    queue.enqueue(TestJob::Report(mailbox.this(), 1));
+    assert_eq!(mailbox.receive(), 1);
+
+    assert!(queue.retry(failing));
+    assert!(!queue.retry(failing));
+    assert_eq!(queue.pending(), 1);
+}
@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use lunatic::{sleep, test, ExitReason, LinkSet, Mailbox};
+
+#[test]
+fn wait_all(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.catch_link_failure();
+    let mut set = LinkSet::new(&mailbox);
+    for i in 0..4u64 {
+        set.spawn(i, |i, _: Mailbox<()>| {
+            sleep(Duration::from_millis(10 * i));
+            assert!(i % 2 == 0);
+        });
+    }
+    assert_eq!(set.len(), 4);
+    assert_eq!(set.running(), 4);
+
+    let mut exits = set.wait_all();
+    exits.sort_by_key(|exit| exit.index);
+    let reasons: Vec<ExitReason> = exits.iter().map(|exit| exit.reason).collect();
+    assert_eq!(
+        reasons,
+        vec![
+            ExitReason::Finished,
+            ExitReason::Failed,
+            ExitReason::Finished,
+            ExitReason::Failed
+        ]
+    );
+    assert_eq!(set.running(), 0);
+    assert!(set.wait_any().is_none());
+}
+
+#[test]
+fn wait_any(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.catch_link_failure();
+    let mut set = LinkSet::new(&mailbox);
+    let waiting = set.spawn((), |_, mailbox: Mailbox<()>| mailbox.receive());
+    set.spawn((), |_, _: Mailbox<()>| panic!("failed"));
+
+    let exit = set.wait_any().unwrap();
+    assert_eq!(exit.index, 1);
+    assert_eq!(exit.reason, ExitReason::Failed);
+
+    waiting.send(());
+    let exit = set.wait_any().unwrap();
+    assert_eq!(exit.index, 0);
+    assert_eq!(exit.reason, ExitReason::Finished);
+    assert_eq!(exit.process.id(), waiting.id());
+}
+
+#[test]
+fn kill_all(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.catch_link_failure();
+    let mut set = LinkSet::new(&mailbox);
+    for _ in 0..3 {
+        set.spawn((), |_, mailbox: Mailbox<()>| mailbox.receive());
+    }
+    set.kill_all();
+    let exits = set.wait_all();
+    assert_eq!(exits.len(), 3);
+    assert!(exits.iter().all(|exit| exit.reason == ExitReason::Failed));
+}
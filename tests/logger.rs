@@ -0,0 +1,26 @@
+#![cfg(feature = "log")]
+
+use log::{Level, LevelFilter, Log, Metadata};
+use lunatic::{
+    logger::{self, ProcessLogger},
+    test,
+};
+
+#[test]
+fn logger_level() {
+    let logger = ProcessLogger::new(LevelFilter::Info);
+    let metadata = |level| Metadata::builder().level(level).build();
+    assert!(logger.enabled(&metadata(Level::Error)));
+    assert!(logger.enabled(&metadata(Level::Info)));
+    assert!(!logger.enabled(&metadata(Level::Debug)));
+}
+
+#[test]
+fn log_with_process_name() {
+    ProcessLogger::init(LevelFilter::Info).unwrap();
+    log::info!("without a name");
+    logger::set_process_name("logger/named");
+    log::info!("with a name");
+    // The logger can only be installed once per process.
+    assert!(ProcessLogger::init(LevelFilter::Info).is_err());
+}
@@ -1,4 +1,6 @@
-use lunatic::{protocol::End, spawn, spawn_link, test, ProcessConfig};
+use std::time::Duration;
+
+use lunatic::{protocol::End, receive, spawn, spawn_link, test, Mailbox, ProcessConfig};
 
 #[test]
 fn spawn() {
@@ -131,3 +133,65 @@ fn func1() {}
 fn func2(a: i32, b: i32) {
     assert_eq!(a, b)
 }
+
+#[test]
+fn multi_capture_mailbox() {
+    let config = ProcessConfig::new();
+    let a = 1;
+    let b = "Hello".to_owned();
+    let c = vec![1, 2, 3];
+    spawn!(|a, b, _mailbox: Mailbox<()>| assert_eq!((a, b.as_str()), (1, "Hello")));
+    let b = "Hello".to_owned();
+    spawn!(&config, |a, b, c, _mailbox: Mailbox<()>| assert_eq!(
+        (a, b.as_str(), c),
+        (1, "Hello", vec![1, 2, 3])
+    ));
+    let b = "Hello".to_owned();
+    let c = vec![1, 2, 3];
+    spawn_link!(|a, b, c, _mailbox: Mailbox<()>| assert_eq!(
+        (a, b.as_str(), c),
+        (1, "Hello", vec![1, 2, 3])
+    ));
+    let b = "Hello".to_owned();
+    spawn_link!(&config, |a, b, _proto: Protocol<End>| assert_eq!(
+        (a, b.as_str()),
+        (1, "Hello")
+    ));
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+enum Message {
+    Ping(u64),
+    Data(String),
+    Stop,
+}
+
+#[test]
+fn receive(mailbox: Mailbox<Message>) {
+    let this = mailbox.this();
+    this.send(Message::Data("first".to_owned()));
+    this.send(Message::Ping(1));
+    this.send(Message::Stop);
+
+    // Messages that don't match stay in the mailbox.
+    let pinged = receive! { mailbox,
+        Message::Ping(n) => n,
+        Message::Stop => {
+            panic!("Stop was received before Ping");
+        }
+    };
+    assert_eq!(pinged, 1);
+
+    let data = receive! { mailbox,
+        Message::Data(data) => Some(data),
+        after Duration::from_millis(10) => None,
+    };
+    assert_eq!(data, Some("first".to_owned()));
+
+    let timed_out = receive! { mailbox,
+        Message::Ping(_) | Message::Data(_) => false,
+        after Duration::from_millis(10) => true,
+    };
+    assert!(timed_out);
+    assert_eq!(mailbox.receive(), Message::Stop);
+}
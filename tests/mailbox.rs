@@ -1,5 +1,7 @@
 #[cfg(feature = "msgpack_serializer")]
 mod msgpack {
+    use std::collections::HashMap;
+
     use lunatic::{serializer::MessagePack, test, Mailbox, Process, Tag};
     use serde::{Deserialize, Serialize};
 
@@ -55,6 +57,28 @@ mod msgpack {
         assert_eq!(input, output);
     }
 
+    #[test]
+    fn non_string_map_keys(mailbox: Mailbox<()>) {
+        let parent = mailbox.this();
+
+        let child = Process::spawn_link(
+            parent,
+            |parent, child_mailbox: Mailbox<HashMap<(u8, u8), String>, MessagePack>| {
+                let map = child_mailbox.receive();
+                assert_eq!(map[&(1, 2)], "one-two");
+                assert_eq!(map[&(3, 4)], "three-four");
+                // Indicate end of sub-process
+                parent.send(());
+            },
+        );
+
+        let mut map = HashMap::new();
+        map.insert((1, 2), "one-two".to_owned());
+        map.insert((3, 4), "three-four".to_owned());
+        child.send(map);
+        mailbox.receive();
+    }
+
     #[test]
     fn tagged_message(mailbox: Mailbox<()>) {
         let parent = mailbox.this();
@@ -85,3 +109,44 @@ mod msgpack {
         let _ = mailbox.receive();
     }
 }
+
+#[cfg(feature = "json_serializer")]
+mod json {
+    use lunatic::{serializer::Json, test, Mailbox, Process};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+    struct Point {
+        x: i32,
+        y: i32,
+        label: Option<String>,
+    }
+
+    #[test]
+    fn json_serializer(mailbox: Mailbox<Point, Json>) {
+        let parent = mailbox.this();
+
+        let child = Process::spawn_link(parent, |parent, child_mailbox: Mailbox<Point, Json>| {
+            let mut point = child_mailbox.receive();
+            point.label = Some("moved".to_owned());
+            point.x += 1;
+            parent.send(point);
+        });
+
+        child.send(Point {
+            x: 1,
+            y: 2,
+            label: None,
+        });
+        let point = mailbox.receive();
+
+        assert_eq!(
+            point,
+            Point {
+                x: 2,
+                y: 2,
+                label: Some("moved".to_owned()),
+            }
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use lunatic::{test, Mailbox, MessageEnum};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, MessageEnum)]
+enum Command {
+    Add(u32),
+    Sub(u32),
+    Rename { name: String },
+    Move(i32, i32),
+    Reset,
+}
+
+#[derive(Default)]
+struct State {
+    count: u32,
+    name: String,
+    position: (i32, i32),
+}
+
+impl CommandHandler for State {
+    fn add(&mut self, value: u32) {
+        self.count += value;
+    }
+
+    fn sub(&mut self, value: u32) {
+        self.count -= value;
+    }
+
+    fn rename(&mut self, name: String) {
+        self.name = name;
+    }
+
+    fn r#move(&mut self, value_0: i32, value_1: i32) {
+        self.position = (value_0, value_1);
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+    }
+}
+
+#[test]
+fn dispatch(mailbox: Mailbox<Command>) {
+    let this = mailbox.this();
+    this.send(Command::Add(5));
+    this.send(Command::Sub(2));
+    this.send(Command::Rename {
+        name: "lunatic".to_owned(),
+    });
+    this.send(Command::Move(1, -1));
+
+    let mut state = State::default();
+    for _ in 0..4 {
+        mailbox.receive_dispatch(&mut state);
+    }
+    assert_eq!(state.count, 3);
+    assert_eq!(state.name, "lunatic");
+    assert_eq!(state.position, (1, -1));
+
+    this.send(Command::Reset);
+    mailbox
+        .receive_dispatch_timeout(&mut state, Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(state.count, 0);
+    assert!(mailbox
+        .receive_dispatch_timeout(&mut state, Duration::from_millis(10))
+        .is_err());
+}
+
+#[derive(Serialize, Deserialize, MessageEnum)]
+enum Event<T> {
+    Value(T),
+    Ping,
+}
+
+struct Collector(Vec<String>);
+
+impl EventHandler<String> for Collector {
+    fn value(&mut self, value: String) {
+        self.0.push(value);
+    }
+
+    fn ping(&mut self) {
+        self.0.push("ping".to_owned());
+    }
+}
+
+#[test]
+fn generic_enum(mailbox: Mailbox<Event<String>>) {
+    let this = mailbox.this();
+    this.send("hello".to_owned().into());
+    this.send(Event::Ping);
+
+    let mut collector = Collector(Vec::new());
+    mailbox.receive_dispatch(&mut collector);
+    mailbox.receive_dispatch(&mut collector);
+    assert_eq!(collector.0, vec!["hello", "ping"]);
+}
@@ -2,7 +2,8 @@ use std::time::Duration;
 
 use lunatic::{
     process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartProcess},
-    spawn_link, Mailbox, Process, ReceiveError,
+    serializer::{Bincode, Bytes, Compressed, Raw, RawMessage, SerializerId, Tagged},
+    spawn_link, Mailbox, Priority, Process, ProcessRequest, ReceiveError, Tag,
 };
 use lunatic_test::test;
 
@@ -113,6 +114,78 @@ fn timeout(mailbox: Mailbox<u64>) {
     };
 }
 
+#[test]
+fn timeout_skips_tagged(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    let tag = Tag::new();
+    this.tag_send(tag, 1);
+    // A tagged message (e.g. a late response to a request) is not an untagged receive's business.
+    let result = mailbox.receive_timeout(Duration::new(0, 10_000));
+    assert!(matches!(result, Err(ReceiveError::Timeout)));
+    this.send(2);
+    assert_eq!(
+        mailbox.receive_timeout(Duration::from_millis(10)).unwrap(),
+        2
+    );
+    assert_eq!(mailbox.tag_receive(Some(&[tag])), 1);
+}
+
+#[test]
+fn try_receive(mailbox: Mailbox<u64>) {
+    assert_eq!(mailbox.try_receive(), None);
+    mailbox.this().send(1);
+    // Give the message time to arrive.
+    lunatic::sleep(Duration::from_millis(10));
+    assert_eq!(mailbox.try_receive(), Some(1));
+    assert_eq!(mailbox.try_receive(), None);
+}
+
+#[test]
+fn selective_receive(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    this.send(1);
+    this.send(2);
+    this.send(3);
+    assert_eq!(mailbox.receive_where(|message| *message == 3), 3);
+    // Skipped messages stay in the mailbox in their original order.
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(mailbox.receive_where(|message| *message % 2 == 0), 2);
+    let result = mailbox.receive_timeout(Duration::new(0, 10_000));
+    assert!(matches!(result, Err(ReceiveError::Timeout)));
+}
+
+#[test]
+fn peek_and_requeue(mailbox: Mailbox<i32>) {
+    let this = mailbox.this();
+    this.send(1);
+    this.send(2);
+    assert_eq!(mailbox.peek(), 1);
+    assert_eq!(mailbox.len(), 2);
+    assert_eq!(mailbox.receive(), 1);
+
+    this.send_priority(3, Priority::High);
+    assert_eq!(mailbox.peek(), 3);
+    assert_eq!(mailbox.receive(), 3);
+
+    // A requeued message is received before all waiting messages.
+    let message = mailbox.receive();
+    assert_eq!(message, 2);
+    this.send(4);
+    mailbox.requeue(message);
+    assert_eq!(mailbox.receive(), 2);
+    assert_eq!(mailbox.receive(), 4);
+    let result = mailbox.peek_timeout(Duration::new(0, 10_000));
+    assert!(matches!(result, Err(ReceiveError::Timeout)));
+
+    // Receives waiting for other tags leave requeued messages in place.
+    mailbox.requeue(5);
+    let tag = Tag::new();
+    this.tag_send(tag, 6);
+    assert_eq!(mailbox.tag_receive(Some(&[tag])), 6);
+    let mailbox = mailbox.catch_link_failure();
+    assert_eq!(mailbox.receive().unwrap(), 5);
+}
+
 #[derive(serde::Serialize, serde::Deserialize)]
 struct Proc(Process<i32>);
 
@@ -142,3 +215,139 @@ struct X {
     enb: E,
     enc: E,
 }
+
+#[test]
+fn priority_messages(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    this.send(1);
+    this.send(2);
+    this.send_priority(3, Priority::High);
+    this.send_priority(4, Priority::Normal);
+    // Give the messages time to arrive.
+    lunatic::sleep(Duration::from_millis(10));
+    assert_eq!(mailbox.receive(), 3);
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(mailbox.receive(), 2);
+    assert_eq!(mailbox.receive(), 4);
+}
+
+#[test]
+fn process_request() {
+    let adder = Process::spawn_link(
+        (),
+        |_, mailbox: Mailbox<ProcessRequest<(i32, i32), i32>>| {
+            let first = mailbox.receive();
+            let second = mailbox.receive();
+            // Reply out of order, the responses are matched by their tags.
+            let (a, b) = *second.data();
+            second.reply(a + b);
+            let (a, b) = *first.data();
+            first.reply(a + b);
+            // Don't reply to the last request.
+            mailbox.receive();
+        },
+    );
+
+    let child = adder.clone();
+    Process::spawn_link(child, |adder, _: Mailbox<()>| {
+        assert_eq!(adder.request((1, 2)), 3);
+    });
+    lunatic::sleep(Duration::from_millis(10));
+    assert_eq!(adder.request((3, 4)), 7);
+
+    let result = adder.request_timeout((5, 6), Duration::from_millis(10));
+    assert!(matches!(result, Err(ReceiveError::Timeout)));
+}
+
+#[test]
+fn receive_tagged_timeout(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    let tag = Tag::new();
+    this.send(1);
+    this.tag_send(Tag::new(), 2);
+    let result = mailbox.receive_tagged_timeout(tag, Duration::from_millis(10));
+    assert!(matches!(result, Err(ReceiveError::Timeout)));
+
+    this.tag_send(tag, 3);
+    assert_eq!(
+        mailbox
+            .receive_tagged_timeout(tag, Duration::from_millis(10))
+            .unwrap(),
+        3
+    );
+    // Other messages are not disturbed.
+    assert_eq!(mailbox.receive(), 1);
+}
+
+#[test]
+fn raw_bytes(mailbox: Mailbox<Vec<u8>, Bytes>) {
+    let data: Vec<u8> = (0..1024 * 1024).map(|i| i as u8).collect();
+    let this = mailbox.this();
+    this.send_bytes(&data);
+    this.send(vec![1, 2, 3]);
+    this.send(Vec::new());
+    assert_eq!(mailbox.receive(), data);
+    assert_eq!(mailbox.receive(), vec![1, 2, 3]);
+    assert_eq!(mailbox.receive(), Vec::<u8>::new());
+}
+
+#[test]
+fn drain_and_len(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    assert!(mailbox.is_empty());
+    this.send(1);
+    this.send(2);
+    this.send_priority(3, Priority::High);
+    this.tag_send(Tag::new(), 4);
+    assert_eq!(mailbox.len(), 3);
+    // Counting doesn't change the order of messages.
+    assert_eq!(mailbox.drain(), vec![3, 1, 2]);
+    assert_eq!(mailbox.len(), 0);
+}
+
+#[test]
+fn tagged_and_raw(mailbox: Mailbox<RawMessage, Raw>) {
+    let this = mailbox.this();
+    let tagged = Process::<u64, Tagged<Bincode>>::new(this.node_id(), this.id());
+    tagged.send(42);
+    let message = mailbox.receive();
+    assert_eq!(message.serializer_id, Bincode::SERIALIZER_ID);
+    assert_eq!(message.payload, 42u64.to_le_bytes());
+
+    // A raw message with the right ID can be decoded by the tagged serializer.
+    let child = Process::spawn(this, |parent, mailbox: Mailbox<u64, Tagged<Bincode>>| {
+        let value = mailbox.receive();
+        // Messages in other formats are rejected.
+        assert!(matches!(
+            mailbox.receive_timeout(Duration::from_secs(1)),
+            Err(ReceiveError::DeserializationFailed(_))
+        ));
+        parent.send(RawMessage {
+            serializer_id: 200,
+            payload: value.to_le_bytes().to_vec(),
+        });
+    });
+    let raw = Process::<RawMessage, Raw>::new(child.node_id(), child.id());
+    raw.send(message);
+    raw.send(RawMessage {
+        serializer_id: 200,
+        payload: 7u64.to_le_bytes().to_vec(),
+    });
+    assert_eq!(mailbox.receive().serializer_id, 200);
+}
+
+#[test]
+fn send_batch(mailbox: Mailbox<u64>) {
+    mailbox.this().send_batch(0..100);
+    assert_eq!(mailbox.drain(), (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn compressed(mailbox: Mailbox<Vec<u64>, Compressed<1024>>) {
+    let this = mailbox.this();
+    let large: Vec<u64> = (0..100_000).map(|i| i % 100).collect();
+    this.send(large.clone());
+    this.send(vec![1, 2, 3]);
+    assert_eq!(mailbox.receive(), large);
+    assert_eq!(mailbox.receive(), vec![1, 2, 3]);
+}
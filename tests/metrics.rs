@@ -0,0 +1,17 @@
+use lunatic::{metrics, test};
+
+#[test]
+fn emit_metrics() {
+    let requests = metrics::counter("test.requests");
+    assert_eq!(requests.name(), "test.requests");
+    requests.increment(1);
+    requests.increment(5);
+
+    let connections = metrics::gauge("test.connections");
+    connections.set(10.0);
+    connections.increment(2.0);
+    connections.decrement(1.0);
+
+    metrics::histogram("test.latency_ms").record(3.5);
+    assert!(metrics::memory_usage() > 0);
+}
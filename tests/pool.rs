@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use lunatic::{
+    pool::Pool,
+    process::{
+        AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler, StartProcess,
+    },
+    sleep, test,
+};
+
+struct Worker {
+    this: ProcessRef<Worker>,
+    count: u32,
+}
+
+impl AbstractProcess for Worker {
+    type Arg = u32;
+    type State = Worker;
+
+    fn init(this: ProcessRef<Self>, count: u32) -> Worker {
+        Worker { this, count }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Inc;
+impl RequestHandler<Inc> for Worker {
+    type Response = (ProcessRef<Worker>, u32);
+
+    fn handle(state: &mut Self::State, _: Inc) -> Self::Response {
+        state.count += 1;
+        (state.this.clone(), state.count)
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Panic;
+impl MessageHandler<Panic> for Worker {
+    fn handle(_: &mut Self::State, _: Panic) {
+        panic!();
+    }
+}
+
+#[test]
+fn round_robin() {
+    let pool = Pool::<Worker>::start_link((3, 10), None);
+    let workers = pool.workers();
+    assert_eq!(workers.len(), 3);
+
+    for round in 1..=2 {
+        for worker in workers.iter() {
+            let (handled_by, count) = pool.worker().request(Inc);
+            assert_eq!(&handled_by, worker);
+            assert_eq!(count, 10 + round);
+        }
+    }
+}
+
+#[test]
+fn restart_failed_worker() {
+    let pool = Pool::<Worker>::start_link((2, 0), None);
+    let workers = pool.workers();
+    workers[0].send(Panic);
+    sleep(Duration::from_millis(50));
+
+    let restarted = pool.workers();
+    assert_ne!(restarted[0], workers[0]);
+    assert_eq!(restarted[1], workers[1]);
+    // The restarted worker starts with the original argument.
+    assert_eq!(restarted[0].request(Inc).1, 1);
+}
@@ -1,8 +1,9 @@
 use std::time::Duration;
 
 use lunatic::{
+    distributed,
     host::api::{message::receive, process::die_when_link_dies},
-    spawn_link, Mailbox, Process, ProcessConfig,
+    spawn_link, DownReason, Mailbox, Process, ProcessConfig, ProcessDown, ReceiveError, Tag,
 };
 use lunatic_test::test;
 
@@ -137,3 +138,207 @@ fn link_should_trigger_on_dead_process() {
     // Confirm it's a link broke message and not a timeout
     assert_ne!(result, 9027);
 }
+
+#[test]
+fn link_with_tag(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.catch_link_failure();
+    let worker = |_, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        panic!("fails");
+    };
+    let a = Process::spawn((), worker);
+    let b = Process::spawn((), worker);
+    let (tag_a, tag_b) = (Tag::new(), Tag::new());
+    a.link_with(tag_a);
+    b.link_with(tag_b);
+
+    // The tag of the link tells which process failed.
+    b.send(());
+    let trapped = mailbox.tag_receive(Some(&[tag_a, tag_b])).unwrap_err();
+    assert_eq!(trapped.tag(), tag_b);
+    a.unlink();
+    a.kill();
+}
+
+#[test]
+fn monitor_failing_process() {
+    let child = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        panic!("panics");
+    });
+    let monitor = child.monitor();
+    child.send(());
+    let down = monitor.wait();
+    assert_eq!(down.id, child.id());
+    assert_eq!(down.tag, monitor.tag());
+    assert_eq!(down.reason, DownReason::Failed);
+}
+
+#[test]
+fn monitor_dead_process() {
+    let child = Process::spawn((), |_, _: Mailbox<()>| {});
+    // Give enough time for process to finish
+    lunatic::sleep(Duration::from_millis(100));
+    let monitor = child.monitor();
+    assert!(monitor.wait_timeout(Duration::from_millis(100)).is_ok());
+}
+
+#[test]
+fn demonitor() {
+    let child = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        panic!("panics");
+    });
+    let monitor = child.monitor();
+    let tag = monitor.tag();
+    child.demonitor(monitor);
+    // Give the monitor a chance to stop
+    lunatic::sleep(Duration::from_millis(10));
+    child.send(());
+    // Give enough time for process to fail
+    lunatic::sleep(Duration::from_millis(100));
+    let message = unsafe { Mailbox::<ProcessDown>::new() }
+        .tag_receive_timeout(Some(&[tag]), Duration::from_millis(10));
+    assert!(message.is_err());
+}
+
+#[test]
+fn dropped_monitor_stops() {
+    let child = Process::spawn((), |_, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        panic!("panics");
+    });
+    let tag = child.monitor().tag();
+    // Give the monitor a chance to stop
+    lunatic::sleep(Duration::from_millis(10));
+    child.send(());
+    // Give enough time for process to fail
+    lunatic::sleep(Duration::from_millis(100));
+    let message = unsafe { Mailbox::<ProcessDown>::new() }
+        .tag_receive_timeout(Some(&[tag]), Duration::from_millis(10));
+    assert!(message.is_err());
+}
+
+#[test]
+fn catch_link_failure(mailbox: Mailbox<u32>) {
+    let mailbox = mailbox.catch_link_failure();
+    let child = Process::spawn_link(mailbox.this(), |parent, mailbox: Mailbox<()>| {
+        parent.send(1);
+        mailbox.receive();
+        panic!("panics");
+    });
+    assert_eq!(mailbox.receive().unwrap(), 1);
+    child.send(());
+    assert!(mailbox.receive().is_err());
+}
+
+#[test]
+fn node_subscription() {
+    let subscription = distributed::subscribe();
+    // The membership of a single node "cluster" doesn't change.
+    let event = subscription.receive_timeout(Duration::from_millis(300));
+    assert!(matches!(event, Err(ReceiveError::Timeout)));
+    subscription.unsubscribe();
+}
+
+#[test]
+fn scope_waits_for_processes(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    let result = lunatic::scope(|scope| {
+        for i in 0..3 {
+            scope.spawn((this.clone(), i), |(parent, i), _: Mailbox<()>| {
+                lunatic::sleep(Duration::from_millis(20));
+                parent.send(i);
+            });
+        }
+        "done"
+    });
+    assert_eq!(result, "done");
+    // All processes sent their message before the scope returned.
+    let mut received: Vec<u64> = (0..3).filter_map(|_| mailbox.try_receive()).collect();
+    received.sort_unstable();
+    assert_eq!(received, vec![0, 1, 2]);
+}
+
+#[test]
+fn scope_cancel() {
+    lunatic::scope(|scope| {
+        let stuck = scope.spawn((), |_, mailbox: Mailbox<()>| mailbox.receive());
+        scope.spawn((), |_, _: Mailbox<()>| {});
+        // The scope doesn't wait for a cancelled process, and the caller survives it.
+        scope.cancel(&stuck);
+    });
+}
+
+#[test]
+fn spawn_notify(mailbox: Mailbox<()>) {
+    let tag = Tag::new();
+    Process::<()>::spawn_notify(
+        5,
+        |n, _: Mailbox<()>| lunatic::sleep(Duration::from_millis(n)),
+        mailbox.this(),
+        tag,
+    );
+    // The notification arrives once the task finished.
+    mailbox
+        .tag_receive_timeout(Some(&[tag]), Duration::from_millis(100))
+        .unwrap();
+}
+
+#[test]
+fn probe_alive_and_equality(mailbox: Mailbox<()>) {
+    let child = Process::spawn(mailbox.this(), |parent, mailbox: Mailbox<()>| {
+        mailbox.receive();
+        parent.send(());
+    });
+    assert!(child.probe_alive(Duration::from_millis(50)));
+    assert!(mailbox.this().probe_alive(Duration::from_millis(50)));
+
+    // Handles to the same process are deduplicated.
+    let copy = Process::<()>::new(child.node_id(), child.id());
+    let set: std::collections::HashSet<_> = vec![child.clone(), copy].into_iter().collect();
+    assert_eq!(set.len(), 1);
+
+    child.send(());
+    mailbox.receive();
+    lunatic::sleep(Duration::from_millis(100));
+    assert!(!child.probe_alive(Duration::from_millis(50)));
+}
+
+#[test]
+fn spawn_init(mailbox: Mailbox<i32>) {
+    let child = Process::spawn_init(
+        mailbox.this(),
+        |parent| {
+            // Registered before `spawn_init` returns.
+            Process::<i32>::this().register("spawn_init");
+            parent
+        },
+        |parent, mailbox: Mailbox<i32>| parent.send(mailbox.receive() * 2),
+    )
+    .unwrap();
+    assert_eq!(Process::<i32>::lookup("spawn_init"), Some(child.clone()));
+    child.send(21);
+    assert_eq!(mailbox.receive(), 42);
+
+    // A failing `init` is reported.
+    let failed =
+        Process::<i32>::spawn_init((), |_| panic!(), |_: (), _: Mailbox<i32>| unreachable!());
+    assert!(failed.is_none());
+}
+
+#[test]
+fn yield_now(mailbox: Mailbox<u64>) {
+    let parent = mailbox.this();
+    Process::spawn(parent, |parent, _: Mailbox<()>| {
+        let mut sum = 0;
+        for i in 0..1_000u64 {
+            sum += i;
+            if i % 100 == 0 {
+                lunatic::yield_now();
+            }
+        }
+        parent.send(sum);
+    });
+    assert_eq!(mailbox.receive(), 499_500);
+}
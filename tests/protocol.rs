@@ -1,6 +1,11 @@
+use std::time::Duration;
+
 use lunatic::{
-    protocol::{End, Protocol, Send},
-    Process,
+    branch, choose,
+    protocol::{
+        Branch, End, Offer, Par, Protocol, Rec, Recv, Resumable, Send, SessionAborted, Var,
+    },
+    sleep, Mailbox, Process, ReceiveError,
 };
 use lunatic_test::test;
 
@@ -13,6 +18,95 @@ fn drop_unfinished() {
     let _ = protocol.receive();
 }
 
+type Login = Recv<String, choose![Send<u64, End>, Send<String, End>, End]>;
+
+fn login(password: &str) -> Result<u64, Option<String>> {
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Login>| {
+        let (protocol, password) = protocol.receive();
+        match password.as_str() {
+            "secret" => {
+                let _ = protocol.select_left().send(42);
+            }
+            "" => {
+                let _ = protocol
+                    .select_right()
+                    .select_left()
+                    .send("empty password".to_owned());
+            }
+            _ => {
+                let _ = protocol.select_right().select_right();
+            }
+        }
+    });
+
+    let protocol = protocol.send(password.to_owned());
+    branch! { protocol,
+        Success => {
+            let (_, token) = protocol.receive();
+            Ok(token)
+        },
+        Invalid => {
+            let (_, reason) = protocol.receive();
+            Err(Some(reason))
+        },
+        Denied => Err(None),
+    }
+}
+
+#[test]
+fn choose_and_offer() {
+    assert_eq!(login("secret"), Ok(42));
+    assert_eq!(login(""), Err(Some("empty password".to_owned())));
+    assert_eq!(login("guess"), Err(None));
+}
+
+type Sum = Rec<Offer<Recv<i32, Var>, Send<i32, End>>>;
+
+#[test]
+fn recursive_protocol() {
+    let mut protocol = Process::spawn_link((), |_, mut protocol: Protocol<Sum>| {
+        let mut sum = 0;
+        loop {
+            match protocol.enter().offer() {
+                Branch::Left(next) => {
+                    let (next, number) = next.receive();
+                    sum += number;
+                    protocol = next;
+                }
+                Branch::Right(next) => {
+                    let _ = next.send(sum);
+                    break;
+                }
+            }
+        }
+    });
+
+    for number in 1..=4 {
+        protocol = protocol.enter().select_left().send(number);
+    }
+    let (_, sum) = protocol.enter().select_right().receive();
+    assert_eq!(sum, 10);
+}
+
+#[test]
+fn receive_timeout() {
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Send<i32, End>>| {
+        sleep(Duration::from_millis(100));
+        let _ = protocol.send(42);
+    });
+
+    // The peer stalls, the session stays in the same state.
+    let protocol = match protocol.receive_timeout(Duration::from_millis(10)) {
+        Err((protocol, ReceiveError::Timeout)) => protocol,
+        _ => panic!("expected a timeout"),
+    };
+    let (_, value) = protocol
+        .receive_timeout(Duration::from_secs(1))
+        .map_err(|(_, err)| err)
+        .unwrap();
+    assert_eq!(value, 42);
+}
+
 #[cfg(feature = "msgpack_serializer")]
 #[test]
 fn msg_pack_serializer() {
@@ -32,3 +126,55 @@ fn msg_pack_serializer() {
     let (_, result) = protocol.receive();
     assert_eq!(0.88, result);
 }
+
+type Duplex = Par<Recv<u64, End>, Send<u64, End>, Send<String, End>>;
+
+#[test]
+fn fork() {
+    let protocol = Process::spawn_link((), |_, protocol: Protocol<Duplex>| {
+        let protocol = protocol.fork(
+            |protocol| {
+                let (protocol, value) = protocol.receive();
+                assert_eq!(value, 1);
+                let _: Protocol<End> = protocol;
+            },
+            |protocol| {
+                let _ = protocol.send(2);
+            },
+        );
+        let _ = protocol.send("joined".to_owned());
+    });
+
+    let protocol = protocol.fork(
+        |protocol| {
+            let _ = protocol.send(1);
+        },
+        |protocol| {
+            let (protocol, value) = protocol.receive();
+            assert_eq!(value, 2);
+            let _: Protocol<End> = protocol;
+        },
+    );
+    let (_, joined) = protocol.receive();
+    assert_eq!(joined, "joined");
+}
+
+type Double = Recv<u64, Send<u64, End>>;
+
+#[test]
+fn resumable_session(mailbox: Mailbox<()>) {
+    let mailbox = mailbox.catch_link_failure();
+    let session = Resumable::new((), |_, protocol: Protocol<Double>| {
+        let (protocol, value) = protocol.receive();
+        assert_ne!(value, 0, "can't double zero");
+        let _ = protocol.send(value * 2);
+    });
+
+    // The crash of the peer aborts the session without failing this process.
+    let aborted = session.start(&mailbox).send(0).try_receive();
+    assert_eq!(aborted.err(), Some(SessionAborted));
+
+    // The session restarts from the beginning.
+    let (_, doubled) = session.start(&mailbox).send(21).try_receive().unwrap();
+    assert_eq!(doubled, 42);
+}
@@ -0,0 +1,22 @@
+use lunatic::{rand, test};
+
+#[test]
+fn random_values() {
+    assert_eq!(rand::random_bytes(32).len(), 32);
+    assert_ne!(rand::random_bytes(32), rand::random_bytes(32));
+    assert_ne!(rand::random_u64(), rand::random_u64());
+}
+
+#[test]
+fn uuid_v4() {
+    let uuid = rand::uuid_v4();
+    assert_ne!(uuid, rand::uuid_v4());
+
+    let formatted = uuid.to_string();
+    assert_eq!(formatted.len(), 36);
+    assert_eq!(&formatted[14..15], "4");
+    assert!(matches!(&formatted[19..20], "8" | "9" | "a" | "b"));
+
+    let uuid = rand::Uuid::from_bytes([0xab; 16]);
+    assert_eq!(uuid.to_string(), "abababab-abab-abab-abab-abababababab");
+}
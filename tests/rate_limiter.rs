@@ -0,0 +1,18 @@
+use std::time::{Duration, Instant};
+
+use lunatic::{process::StartProcess, rate_limiter::RateLimiter, test};
+
+#[test]
+fn token_bucket() {
+    let limiter = RateLimiter::start_link((2, Duration::from_millis(100)), None);
+    assert!(limiter.try_acquire());
+    assert!(limiter.try_acquire());
+    assert!(!limiter.try_acquire());
+    assert!(!limiter.acquire_timeout(Duration::from_millis(10)));
+
+    // Waits for the next refill.
+    let start = Instant::now();
+    limiter.acquire();
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert!(limiter.acquire_timeout(Duration::from_millis(200)));
+}
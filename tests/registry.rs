@@ -0,0 +1,60 @@
+use lunatic::{registry, spawn_link, test, Mailbox, Process};
+
+#[test]
+fn register_and_lookup(mailbox: Mailbox<i32>) {
+    registry::register("registry/parent", &mailbox.this());
+
+    spawn_link!(|| {
+        let parent = registry::lookup::<i32>("registry/parent").unwrap();
+        parent.send(1337);
+    });
+
+    assert_eq!(1337, mailbox.receive());
+}
+
+#[test]
+fn lookup_wrong_type(mailbox: Mailbox<i32>) {
+    registry::register("registry/typed", &mailbox.this());
+    let process: Option<Process<String>> = registry::lookup("registry/typed");
+    assert!(process.is_none());
+}
+
+#[test]
+fn unregister(mailbox: Mailbox<i32>) {
+    registry::register("registry/unregister", &mailbox.this());
+    assert!(registry::lookup::<i32>("registry/unregister").is_some());
+    registry::unregister::<i32>("registry/unregister");
+    assert!(registry::lookup::<i32>("registry/unregister").is_none());
+}
+
+#[test]
+fn iter(mailbox: Mailbox<i32>) {
+    registry::register("registry/iter/a", &mailbox.this());
+    registry::register("registry/iter/b", &mailbox.this());
+    registry::register("registry/iter/c", &mailbox.this());
+    registry::register("registry/other", &mailbox.this());
+    registry::unregister::<i32>("registry/iter/c");
+
+    let entries = registry::iter("registry/iter/");
+    let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["registry/iter/a", "registry/iter/b"]);
+    assert!(entries
+        .iter()
+        .all(|(_, process)| process.id() == mailbox.this().id()));
+}
+
+#[test]
+fn global_register_and_lookup(mailbox: Mailbox<i32>) {
+    registry::global::register("registry/global", &mailbox.this());
+    // Global names don't collide with local ones.
+    assert!(registry::lookup::<i32>("registry/global").is_none());
+
+    spawn_link!(|| {
+        let parent = registry::global::lookup::<i32>("registry/global").unwrap();
+        parent.send(1337);
+    });
+    assert_eq!(1337, mailbox.receive());
+
+    registry::global::unregister::<i32>("registry/global");
+    assert!(registry::global::lookup::<i32>("registry/global").is_none());
+}
@@ -0,0 +1,38 @@
+use std::collections::HashMap;
+
+use lunatic::{
+    process::{process_id, StartProcess},
+    router::{Router, RouterArg},
+    test, Mailbox, Process,
+};
+
+type Event = (Process<(u64, u64, u64)>, u64, u64);
+
+fn worker(mailbox: Mailbox<Event>) {
+    loop {
+        let (parent, key, seq) = mailbox.receive();
+        parent.send((process_id(), key, seq));
+    }
+}
+
+#[test]
+fn routes_by_key(mailbox: Mailbox<(u64, u64, u64)>) {
+    let router = Router::start_link(RouterArg::new(4, |(_, key, _)| *key, worker), None);
+    assert_eq!(router.workers().len(), 4);
+    for seq in 0..10 {
+        for key in 0..8 {
+            router.route((mailbox.this(), key, seq));
+        }
+    }
+
+    let mut workers = HashMap::new();
+    let mut next_seq = HashMap::new();
+    for _ in 0..80 {
+        let (worker, key, seq) = mailbox.receive();
+        // Each key is handled by a single worker, in the order it was routed.
+        assert_eq!(*workers.entry(key).or_insert(worker), worker);
+        let next = next_seq.entry(key).or_insert(0);
+        assert_eq!(seq, *next);
+        *next += 1;
+    }
+}
@@ -0,0 +1,46 @@
+use lunatic::{
+    process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartProcess},
+    service::Service,
+    spawn_link, test, Mailbox,
+};
+
+struct Billing;
+
+impl AbstractProcess for Billing {
+    type Arg = u64;
+    type State = u64;
+
+    fn init(_: ProcessRef<Self>, factor: u64) -> u64 {
+        factor
+    }
+}
+
+impl RequestHandler<u64> for Billing {
+    type Response = u64;
+
+    fn handle(factor: &mut Self::State, amount: u64) -> u64 {
+        amount * *factor
+    }
+}
+
+#[test]
+fn register_and_connect(mailbox: Mailbox<u64>) {
+    assert!(Service::<Billing>::connect("service/billing").is_none());
+    let server = Billing::start_link(2, None);
+    let service = Service::register("service/billing", &server);
+
+    let this = mailbox.this();
+    spawn_link!(|this| {
+        let billing = Service::<Billing>::connect("service/billing").unwrap();
+        this.send(billing.request(21));
+    });
+    assert_eq!(mailbox.receive(), 42);
+
+    // A new server replaces the old one.
+    let replacement = Billing::start_link(3, None);
+    Service::register("service/billing", &replacement);
+    assert_eq!(service.resolve().unwrap(), replacement);
+
+    service.unregister();
+    assert!(Service::<Billing>::connect("service/billing").is_none());
+}
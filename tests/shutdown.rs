@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use lunatic::{shutdown, test, Mailbox, Process};
+
+#[test]
+fn shutdown(mailbox: Mailbox<String>) {
+    let this = mailbox.this();
+    Process::spawn(this.clone(), |parent, _: Mailbox<()>| {
+        let subscription = shutdown::subscribe();
+        parent.send("subscribed".to_owned());
+        let signal = subscription.receive();
+        parent.send("flushed".to_owned());
+        signal.acknowledge();
+    });
+    Process::spawn(this, |parent, mailbox: Mailbox<()>| {
+        let _subscription = shutdown::subscribe();
+        parent.send("subscribed".to_owned());
+        // Never acknowledges the signal.
+        mailbox.receive();
+    });
+    mailbox.receive();
+    mailbox.receive();
+
+    // Only the process that didn't acknowledge is killed.
+    assert_eq!(shutdown::shutdown(Duration::from_millis(100)), 1);
+    assert_eq!(mailbox.receive(), "flushed");
+    // All subscribers are gone after the shutdown.
+    assert_eq!(shutdown::shutdown(Duration::from_millis(100)), 0);
+}
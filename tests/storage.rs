@@ -0,0 +1,78 @@
+use std::time::Duration;
+
+use lunatic::{
+    process::StartProcess,
+    sleep,
+    storage::{ShardedTable, Table},
+    test, Mailbox, Process,
+};
+
+#[test]
+fn insert_get_remove() {
+    let table = Table::<String, u64>::start_link((), None);
+    assert_eq!(table.insert("a".to_owned(), 1), None);
+    assert_eq!(table.insert("a".to_owned(), 2), Some(1));
+    assert_eq!(table.get("a".to_owned()), Some(2));
+    assert_eq!(table.remove("a".to_owned()), Some(2));
+    assert_eq!(table.get("a".to_owned()), None);
+}
+
+#[test]
+fn update_and_entries() {
+    let table = Table::<String, u64>::start_link((), None);
+    assert_eq!(
+        table.update("a".to_owned(), |v| Some(v.unwrap_or(0) + 1)),
+        Some(1)
+    );
+    assert_eq!(
+        table.update("a".to_owned(), |v| Some(v.unwrap_or(0) + 1)),
+        Some(2)
+    );
+    table.insert("b".to_owned(), 10);
+    // Returning `None` removes the key.
+    assert_eq!(table.update("b".to_owned(), |_| None), None);
+
+    let mut entries = table.entries();
+    entries.sort();
+    assert_eq!(entries, vec![("a".to_owned(), 2)]);
+}
+
+#[test]
+fn ttl() {
+    let table = Table::<u32, u32>::start_link((), None);
+    table.insert_ttl(1, 1, Duration::from_millis(20));
+    table.insert(2, 2);
+    assert_eq!(table.get(1), Some(1));
+    sleep(Duration::from_millis(50));
+    assert_eq!(table.get(1), None);
+    assert_eq!(table.entries(), vec![(2, 2)]);
+}
+
+#[test]
+fn sharded_table(mailbox: Mailbox<()>) {
+    let table = ShardedTable::<u32, u32>::new(4);
+    for i in 0..100 {
+        table.insert(i, i * 2);
+    }
+    // Keys are spread over all shards.
+    assert!(table
+        .shards()
+        .iter()
+        .all(|shard| !shard.entries().is_empty()));
+
+    // Other processes access the same shards.
+    Process::spawn(
+        (table.clone(), mailbox.this()),
+        |(table, parent), _: Mailbox<()>| {
+            assert_eq!(table.update(7, |v| v.map(|v| v + 1)), Some(15));
+            parent.send(());
+        },
+    );
+    mailbox.receive();
+    assert_eq!(table.get(7), Some(15));
+
+    let mut entries = table.entries();
+    entries.sort_unstable();
+    assert_eq!(entries.len(), 100);
+    assert_eq!(entries[0], (0, 0));
+}
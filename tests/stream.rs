@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+use lunatic::{stream, test, Mailbox, Process, ReceiveError};
+
+#[test]
+fn stream_values() {
+    let (sink, stream) = stream::channel::<u64>(4);
+    Process::spawn_link(sink, |mut sink, _: Mailbox<()>| {
+        for i in 0..100 {
+            sink.send(i);
+        }
+        sink.close();
+    });
+    let values: Vec<u64> = stream.collect();
+    assert_eq!(values, (0..100).collect::<Vec<_>>());
+}
+
+#[test]
+fn producer_blocks_on_full_window(mailbox: Mailbox<u64>) {
+    let (sink, mut stream) = stream::channel::<u64>(2);
+    Process::spawn_link(
+        (sink, mailbox.this()),
+        |(mut sink, parent), _: Mailbox<()>| {
+            for i in 0..3 {
+                sink.send(i);
+                parent.send(i);
+            }
+            sink.close();
+        },
+    );
+    assert_eq!(mailbox.receive(), 0);
+    assert_eq!(mailbox.receive(), 1);
+    // The window is full until the consumer receives a value.
+    let blocked = mailbox.receive_timeout(Duration::from_millis(50));
+    assert!(matches!(blocked, Err(ReceiveError::Timeout)));
+
+    assert_eq!(stream.receive(), Some(0));
+    assert_eq!(mailbox.receive(), 2);
+    assert_eq!(stream.receive(), Some(1));
+    assert_eq!(stream.receive(), Some(2));
+    assert_eq!(stream.receive(), None);
+}
@@ -5,8 +5,11 @@ use lunatic::{
         AbstractProcess, Message, MessageHandler, ProcessRef, Request, RequestHandler, StartProcess,
     },
     sleep, spawn,
-    supervisor::{Supervisor, SupervisorConfig, SupervisorStrategy},
-    test,
+    supervisor::{
+        CrashReport, DynamicSupervisor, Restart, Supervisor, SupervisorConfig, SupervisorEvent,
+        SupervisorStrategy,
+    },
+    test, Mailbox, Process,
 };
 
 const LOGGER_NAME: &'static str = "logger/assert_order";
@@ -456,6 +459,31 @@ fn ten_children_sup() {
     Sup::start_link((), None);
 }
 
+#[test]
+#[should_panic]
+fn max_restarts_exceeded() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = A;
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForOne);
+            config.set_max_restarts(2);
+            config.set_max_seconds(60);
+            config.children_args(((0, 'a'), None));
+        }
+    }
+
+    // The supervisor gives up on the third failure and takes the linked test process down.
+    let sup = Sup::start_link((), None);
+    for _ in 0..3 {
+        sup.children().send(Panic);
+        sleep(Duration::from_millis(10));
+    }
+    sleep(Duration::from_millis(100));
+}
+
 #[test]
 #[should_panic]
 fn children_args_not_called() {
@@ -510,6 +538,61 @@ fn shutdown() {
     );
 }
 
+struct Slow;
+
+impl AbstractProcess for Slow {
+    type Arg = char;
+    type State = Slow;
+
+    fn init(_: ProcessRef<Self>, name: char) -> Slow {
+        if let Some(logger) = ProcessRef::<Logger>::lookup(LOGGER_NAME) {
+            logger.request(LogEvent::Init(name));
+        }
+        Slow
+    }
+
+    fn terminate(_: Self::State) {
+        sleep(Duration::from_secs(10));
+    }
+}
+
+#[test]
+fn shutdown_timeout() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, Slow);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForAll);
+            config.set_shutdown_timeout(1, Duration::from_millis(50));
+            config.children_args((((0, 'a'), None), ('b', None)));
+        }
+    }
+
+    let logger = Logger::start_link((), Some(LOGGER_NAME));
+    let sup = Sup::start((), None);
+
+    // The slow child is killed when it's shut down to be restarted with the failed one.
+    sup.children().0.send(Panic);
+    sleep(Duration::from_millis(200));
+    assert_eq!(sup.count_children(), 2);
+
+    sup.shutdown();
+    let log = logger.request(TakeLogs);
+    assert_eq!(
+        log,
+        vec![
+            LogEvent::Init('a'),
+            LogEvent::Init('b'),
+            LogEvent::Panic('a'),
+            LogEvent::Init('a'),
+            LogEvent::Init('b'),
+            LogEvent::Shutdown('a'),
+        ],
+    );
+}
+
 #[test]
 fn lookup_children() {
     struct Sup;
@@ -575,3 +658,340 @@ fn block_until_shutdown() {
     // the test will hang if block_until_shutdown() fails
     sup_cloned.block_until_shutdown()
 }
+
+#[test]
+fn nested_supervisor() {
+    struct Inner;
+    impl Supervisor for Inner {
+        type Arg = u32;
+        type Children = A;
+
+        fn init(config: &mut SupervisorConfig<Self>, count: u32) {
+            config.set_strategy(SupervisorStrategy::OneForOne);
+            // Give up on the first failure, so that the outer supervisor needs to step in.
+            config.set_max_restarts(0);
+            config.children_args(((count, 'i'), None));
+        }
+    }
+
+    struct Outer;
+    impl Supervisor for Outer {
+        type Arg = ();
+        type Children = (A, Inner);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForOne);
+            config.children_args((((0, 'o'), None), (10, None)));
+        }
+    }
+
+    let outer = Outer::start_link((), None);
+    let (_, inner) = outer.children();
+    let child = inner.children();
+    child.send(Inc);
+    assert_eq!(child.request(Count), 11);
+
+    // The inner supervisor fails together with its child and is restarted with the original
+    // arguments by the outer one.
+    child.send(Panic);
+    sleep(Duration::from_millis(50));
+    let (_, restarted_inner) = outer.children();
+    assert_ne!(inner, restarted_inner);
+    assert_eq!(restarted_inner.children().request(Count), 10);
+}
+
+#[test]
+fn dynamic_supervisor() {
+    // Doesn't use `A`, so that no events are logged to the loggers of other tests.
+    struct Worker(u32);
+    impl AbstractProcess for Worker {
+        type Arg = u32;
+        type State = Self;
+
+        fn init(_: ProcessRef<Self>, count: u32) -> Self {
+            Worker(count)
+        }
+    }
+    impl MessageHandler<Inc> for Worker {
+        fn handle(state: &mut Self::State, _: Inc) {
+            state.0 += 1;
+        }
+    }
+    impl RequestHandler<Count> for Worker {
+        type Response = u32;
+
+        fn handle(state: &mut Self::State, _: Count) -> u32 {
+            state.0
+        }
+    }
+    impl MessageHandler<Panic> for Worker {
+        fn handle(_: &mut Self::State, _: Panic) {
+            panic!();
+        }
+    }
+
+    let sup = DynamicSupervisor::<Worker>::start_link((), None);
+    assert!(sup.which_children().is_empty());
+
+    let first = sup.start_child(1);
+    let second = sup.start_child(2);
+    assert!(sup.which_children() == vec![first.clone(), second.clone()]);
+
+    // A failed child is restarted with its original argument.
+    first.send(Inc);
+    assert_eq!(first.request(Count), 2);
+    first.send(Panic);
+    sleep(Duration::from_millis(50));
+    let children = sup.which_children();
+    assert_eq!(children.len(), 2);
+    assert!(children[0] != first);
+    assert_eq!(children[0].request(Count), 1);
+
+    assert!(sup.terminate_child(&second));
+    assert!(!sup.terminate_child(&second));
+    assert!(sup.which_children() == vec![children[0].clone()]);
+}
+
+#[test]
+fn snapshot_restored_on_restart() {
+    struct Snapshotting(u32);
+    impl AbstractProcess for Snapshotting {
+        type Arg = u32;
+        type State = Self;
+
+        fn init(_: ProcessRef<Self>, count: u32) -> Self {
+            Snapshotting(count)
+        }
+
+        fn snapshot(state: &Self) -> Option<u32> {
+            Some(state.0)
+        }
+    }
+    impl MessageHandler<Inc> for Snapshotting {
+        fn handle(state: &mut Self::State, _: Inc) {
+            state.0 += 1;
+        }
+    }
+    impl RequestHandler<Count> for Snapshotting {
+        type Response = u32;
+
+        fn handle(state: &mut Self::State, _: Count) -> u32 {
+            state.0
+        }
+    }
+    impl MessageHandler<Panic> for Snapshotting {
+        fn handle(_: &mut Self::State, _: Panic) {
+            panic!();
+        }
+    }
+
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = Snapshotting;
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForOne);
+            config.children_args((10, None));
+        }
+    }
+
+    let sup = Sup::start_link((), None);
+    let child = sup.children();
+    child.send(Inc);
+    child.send(Inc);
+    assert_eq!(child.request(Count), 12);
+
+    // The child is restarted with the last snapshot instead of the original argument.
+    child.send(Panic);
+    sleep(Duration::from_millis(50));
+    let restarted = sup.children();
+    assert!(restarted != child);
+    assert_eq!(restarted.request(Count), 12);
+}
+
+#[test]
+fn temporary_child_not_restarted() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, A);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForAll);
+            config.set_restart(1, Restart::Temporary);
+            config.children_args((((0, 'a'), None), ((0, 'b'), None)));
+        }
+    }
+
+    let logger = Logger::start_link((), Some(LOGGER_NAME));
+    let sup = Sup::start((), None);
+    let (a, b) = sup.children();
+
+    // The temporary child is not restarted and its sibling keeps running.
+    b.send(Panic);
+    sleep(Duration::from_millis(10));
+    assert!(sup.children().0 == a);
+
+    // Restarting all children skips the terminated temporary child.
+    a.send(Panic);
+    sleep(Duration::from_millis(10));
+    sup.shutdown();
+
+    let log = logger.request(TakeLogs);
+    assert_eq!(
+        log,
+        vec![
+            LogEvent::Init('a'),
+            LogEvent::Init('b'),
+            LogEvent::Panic('b'),
+            LogEvent::Panic('a'),
+            LogEvent::Init('a'),
+            LogEvent::Shutdown('a'),
+        ]
+    );
+}
+
+#[test]
+fn children_info() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, A);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_restart(1, Restart::Temporary);
+            config.children_args((((0, 'a'), Some("a".to_owned())), ((0, 'b'), None)));
+        }
+    }
+
+    let sup = Sup::start((), None);
+    let (a, b) = sup.children();
+    assert_eq!(sup.count_children(), 2);
+
+    a.send(Panic);
+    b.send(Panic);
+    sleep(Duration::from_millis(10));
+    let (a, _) = sup.children();
+
+    let info = sup.which_children();
+    assert_eq!(sup.count_children(), 1);
+    assert_eq!(info[0].name.as_deref(), Some("a"));
+    assert_eq!(info[0].process.id(), a.id());
+    assert_eq!(info[0].restarts, 1);
+    assert!(info[0].running);
+    assert_eq!(info[1].name, None);
+    assert_eq!(info[1].restart, Restart::Temporary);
+    assert_eq!(info[1].restarts, 0);
+    assert!(!info[1].running);
+}
+
+#[test]
+fn crash_reports(mailbox: Mailbox<CrashReport>) {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = Process<CrashReport>;
+        type Children = A;
+
+        fn init(config: &mut SupervisorConfig<Self>, crash_log: Process<CrashReport>) {
+            config.set_crash_log(crash_log);
+            config.children_args(((0, 'a'), None));
+        }
+    }
+
+    let sup = Sup::start(mailbox.this(), None);
+    let child = sup.children();
+    child.send(Panic);
+
+    let report = mailbox.receive();
+    assert_eq!(report.process.id(), child.id());
+    assert!(report.message.contains("explicit panic"));
+    sleep(Duration::from_millis(10));
+    assert_eq!(sup.which_children()[0].last_crash, Some(report));
+}
+
+#[test]
+fn events(mailbox: Mailbox<SupervisorEvent>) {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = A;
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_max_restarts(1);
+            config.children_args(((0, 'a'), None));
+        }
+    }
+
+    let sup = Sup::start((), None);
+    sup.subscribe_events(&mailbox.this());
+    let child = sup.children();
+    child.send(Panic);
+
+    match mailbox.receive() {
+        SupervisorEvent::ChildCrashed {
+            index,
+            process,
+            report,
+        } => {
+            assert_eq!(index, 0);
+            assert_eq!(process.id(), child.id());
+            assert!(report.unwrap().message.contains("explicit panic"));
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+    let restarted = sup.children();
+    assert_eq!(
+        mailbox.receive(),
+        SupervisorEvent::ChildRestarted {
+            index: 0,
+            process: Process::new(restarted.node_id(), restarted.id()),
+            restarts: 1,
+        }
+    );
+
+    // The second failure exceeds the restart intensity.
+    restarted.send(Panic);
+    assert!(matches!(
+        mailbox.receive(),
+        SupervisorEvent::ChildCrashed { .. }
+    ));
+    assert_eq!(
+        mailbox.receive(),
+        SupervisorEvent::LimitReached {
+            max_restarts: 1,
+            max_seconds: 5,
+        }
+    );
+}
+
+#[test]
+fn get_child_by_name() {
+    struct Sup;
+    impl Supervisor for Sup {
+        type Arg = ();
+        type Children = (A, Logger);
+
+        fn init(config: &mut SupervisorConfig<Self>, _: ()) {
+            config.set_strategy(SupervisorStrategy::OneForOne);
+            config.children_args((((0, 'a'), Some("get_child/a".to_owned())), ((), None)));
+        }
+    }
+
+    let sup = Sup::start((), None);
+
+    let child = sup.get_child::<A>("get_child/a").unwrap();
+    assert_eq!(child, sup.children().0);
+    child.send(Inc);
+    assert_eq!(child.request(Count), 1);
+    // Names only match children of the right type.
+    assert!(sup.get_child::<Logger>("get_child/a").is_none());
+    assert!(sup.get_child::<A>("get_child/missing").is_none());
+
+    // The name refers to the restarted child.
+    child.send(Panic);
+    sleep(Duration::from_millis(10));
+    let restarted = sup.get_child::<A>("get_child/a").unwrap();
+    assert_ne!(restarted, child);
+    assert_eq!(restarted.request(Count), 0);
+}
@@ -1,6 +1,6 @@
 use std::time::Duration;
 
-use lunatic::{sleep, spawn_link, ProcessConfig};
+use lunatic::{sleep, spawn_link, task, ProcessConfig};
 use lunatic_test::test;
 
 #[test]
@@ -45,3 +45,39 @@ fn timeout_task() {
     let result = task.result_timeout(Duration::from_millis(10));
     assert!(result.is_err());
 }
+
+#[test]
+fn cancel_task() {
+    let task = spawn_link!(@task || panic_later());
+    task.cancel();
+    sleep(Duration::from_millis(50));
+}
+
+fn panic_later() -> u32 {
+    sleep(Duration::from_millis(25));
+    // If the task wasn't canceled, this would kill the linked parent.
+    panic!("not canceled");
+}
+
+#[test]
+fn join_all() {
+    let tasks = (1..=4)
+        .map(|n| {
+            spawn_link!(@task |n| {
+                // Later tasks finish first.
+                sleep(Duration::from_millis(40 - n * 10));
+                n * 10
+            })
+        })
+        .collect();
+    assert_eq!(task::join_all(tasks), vec![10, 20, 30, 40]);
+}
+
+#[test]
+fn race() {
+    let slow = spawn_link!(@task || panic_later());
+    let fast = spawn_link!(@task || 7);
+    assert_eq!(task::race(vec![slow, fast]), 7);
+    // The slow task was canceled and doesn't kill the caller.
+    sleep(Duration::from_millis(50));
+}
@@ -0,0 +1,77 @@
+use std::{
+    io::{Read, Write},
+    time::Duration,
+};
+
+use lunatic::{net, spawn_link, Mailbox, Process};
+use lunatic_test::test;
+
+#[test]
+fn tcp_ping_pong() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = spawn_link!(@task |addr| {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"PING").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    });
+
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut buf = [0; 4];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"PING");
+    stream.write_all(b"PONG").unwrap();
+    assert_eq!(&client.result(), b"PONG");
+}
+
+#[test]
+fn tcp_read_timeout() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let mut stream = net::TcpStream::connect(addr).unwrap();
+    assert_eq!(stream.read_timeout().unwrap(), None);
+    stream
+        .set_read_timeout(Some(Duration::from_millis(10)))
+        .unwrap();
+    assert_eq!(
+        stream.read_timeout().unwrap(),
+        Some(Duration::from_millis(10))
+    );
+
+    let (_server_stream, _) = listener.accept().unwrap();
+    let mut buf = [0; 4];
+    assert!(stream.read(&mut buf).is_err());
+}
+
+#[test]
+fn tcp_split() {
+    let listener = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let client = spawn_link!(@task |addr| {
+        let mut stream = net::TcpStream::connect(addr).unwrap();
+        stream.write_all(b"PING").unwrap();
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).unwrap();
+        buf
+    });
+
+    // One process reads from the connection and forwards to the writer process.
+    let (stream, _) = listener.accept().unwrap();
+    let (reader, writer) = stream.split();
+    let writer = Process::spawn_link(writer, |mut writer, mailbox: Mailbox<[u8; 4]>| {
+        let buf = mailbox.receive();
+        assert_eq!(&buf, b"PING");
+        writer.write_all(b"PONG").unwrap();
+    });
+    Process::spawn_link((reader, writer), |(mut reader, writer), _: Mailbox<()>| {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf).unwrap();
+        writer.send(buf);
+    });
+    assert_eq!(&client.result(), b"PONG");
+}
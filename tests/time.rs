@@ -0,0 +1,32 @@
+use std::time::{Duration, SystemTime};
+
+use lunatic::{
+    sleep, test,
+    time::{self, Deadline},
+};
+
+#[test]
+fn clocks() {
+    assert!(time::now() > SystemTime::UNIX_EPOCH);
+
+    let start = time::instant();
+    sleep(Duration::from_millis(20));
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(time::instant() >= start + elapsed);
+    // Durations between instants saturate at zero.
+    assert_eq!(start - time::instant(), Duration::ZERO);
+}
+
+#[test]
+fn deadline() {
+    let deadline = Deadline::after(Duration::from_millis(20));
+    assert!(!deadline.is_expired());
+    assert!(deadline.remaining() <= Duration::from_millis(20));
+    sleep(Duration::from_millis(30));
+    assert!(deadline.is_expired());
+    assert_eq!(deadline.remaining(), Duration::ZERO);
+
+    // Too big timeouts never expire.
+    assert!(!Deadline::after(Duration::MAX).is_expired());
+}
@@ -1,6 +1,9 @@
 use std::time::Duration;
 
-use lunatic::process::{AbstractProcess, Message, MessageHandler, ProcessRef, StartProcess};
+use lunatic::{
+    process::{AbstractProcess, Message, MessageHandler, ProcessRef, StartProcess},
+    Mailbox,
+};
 use lunatic_test::test;
 
 struct P;
@@ -46,3 +49,26 @@ fn cancel_send_after() {
     // give enough time for the message to be sent if it wasn't canceled
     lunatic::sleep(Duration::from_millis(25));
 }
+
+#[test]
+fn send_interval(mailbox: Mailbox<u32>) {
+    let interval = mailbox.this().send_interval(7, Duration::from_millis(5));
+    for _ in 0..3 {
+        assert_eq!(mailbox.receive(), 7);
+    }
+    interval.cancel();
+
+    // Drain a message that could have been on the way while canceling.
+    let _ = mailbox.receive_timeout(Duration::from_millis(10));
+    assert!(mailbox.receive_timeout(Duration::from_millis(25)).is_err());
+}
+
+#[test]
+#[should_panic]
+fn process_ref_send_interval() {
+    let process = P::start_link((), None);
+    let _interval = process.send_interval((), Duration::from_millis(5));
+
+    // give enough time to fail
+    lunatic::sleep(Duration::from_millis(25));
+}
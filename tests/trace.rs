@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use lunatic::{
+    host,
+    process::{AbstractProcess, ProcessRef, Request, RequestHandler, StartProcess},
+    serializer::{Bincode, SerializerId, Tagged},
+    sleep, test,
+    trace::{self, SpanRecord, TraceContext},
+    Mailbox, Process, Tag,
+};
+
+const CONTEXT: TraceContext = TraceContext {
+    trace_id: 1,
+    span_id: 2,
+};
+
+#[test]
+fn context_is_propagated(mailbox: Mailbox<Option<TraceContext>>) {
+    let echo = Process::spawn((), |_, mailbox: Mailbox<Process<Option<TraceContext>>>| {
+        loop {
+            let parent = mailbox.receive();
+            parent.send(trace::current());
+        }
+    });
+
+    // Without a span, no context is attached.
+    assert_eq!(trace::current(), None);
+    echo.send(mailbox.this());
+    assert_eq!(mailbox.receive(), None);
+
+    let span = trace::span("request");
+    echo.send(mailbox.this());
+    assert_eq!(mailbox.receive(), Some(span.context()));
+    assert_eq!(trace::current(), Some(span.context()));
+    drop(span);
+    assert_eq!(trace::current(), None);
+}
+
+#[test]
+fn spans_are_exported(mailbox: Mailbox<SpanRecord>) {
+    trace::set_exporter(&mailbox.this());
+    let worker = Process::spawn((), |_, mailbox: Mailbox<u64>| {
+        mailbox.receive();
+        let _span = trace::span("work");
+    });
+
+    let span = trace::span("request");
+    worker.send(42);
+    drop(span);
+
+    let mut records = [mailbox.receive(), mailbox.receive()];
+    records.sort_by_key(|record| record.name.clone());
+    let (request, work) = (&records[0], &records[1]);
+    assert_eq!(request.name, "request");
+    assert_eq!(request.parent_span_id, None);
+    assert_eq!(work.name, "work");
+    assert_eq!(work.trace_id, request.trace_id);
+    assert_eq!(work.parent_span_id, Some(request.span_id));
+}
+
+#[test]
+fn untraced_message_keeps_context(mailbox: Mailbox<u64>) {
+    // The child is spawned without a context.
+    Process::spawn(mailbox.this(), |parent, _: Mailbox<()>| {
+        sleep(Duration::from_millis(50));
+        parent.send(1);
+    });
+    trace::set_current(Some(CONTEXT));
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(trace::current(), Some(CONTEXT));
+}
+
+#[test]
+fn deferred_message_keeps_context(mailbox: Mailbox<u64>) {
+    let this = mailbox.this();
+    trace::set_current(Some(CONTEXT));
+    this.send(1);
+    trace::set_current(None);
+    this.send(2);
+
+    assert_eq!(mailbox.receive_where(|message| *message == 2), 2);
+    assert_eq!(trace::current(), None);
+    assert_eq!(mailbox.receive(), 1);
+    assert_eq!(trace::current(), Some(CONTEXT));
+}
+
+#[test]
+fn tagged_message_has_no_context(mailbox: Mailbox<u8>) {
+    let tag = Tag::new();
+    let child = Process::spawn((mailbox.this(), tag), |(parent, tag), _: Mailbox<()>| {
+        // Read the first byte of the message as it was sent.
+        host::receive(&[tag.id()], u64::MAX);
+        let mut first = 0;
+        unsafe {
+            host::api::message::seek_data(0);
+            host::api::message::read_data(&mut first, 1);
+        }
+        parent.send(first);
+    });
+    trace::set_current(Some(CONTEXT));
+    let child = Process::<u64, Tagged<Bincode>>::new(child.node_id(), child.id());
+    child.tag_send(tag, 42);
+    assert_eq!(mailbox.receive(), Bincode::SERIALIZER_ID);
+}
+
+#[test]
+fn untraced_request_after_traced_one() {
+    struct Server;
+
+    impl AbstractProcess for Server {
+        type Arg = ();
+        type State = Server;
+
+        fn init(_: ProcessRef<Self>, _: ()) -> Server {
+            Server
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct Context;
+    impl RequestHandler<Context> for Server {
+        type Response = Option<TraceContext>;
+
+        fn handle(_: &mut Self::State, _: Context) -> Option<TraceContext> {
+            trace::current()
+        }
+    }
+
+    let server = Server::start_link((), None);
+    trace::set_current(Some(CONTEXT));
+    assert_eq!(server.request(Context), Some(CONTEXT));
+
+    // The context of the traced request isn't kept by the server.
+    trace::set_current(None);
+    assert_eq!(server.request(Context), None);
+    // The reply doesn't carry it either.
+    assert_eq!(trace::current(), None);
+}
@@ -1,3 +1,5 @@
+use std::{io::ErrorKind, net::Ipv4Addr};
+
 use lunatic::net;
 use lunatic_test::test;
 
@@ -105,3 +107,14 @@ fn udp_broadcast_setter_getter_false() {
 
     assert_eq!(cur_broadcast, false);
 }
+
+#[test]
+fn udp_multicast_unsupported() {
+    let socket = net::UdpSocket::bind("0.0.0.0:0").unwrap();
+    let group = Ipv4Addr::new(224, 0, 0, 123);
+    let error = socket
+        .join_multicast_v4(&group, &Ipv4Addr::UNSPECIFIED)
+        .unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}